@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+
+use errors::{bail, Result};
+
+/// What to do with the metadata (EXIF and ICC colour profile) of the source image
+/// when writing a processed image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageMetadata {
+    /// Do not copy any metadata to the processed image
+    #[default]
+    Strip,
+    /// Only copy the ICC colour profile
+    ColorProfile,
+    /// Copy both the ICC colour profile and the EXIF data
+    Keep,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageProcessing {
+    /// Default JPEG quality, from 1 to 100. `75` by default.
+    pub jpeg_quality: u8,
+    /// Default AVIF quality, from 1 to 100. `70` by default.
+    pub avif_quality: u8,
+    /// AVIF encoding speed, from 1 (slowest, smallest files) to 10 (fastest). `10` by default.
+    pub avif_speed: u8,
+    /// Default WebP quality, from 1 to 100. Lossless encoding is used when not set.
+    pub webp_quality: Option<u8>,
+    /// WebP compression method, from 0 (fastest) to 6 (slowest, smallest files). `4` by default.
+    pub webp_method: u8,
+    /// Which metadata of the source image to keep in the JPEG images. `strip` by default.
+    pub metadata: ImageMetadata,
+    /// Whether to keep the GPS location when the EXIF data is kept. `false` by default.
+    pub keep_gps: bool,
+}
+
+impl Default for ImageProcessing {
+    fn default() -> Self {
+        ImageProcessing {
+            jpeg_quality: 75,
+            avif_quality: 70,
+            avif_speed: 10,
+            webp_quality: None,
+            webp_method: 4,
+            metadata: ImageMetadata::default(),
+            keep_gps: false,
+        }
+    }
+}
+
+impl ImageProcessing {
+    pub fn validate(&self) -> Result<()> {
+        let qualities = [
+            ("jpeg_quality", Some(self.jpeg_quality)),
+            ("avif_quality", Some(self.avif_quality)),
+            ("webp_quality", self.webp_quality),
+        ];
+        for (name, quality) in qualities {
+            if let Some(q) = quality {
+                if q == 0 || q > 100 {
+                    bail!("`image_processing.{}` must be within the range [1; 100]", name);
+                }
+            }
+        }
+        if self.avif_speed == 0 || self.avif_speed > 10 {
+            bail!("`image_processing.avif_speed` must be within the range [1; 10]");
+        }
+        if self.webp_method > 6 {
+            bail!("`image_processing.webp_method` must be within the range [0; 6]");
+        }
+        Ok(())
+    }
+}
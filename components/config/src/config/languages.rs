@@ -5,6 +5,7 @@ use libs::unic_langid::LanguageIdentifier;
 use serde::{Deserialize, Serialize};
 
 use crate::config::search;
+use crate::config::slugify;
 use crate::config::taxonomies;
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -28,6 +29,13 @@ pub struct LanguageOptions {
     /// another `String` representing its translation.
     /// Use `get_translation()` method for translating key into different languages.
     pub translations: HashMap<String, String>,
+    /// The first component of the URLs of that language, without slashes. Defaults to the
+    /// language code, or to nothing for the default language. An empty string puts the language
+    /// at the root of the site.
+    pub url_prefix: Option<String>,
+    /// How to slugify paths, taxonomies and anchors in that language.
+    /// Defaults to the `[slugify]` section of the config.
+    pub slugify: Option<slugify::Slugify>,
 }
 
 impl LanguageOptions {
@@ -65,6 +73,8 @@ impl LanguageOptions {
         }
         merge_field!(self.title, other.title, "title");
         merge_field!(self.description, other.description, "description");
+        merge_field!(self.url_prefix, other.url_prefix, "url_prefix");
+        merge_field!(self.slugify, other.slugify, "slugify");
         merge_field!(
             self.feed_filenames.is_empty()
                 || self.feed_filenames == LanguageOptions::default().feed_filenames,
@@ -108,10 +118,25 @@ impl Default for LanguageOptions {
             build_search_index: false,
             search: search::Search::default(),
             translations: HashMap::new(),
+            url_prefix: None,
+            slugify: None,
         }
     }
 }
 
+/// URL prefixes are a single path component
+pub fn validate_url_prefix(code: &str, prefix: &str) -> Result<()> {
+    if prefix.contains(['/', '\\', '#', '?']) || prefix == "." || prefix == ".." {
+        bail!(
+            "The `url_prefix` of language `{}` must be a single path component, got `{}`",
+            code,
+            prefix
+        )
+    }
+
+    Ok(())
+}
+
 /// We want to ensure the language codes are valid ones
 pub fn validate_code(code: &str) -> Result<()> {
     if LanguageIdentifier::from_bytes(code.as_bytes()).is_err() {
@@ -136,6 +161,8 @@ mod tests {
             build_search_index: true,
             search: search::Search::default(),
             translations: HashMap::new(),
+            url_prefix: None,
+            slugify: None,
         };
 
         let section_default_language_options = LanguageOptions {
@@ -147,6 +174,8 @@ mod tests {
             build_search_index: true,
             search: search::Search::default(),
             translations: HashMap::new(),
+            url_prefix: None,
+            slugify: None,
         };
 
         base_default_language_options.merge(&section_default_language_options).unwrap();
@@ -163,6 +192,8 @@ mod tests {
             build_search_index: true,
             search: search::Search::default(),
             translations: HashMap::new(),
+            url_prefix: None,
+            slugify: None,
         };
 
         let section_default_language_options = LanguageOptions {
@@ -174,6 +205,8 @@ mod tests {
             build_search_index: true,
             search: search::Search::default(),
             translations: HashMap::new(),
+            url_prefix: None,
+            slugify: None,
         };
 
         let res =
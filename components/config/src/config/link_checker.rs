@@ -1,9 +1,14 @@
+use std::collections::HashMap;
+
 use libs::globset::GlobSet;
+use libs::reqwest::header::{HeaderName, HeaderValue};
 use serde::{Deserialize, Serialize};
 
-use errors::Result;
+use errors::{bail, Result};
 use utils::globs::build_ignore_glob_set;
 
+use crate::config::markup::BoolWithPath;
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LinkCheckerLevel {
     #[serde(rename = "error")]
@@ -18,7 +23,18 @@ impl Default for LinkCheckerLevel {
     }
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+/// Options for the external links starting with a given prefix
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrefixOptions {
+    pub prefix: String,
+    /// Headers sent with the requests to these links, eg a `Cookie` or an `Authorization`
+    pub headers: HashMap<String, String>,
+    /// Status codes that are not errors for these links, eg `403` for sites blocking bots
+    pub accepted_status: Vec<u16>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct LinkChecker {
     /// Skip link checking for these URL prefixes
@@ -33,9 +49,93 @@ pub struct LinkChecker {
     pub ignored_files: Vec<String>,
     #[serde(skip_serializing, skip_deserializing)] // not a typo, 2 are needed
     pub ignored_files_globset: Option<GlobSet>,
+    /// Whether to keep the results of the external link checks on disk to reuse them in the
+    /// next runs. If set to a path, the cache is stored there (relative to the site root)
+    /// instead of the user cache directory. Defaults to false.
+    pub cache: BoolWithPath,
+    /// How long a successful check is reused, in seconds. `604800` (a week) by default.
+    pub cache_ttl: u64,
+    /// How long a failed check is reused, in seconds. `3600` (an hour) by default.
+    pub cache_failure_ttl: u64,
+    /// How many requests can be made to the same domain at the same time. `1` by default.
+    pub per_domain_concurrency: usize,
+    /// The minimum time between the start of two requests to the same domain, in milliseconds.
+    /// `0` (no throttling) by default.
+    pub request_interval: u64,
+    /// How many times a request is retried after a timeout, a `429 Too Many Requests` or a
+    /// `502`, `503` or `504` response. `2` by default.
+    pub retries: u32,
+    /// How long to wait before the first retry when the server doesn't send a `Retry-After`
+    /// header, in milliseconds. It doubles on every retry. `1000` by default.
+    pub retry_backoff: u64,
+    /// The longest `Retry-After` that is waited for, in seconds: the link is reported as broken
+    /// if the server asks to wait longer. `60` by default.
+    pub max_retry_after: u64,
+    /// Whether to warn about the links permanently redirecting (`301` or `308`) to another URL,
+    /// so they can be updated. Defaults to false.
+    pub report_permanent_redirects: bool,
+    /// Headers and accepted status codes for the links starting with some prefixes.
+    /// Every entry whose prefix matches a link applies to it.
+    pub prefix_options: Vec<PrefixOptions>,
+}
+
+impl Default for LinkChecker {
+    fn default() -> Self {
+        LinkChecker {
+            skip_prefixes: Vec::new(),
+            skip_anchor_prefixes: Vec::new(),
+            internal_level: LinkCheckerLevel::default(),
+            external_level: LinkCheckerLevel::default(),
+            ignored_files: Vec::new(),
+            ignored_files_globset: None,
+            cache: BoolWithPath::False,
+            cache_ttl: 604800,
+            cache_failure_ttl: 3600,
+            per_domain_concurrency: 1,
+            request_interval: 0,
+            retries: 2,
+            retry_backoff: 1000,
+            max_retry_after: 60,
+            report_permanent_redirects: false,
+            prefix_options: Vec::new(),
+        }
+    }
 }
 
 impl LinkChecker {
+    /// The options of all the prefixes the link starts with
+    pub fn options_for<'a>(&'a self, link: &'a str) -> impl Iterator<Item = &'a PrefixOptions> {
+        self.prefix_options.iter().filter(move |o| link.starts_with(&o.prefix))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.per_domain_concurrency == 0 {
+            bail!("`link_checker.per_domain_concurrency` must be at least 1");
+        }
+        for options in &self.prefix_options {
+            if options.prefix.is_empty() {
+                bail!("`link_checker.prefix_options`: `prefix` can't be empty");
+            }
+            for (name, value) in &options.headers {
+                if HeaderName::from_bytes(name.as_bytes()).is_err()
+                    || HeaderValue::from_str(value).is_err()
+                {
+                    bail!(
+                        "`link_checker.prefix_options`: invalid header `{}` for the prefix `{}`",
+                        name,
+                        options.prefix
+                    );
+                }
+            }
+            for status in &options.accepted_status {
+                if !(100..=599).contains(status) {
+                    bail!("`link_checker.prefix_options`: `{}` is not an HTTP status code", status);
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn resolve_globset(&mut self) -> Result<()> {
         let glob_set = build_ignore_glob_set(&self.ignored_files, "files")?;
         self.ignored_files_globset = Some(glob_set);
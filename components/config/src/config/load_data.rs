@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::markup::BoolWithPath;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoadData {
+    /// Whether to keep the responses of remote `load_data` requests on disk.
+    /// If set to a path, the cache is stored there (relative to the site root) instead of the
+    /// user cache directory, which allows committing it to pin the data used in CI.
+    pub cache: BoolWithPath,
+    /// How long a cached response is used without asking the server again, in seconds.
+    /// `86400` (a day) by default.
+    pub cache_ttl: u64,
+    /// Only use the cached responses and never make a request. Set by `--offline`.
+    #[serde(skip)]
+    pub offline: bool,
+}
+
+impl Default for LoadData {
+    fn default() -> Self {
+        LoadData { cache: BoolWithPath::False, cache_ttl: 86400, offline: false }
+    }
+}
@@ -1,4 +1,9 @@
-use std::{fmt, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use libs::syntect::{
     highlighting::{Theme, ThemeSet},
@@ -13,6 +18,9 @@ use utils::types::InsertAnchor;
 use crate::highlighting::{CLASS_STYLE, THEME_SET};
 
 pub const DEFAULT_HIGHLIGHT_THEME: &str = "base16-ocean-dark";
+/// The callout kinds available without any configuration
+pub const BUILTIN_CALLOUT_KINDS: [&str; 7] =
+    ["note", "tip", "warning", "important", "info", "question", "error"];
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(default)]
@@ -143,11 +151,124 @@ impl<'de> Deserialize<'de> for MathRenderer {
     }
 }
 
+/// Which headings end up in the table of contents and whether they are numbered
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableOfContents {
+    /// The lowest heading level (1 for `<h1>`) included in the table of contents. Defaults to 1
+    pub min_level: u32,
+    /// The highest heading level included in the table of contents. Defaults to 6
+    pub max_level: u32,
+    /// Whether to prefix the headings of the table of contents and of the content with their
+    /// number, eg `2.3.1`. Defaults to false
+    pub numbering: bool,
+}
+
+impl TableOfContents {
+    pub fn validate(&self) -> Result<()> {
+        if self.min_level < 1 || self.max_level > 6 || self.min_level > self.max_level {
+            bail!(
+                "The table of contents levels must be between 1 and 6 with `min_level` <= `max_level`, got `min_level = {}` and `max_level = {}`",
+                self.min_level,
+                self.max_level
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether a heading of that level is part of the table of contents
+    pub fn includes(&self, level: u32) -> bool {
+        level >= self.min_level && level <= self.max_level
+    }
+}
+
+impl Default for TableOfContents {
+    fn default() -> Self {
+        TableOfContents { min_level: 1, max_level: 6, numbering: false }
+    }
+}
+
+/// How the code blocks of diagram languages are rendered to SVG at build time
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Diagrams {
+    /// Whether to render `dot` and `graphviz` code blocks with the built-in Graphviz layout.
+    /// Defaults to false
+    pub graphviz: bool,
+    /// The commands rendering the code blocks of a language, eg `mermaid`. They get the
+    /// diagram source on their standard input and write the SVG on their standard output
+    pub commands: HashMap<String, String>,
+}
+
+impl Diagrams {
+    pub fn validate(&self) -> Result<()> {
+        for (language, command) in &self.commands {
+            if command.split_whitespace().next().is_none() {
+                bail!("The command to render `{}` diagrams is empty", language);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which library highlights the code blocks
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HighlighterBackend {
+    /// Sublime Text syntaxes, built-in or from `extra_syntaxes_and_themes`
+    #[default]
+    Syntect,
+    /// The bundled tree-sitter grammars, falling back to syntect for the other languages
+    TreeSitter,
+}
+
+/// What an external markdown filter reads and writes
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkdownFilterFormat {
+    /// The HTML of the page
+    #[default]
+    Html,
+    /// The markdown events as JSON
+    Events,
+}
+
+/// An external command transforming the rendered markdown of every page and section
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownFilter {
+    /// The command to run from the root of the site, its arguments being split with shell quoting
+    pub command: String,
+    /// Whether the filter works on the HTML or on the events. Defaults to HTML
+    pub format: MarkdownFilterFormat,
+    /// Whether the output can be cached for a given input. Defaults to true
+    pub cache: bool,
+}
+
+impl Default for MarkdownFilter {
+    fn default() -> Self {
+        MarkdownFilter { command: String::new(), format: MarkdownFilterFormat::Html, cache: true }
+    }
+}
+
+/// A callout kind, used with `> [!kind]` in block quotes. The built-in kinds can be
+/// configured as well, eg to give them aliases
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalloutKind {
+    /// Other names that can be used for that kind, eg `caution` for `warning`
+    pub aliases: Vec<String>,
+    /// The title of the callouts not setting one. Defaults to the name of the kind
+    pub title: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Markdown {
     /// Whether to highlight all code blocks found in markdown files. Defaults to false
     pub highlight_code: bool,
+    /// Which highlighter to use, `syntect` or `tree-sitter`. Defaults to syntect
+    pub highlighter: HighlighterBackend,
     /// Emit an error for missing highlight languages. Defaults to false
     pub error_on_missing_highlight: bool,
     /// Which themes to use for code highlighting. See Readme for supported themes
@@ -189,9 +310,35 @@ pub struct Markdown {
     pub math: MathRenderer,
     /// Whether to cache the rendered math
     pub cache: BoolWithPath,
+    /// Which headings are in the table of contents and whether they are numbered
+    pub toc: TableOfContents,
+    /// Whether to also allow calling shortcodes with the `:::name{key=value}` and
+    /// `::name[text]{key=value}` directive syntax. Defaults to false
+    pub directives: bool,
+    /// Callout kinds on top of the built-in ones, with their aliases and default title
+    pub callouts: HashMap<String, CalloutKind>,
+    /// Which code blocks are rendered as diagrams
+    pub diagrams: Diagrams,
+    /// External commands applied in order to the rendered markdown
+    pub filters: Vec<MarkdownFilter>,
+    /// Directories, relative to the site root, where the files of the `include` code block
+    /// option are looked for after the page directory and the site root
+    pub include_roots: Vec<String>,
+    /// The site root followed by the `include_roots`, set when loading the config file
+    #[serde(skip_serializing, skip_deserializing)] // not a typo, 2 are need
+    pub include_dirs: Vec<PathBuf>,
 }
 
 impl Markdown {
+    pub fn validate_highlighter(&self) -> Result<()> {
+        if self.highlighter == HighlighterBackend::TreeSitter && !cfg!(feature = "tree-sitter") {
+            bail!(
+                "`highlighter = \"tree-sitter\"` requires Zola to be built with the `tree-sitter` feature: `cargo build --release --features tree-sitter`"
+            );
+        }
+        Ok(())
+    }
+
     pub fn validate_external_links_class(&self) -> Result<()> {
         // Validate external link class doesn't contain quotes which would break HTML and aren't valid in CSS
         if let Some(class) = &self.external_links_class {
@@ -202,6 +349,62 @@ impl Markdown {
         Ok(())
     }
 
+    pub fn validate_callouts(&self) -> Result<()> {
+        let is_valid_name = |name: &str| {
+            !name.is_empty()
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        let mut names: HashMap<String, &str> = HashMap::new();
+        for (kind, callout) in &self.callouts {
+            for name in std::iter::once(kind).chain(callout.aliases.iter()) {
+                if !is_valid_name(name) {
+                    bail!(
+                        "Callout name `{}` is invalid, only ASCII letters, digits, `-` and `_` are allowed",
+                        name
+                    );
+                }
+                if let Some(other) = names.insert(name.to_lowercase(), kind) {
+                    bail!("Callout name `{}` is used by both `{}` and `{}`", name, other, kind);
+                }
+            }
+        }
+        for builtin in BUILTIN_CALLOUT_KINDS {
+            if let Some(kind) =
+                names.get(builtin).filter(|kind| !kind.eq_ignore_ascii_case(builtin))
+            {
+                bail!(
+                    "Callout `{}` cannot use `{}` as alias, it is a built-in kind",
+                    kind,
+                    builtin
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate_filters(&self) -> Result<()> {
+        if self.filters.iter().any(|filter| filter.command.trim().is_empty()) {
+            bail!("Every `[[markdown.filters]]` needs a `command`");
+        }
+        Ok(())
+    }
+
+    /// Finds the kind of callout from the name used in `> [!name]`, ignoring the case.
+    /// Returns the kind and its configured title, or None if it's not a known kind or alias
+    pub fn get_callout_kind(&self, name: &str) -> Option<(&str, Option<&str>)> {
+        for (kind, callout) in &self.callouts {
+            if kind.eq_ignore_ascii_case(name)
+                || callout.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+            {
+                return Some((kind.as_str(), callout.title.as_deref()));
+            }
+        }
+        BUILTIN_CALLOUT_KINDS
+            .iter()
+            .find(|kind| kind.eq_ignore_ascii_case(name))
+            .map(|kind| (*kind, None))
+    }
+
     /// Gets the configured highlight theme from the THEME_SET or the config's extra_theme_set
     /// Returns None if the configured highlighting theme is set to use css
     pub fn get_highlight_theme(&self) -> Option<&Theme> {
@@ -252,6 +455,19 @@ impl Markdown {
         }
     }
 
+    pub fn init_include_dirs(&mut self, path: &Path) -> Result<()> {
+        let mut dirs = vec![path.to_path_buf()];
+        for root in &self.include_roots {
+            let dir = path.join(root);
+            if !dir.is_dir() {
+                bail!("The include root `{}` is not a directory", root);
+            }
+            dirs.push(dir);
+        }
+        self.include_dirs = dirs;
+        Ok(())
+    }
+
     pub fn init_extra_syntaxes_and_highlight_themes(&mut self, path: &Path) -> Result<()> {
         let (loaded_extra_syntaxes, loaded_extra_highlight_themes) =
             self.load_extra_syntaxes_and_highlight_themes(path)?;
@@ -348,6 +564,7 @@ impl Default for Markdown {
     fn default() -> Markdown {
         Markdown {
             highlight_code: false,
+            highlighter: HighlighterBackend::Syntect,
             error_on_missing_highlight: false,
             highlight_theme: DEFAULT_HIGHLIGHT_THEME.to_owned(),
             highlight_themes_css: Vec::new(),
@@ -366,6 +583,13 @@ impl Default for Markdown {
             insert_anchor_links: InsertAnchor::None,
             math: MathRenderer::default(),
             cache: BoolWithPath::True(None),
+            toc: TableOfContents::default(),
+            directives: false,
+            callouts: HashMap::new(),
+            diagrams: Diagrams::default(),
+            filters: Vec::new(),
+            include_roots: Vec::new(),
+            include_dirs: Vec::new(),
         }
     }
 }
@@ -1,5 +1,7 @@
+pub mod image_processing;
 pub mod languages;
 pub mod link_checker;
+pub mod load_data;
 pub mod markup;
 pub mod search;
 pub mod slugify;
@@ -53,6 +55,9 @@ pub struct Config {
     pub default_language: String,
     /// The list of supported languages outside of the default one
     pub languages: HashMap<String, languages::LanguageOptions>,
+    /// The language whose version of the content is the `x-default` alternate, for visitors
+    /// whose language is not available. Defaults to None
+    pub hreflang_x_default: Option<String>,
     /// The translations strings for the default language
     translations: HashMap<String, String>,
 
@@ -103,6 +108,10 @@ pub struct Config {
     pub search: search::Search,
     /// The config for the Markdown rendering: syntax highlighting and everything
     pub markdown: markup::Markdown,
+    /// The encoder settings used when processing images
+    pub image_processing: image_processing::ImageProcessing,
+    /// The on-disk cache of the remote `load_data` requests
+    pub load_data: load_data::LoadData,
     /// All user params set in `[extra]` in the config
     pub extra: HashMap<String, Toml>,
     /// Enables the generation of Sitemap.xml
@@ -155,8 +164,24 @@ impl Config {
         }
 
         config.add_default_language()?;
+        config.validate_url_prefixes()?;
+        if let Some(ref lang) = config.hreflang_x_default {
+            if !config.languages.contains_key(lang) {
+                bail!("`hreflang_x_default` is set to `{}` which is not one of the languages of the site", lang);
+            }
+        }
         config.slugify_taxonomies();
         config.link_checker.resolve_globset()?;
+        config.link_checker.validate()?;
+        config.image_processing.validate()?;
+        config.markdown.toc.validate()?;
+        config.markdown.validate_callouts()?;
+        config.markdown.diagrams.validate()?;
+        config.markdown.validate_filters()?;
+        config.markdown.validate_highlighter()?;
+        for lang_options in config.languages.values() {
+            lang_options.search.validate()?;
+        }
 
         let content_glob_set = build_ignore_glob_set(&config.ignored_content, "content")?;
         config.ignored_content_globset = Some(content_glob_set);
@@ -186,6 +211,7 @@ impl Config {
 
         // this is the step at which missing extra syntax and highlighting themes are raised as errors
         config.markdown.init_extra_syntaxes_and_highlight_themes(config_dir)?;
+        config.markdown.init_include_dirs(config_dir)?;
         config.markdown.validate_external_links_class()?;
 
         Ok(config)
@@ -193,10 +219,44 @@ impl Config {
 
     pub fn slugify_taxonomies(&mut self) {
         for (_, lang_options) in self.languages.iter_mut() {
+            let strategy = lang_options.slugify.as_ref().unwrap_or(&self.slugify).taxonomies;
             for tax_def in lang_options.taxonomies.iter_mut() {
-                tax_def.slug = slugify_paths(&tax_def.name, self.slugify.taxonomies);
+                tax_def.slug = slugify_paths(&tax_def.name, strategy);
+            }
+        }
+    }
+
+    /// Ensures two languages do not end up with the same URLs
+    fn validate_url_prefixes(&self) -> Result<()> {
+        let mut prefixes: HashMap<&str, &str> = HashMap::new();
+        for code in self.languages.keys() {
+            let prefix = self.url_prefix(code);
+            languages::validate_url_prefix(code, prefix)?;
+            if let Some(other) = prefixes.insert(prefix, code) {
+                bail!(
+                    "Languages `{}` and `{}` have the same URL prefix `{}`, set a different `url_prefix` for one of them",
+                    other,
+                    code,
+                    prefix
+                );
             }
         }
+        Ok(())
+    }
+
+    /// Returns the first component of the URLs of the given language, without slashes.
+    /// It is empty if the content of that language is at the root of the site.
+    pub fn url_prefix<'a>(&'a self, lang: &'a str) -> &'a str {
+        match self.languages.get(lang).and_then(|l| l.url_prefix.as_deref()) {
+            Some(prefix) => prefix,
+            None if lang == self.default_language => "",
+            None => lang,
+        }
+    }
+
+    /// Returns the slugify strategies of the given language
+    pub fn slugify_for(&self, lang: &str) -> &slugify::Slugify {
+        self.languages.get(lang).and_then(|l| l.slugify.as_ref()).unwrap_or(&self.slugify)
     }
 
     /// Makes a url, taking into account that the base url might have a trailing slash
@@ -239,13 +299,15 @@ impl Config {
             taxonomies: self.taxonomies.clone(),
             search: self.search.clone(),
             translations: self.translations.clone(),
+            url_prefix: None,
+            slugify: None,
         };
 
         if let Some(section_language_options) = self.languages.get(&self.default_language) {
             if base_language_options == languages::LanguageOptions::default() {
                 return Ok(());
             }
-            println!("Warning: config.toml contains both default language specific information at base and under section `[languages.{}]`, \
+            eprintln!("Warning: config.toml contains both default language specific information at base and under section `[languages.{}]`, \
                 which may cause merge conflicts. Please use only one to specify language specific information", self.default_language);
             base_language_options.merge(section_language_options)?;
         }
@@ -397,6 +459,7 @@ impl Default for Config {
             description: None,
             theme: None,
             default_language: "en".to_string(),
+            hreflang_x_default: None,
             languages: HashMap::new(),
             generate_feeds: false,
             feed_limit: None,
@@ -419,6 +482,8 @@ impl Default for Config {
             slugify: slugify::Slugify::default(),
             search: search::Search::default(),
             markdown: markup::Markdown::default(),
+            image_processing: image_processing::ImageProcessing::default(),
+            load_data: load_data::LoadData::default(),
             extra: HashMap::new(),
             generate_sitemap: true,
             generate_robots_txt: true,
@@ -430,6 +495,7 @@ impl Default for Config {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::markup::{HighlighterBackend, MarkdownFilterFormat};
     use utils::slugs::SlugifyStrategy;
 
     #[test]
@@ -466,6 +532,8 @@ mod tests {
                 build_search_index: false,
                 search: search::Search::default(),
                 translations: config.translations.clone(),
+                url_prefix: None,
+                slugify: None,
             },
         );
         config.add_default_language().unwrap();
@@ -494,6 +562,8 @@ mod tests {
                 build_search_index: false,
                 search: search::Search::default(),
                 translations: config.translations.clone(),
+                url_prefix: None,
+                slugify: None,
             },
         );
         let result = config.add_default_language();
@@ -741,6 +811,83 @@ ignored_files = []
         assert_eq!(config.link_checker.ignored_files.len(), 0);
     }
 
+    #[test]
+    fn can_configure_link_checker_cache_and_rate_limits() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+[link_checker]
+cache = ".link-cache"
+cache_failure_ttl = 0
+per_domain_concurrency = 2
+retries = 0
+        "#;
+
+        let config = Config::parse(config_str).unwrap();
+        assert_eq!(
+            config.link_checker.cache,
+            markup::BoolWithPath::True(Some(".link-cache".to_string()))
+        );
+        assert_eq!(config.link_checker.cache_ttl, 604800);
+        assert_eq!(Config::default().link_checker.cache, markup::BoolWithPath::False);
+        assert_eq!(config.link_checker.cache_failure_ttl, 0);
+        assert_eq!(config.link_checker.per_domain_concurrency, 2);
+        assert_eq!(config.link_checker.request_interval, 0);
+        assert_eq!(config.link_checker.retries, 0);
+    }
+
+    #[test]
+    fn can_configure_link_checker_prefix_options() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+[link_checker]
+report_permanent_redirects = true
+
+[[link_checker.prefix_options]]
+prefix = "https://github.com/"
+headers = { Authorization = "Bearer 1234" }
+
+[[link_checker.prefix_options]]
+prefix = "https://github.com/private/"
+accepted_status = [403]
+        "#;
+
+        let config = Config::parse(config_str).unwrap();
+        assert!(config.link_checker.report_permanent_redirects);
+        let options: Vec<_> =
+            config.link_checker.options_for("https://github.com/private/repo").collect();
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].headers["Authorization"], "Bearer 1234");
+        assert_eq!(options[1].accepted_status, vec![403]);
+        assert_eq!(config.link_checker.options_for("https://github.com/zola").count(), 1);
+    }
+
+    #[test]
+    fn errors_on_invalid_accepted_status() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+[[link_checker.prefix_options]]
+prefix = "https://github.com/"
+accepted_status = [1000]
+        "#;
+
+        assert!(Config::parse(config_str).is_err());
+    }
+
+    #[test]
+    fn errors_on_zero_per_domain_concurrency() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+[link_checker]
+per_domain_concurrency = 0
+        "#;
+
+        assert!(Config::parse(config_str).is_err());
+    }
+
     #[test]
     fn non_empty_ignored_content_results_in_vector_of_patterns_and_configured_globset() {
         let config_str = r#"
@@ -907,6 +1054,91 @@ anchors = "safe"
         assert_eq!(config.slugify.anchors, SlugifyStrategy::Safe);
     }
 
+    #[test]
+    fn can_configure_url_prefix_and_slugify_per_language() {
+        let config_str = r#"
+base_url = "example.com"
+default_language = "en"
+taxonomies = [{ name = "Mes Catégories" }]
+
+[slugify]
+paths = "on"
+
+[languages.en]
+url_prefix = "en"
+
+[languages.fr]
+taxonomies = [{ name = "Mes Catégories" }]
+slugify = { paths = "safe", taxonomies = "safe" }
+
+[languages.pt-BR]
+url_prefix = "br"
+        "#;
+
+        let config = Config::parse(config_str).unwrap();
+        assert_eq!(config.url_prefix("en"), "en");
+        assert_eq!(config.url_prefix("fr"), "fr");
+        assert_eq!(config.url_prefix("pt-BR"), "br");
+        assert_eq!(config.slugify_for("en").paths, SlugifyStrategy::On);
+        assert_eq!(config.slugify_for("fr").paths, SlugifyStrategy::Safe);
+        assert_eq!(config.languages["en"].taxonomies[0].slug, "mes-categories");
+        assert_eq!(config.languages["fr"].taxonomies[0].slug, "Mes Catégories");
+    }
+
+    #[test]
+    fn default_language_has_no_url_prefix() {
+        let config = Config::default_for_test();
+        assert_eq!(config.url_prefix(&config.default_language), "");
+    }
+
+    #[test]
+    fn errors_on_duplicate_url_prefixes() {
+        let config_str = r#"
+base_url = "example.com"
+default_language = "en"
+
+[languages.fr]
+url_prefix = ""
+        "#;
+
+        let err = Config::parse(config_str).unwrap_err();
+        assert!(err.to_string().contains("have the same URL prefix ``"));
+    }
+
+    #[test]
+    fn errors_on_url_prefix_with_slashes() {
+        let config_str = r#"
+base_url = "example.com"
+default_language = "en"
+
+[languages.fr]
+url_prefix = "fr/ca"
+        "#;
+
+        let err = Config::parse(config_str).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The `url_prefix` of language `fr` must be a single path component, got `fr/ca`"
+        );
+    }
+
+    #[test]
+    fn errors_on_unknown_hreflang_x_default() {
+        let config_str = r#"
+base_url = "example.com"
+default_language = "en"
+hreflang_x_default = "de"
+
+[languages.fr]
+        "#;
+
+        let err = Config::parse(config_str).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`hreflang_x_default` is set to `de` which is not one of the languages of the site"
+        );
+    }
+
     #[test]
     fn cannot_overwrite_theme_mapping_with_invalid_type() {
         let config_str = r#"
@@ -1005,6 +1237,115 @@ highlight_theme = "css"
         assert_eq!(serialised.markdown.highlight_theme, config.markdown.highlight_theme);
     }
 
+    #[test]
+    fn can_configure_table_of_contents() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+title = "Zola"
+[markdown.toc]
+min_level = 2
+max_level = 3
+numbering = true
+    "#;
+
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.markdown.toc.min_level, 2);
+        assert_eq!(config.markdown.toc.max_level, 3);
+        assert!(config.markdown.toc.numbering);
+        assert!(!config.markdown.toc.includes(1));
+        assert!(config.markdown.toc.includes(3));
+    }
+
+    #[test]
+    fn errors_on_invalid_table_of_contents_levels() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+title = "Zola"
+[markdown.toc]
+min_level = 4
+max_level = 2
+    "#;
+
+        let err = Config::parse(config).unwrap_err();
+        assert!(err.to_string().contains("`min_level` <= `max_level`"));
+    }
+
+    #[test]
+    fn can_configure_callouts() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+title = "Zola"
+[markdown.callouts.todo]
+aliases = ["task"]
+title = "To do"
+[markdown.callouts.warning]
+aliases = ["caution"]
+    "#;
+
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.markdown.get_callout_kind("TASK"), Some(("todo", Some("To do"))));
+        assert_eq!(config.markdown.get_callout_kind("caution"), Some(("warning", None)));
+        assert_eq!(config.markdown.get_callout_kind("Tip"), Some(("tip", None)));
+        assert_eq!(config.markdown.get_callout_kind("nope"), None);
+    }
+
+    #[test]
+    fn errors_on_conflicting_callout_names() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+title = "Zola"
+[markdown.callouts.todo]
+aliases = ["note"]
+    "#;
+
+        let err = Config::parse(config).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Callout `todo` cannot use `note` as alias, it is a built-in kind"));
+    }
+
+    #[test]
+    fn can_configure_markdown_filters() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+title = "Zola"
+[[markdown.filters]]
+command = "python3 filters/abbr.py"
+[[markdown.filters]]
+command = "./filters/wrap-tables"
+format = "events"
+cache = false
+    "#;
+
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.markdown.filters.len(), 2);
+        assert_eq!(config.markdown.filters[0].format, MarkdownFilterFormat::Html);
+        assert!(config.markdown.filters[0].cache);
+        assert_eq!(config.markdown.filters[1].format, MarkdownFilterFormat::Events);
+        assert!(!config.markdown.filters[1].cache);
+    }
+
+    #[test]
+    fn tree_sitter_highlighter_requires_the_feature() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+title = "Zola"
+[markdown]
+highlighter = "tree-sitter"
+    "#;
+
+        match Config::parse(config) {
+            Ok(config) => {
+                assert!(cfg!(feature = "tree-sitter"));
+                assert_eq!(config.markdown.highlighter, HighlighterBackend::TreeSitter);
+            }
+            Err(err) => {
+                assert!(!cfg!(feature = "tree-sitter"));
+                assert!(err.to_string().contains("built with the `tree-sitter` feature"));
+            }
+        }
+    }
+
     #[test]
     fn sets_default_author_if_present() {
         let config = r#"
@@ -1092,5 +1433,116 @@ base_url = "example.com"
         assert!(config.generate_robots_txt);
     }
 
+    #[test]
+    fn can_parse_image_processing_options() {
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[image_processing]
+avif_speed = 4
+webp_method = 6
+metadata = "color_profile"
+"#;
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.image_processing.avif_speed, 4);
+        assert_eq!(config.image_processing.webp_method, 6);
+        assert_eq!(config.image_processing.avif_quality, 70);
+        assert_eq!(config.image_processing.metadata, image_processing::ImageMetadata::ColorProfile);
+    }
+
+    #[test]
+    fn errors_on_invalid_image_processing_options() {
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[image_processing]
+avif_speed = 0
+"#;
+        let err = Config::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`image_processing.avif_speed` must be within the range [1; 10]"
+        );
+    }
+
+    #[test]
+    fn can_parse_load_data_cache() {
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[load_data]
+cache = "fixtures/load_data"
+cache_ttl = 3600
+"#;
+        let config = Config::parse(config).unwrap();
+        assert_eq!(
+            config.load_data.cache,
+            markup::BoolWithPath::True(Some("fixtures/load_data".to_string()))
+        );
+        assert_eq!(config.load_data.cache_ttl, 3600);
+        assert!(!config.load_data.offline);
+    }
+
+    #[test]
+    fn can_parse_chunked_search_index() {
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[search]
+index_format = "chunked"
+shard_prefix_length = 3
+"#;
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.search.index_format, search::IndexFormat::Chunked);
+        assert_eq!(config.search.shard_prefix_length, 3);
+        assert_eq!(config.search.fragment_size, 100);
+        assert_eq!(config.search.index_format.filename("fr"), "search_index/fr/meta.json");
+
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[search]
+fragment_size = 0
+"#;
+        let err = Config::parse(config).unwrap_err();
+        assert_eq!(err.to_string(), "`search.fragment_size` must be greater than 0");
+    }
+
+    #[test]
+    fn can_parse_search_extra_fields_and_boosts() {
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[search]
+extra_fields = ["author"]
+taxonomies = ["tags"]
+index_headings = true
+boosts = { title = 3, "extra.author" = 2, "taxonomies.tags" = 2 }
+"#;
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.search.extra_fields, vec!["author"]);
+        assert!(config.search.index_headings);
+        assert_eq!(config.search.boosts["extra.author"], 2);
+
+        let config = r#"
+title = "My Site"
+base_url = "example.com"
+
+[search]
+boosts = { "extra.author" = 2 }
+"#;
+        let err = Config::parse(config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`search.boosts` contains `extra.author` which is not a field of the search index"
+        );
+    }
+
     // TODO: add a test for excluding paginated pages
 }
@@ -1,5 +1,9 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
+use errors::{bail, Result};
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[derive(Default)]
@@ -9,6 +13,8 @@ pub enum IndexFormat {
     ElasticlunrJavascript,
     FuseJson,
     FuseJavascript,
+    /// The index is split in many small files loaded on demand by the client
+    Chunked,
 }
 
 impl IndexFormat {
@@ -16,13 +22,17 @@ impl IndexFormat {
     fn extension(&self) -> &'static str {
         match *self {
             IndexFormat::ElasticlunrJavascript | IndexFormat::FuseJavascript => "js",
-            IndexFormat::ElasticlunrJson | IndexFormat::FuseJson => "json",
+            IndexFormat::ElasticlunrJson | IndexFormat::FuseJson | IndexFormat::Chunked => "json",
         }
     }
 
-    /// the filename which ought to be used for this format and language `lang`
+    /// the filename which ought to be used for this format and language `lang`.
+    /// For the chunked format, this is the entry point of the index: the other files are next to it
     pub fn filename(&self, lang: &str) -> String {
-        format!("search_index.{}.{}", lang, self.extension())
+        match *self {
+            IndexFormat::Chunked => format!("search_index/{}/meta.{}", lang, self.extension()),
+            _ => format!("search_index.{}.{}", lang, self.extension()),
+        }
     }
 }
 
@@ -43,8 +53,23 @@ pub struct Search {
     pub include_date: bool,
     /// Include the path of the page in the search index. `false` by default.
     pub include_path: bool,
+    /// Names of the `extra` front matter fields to include in the search index. Empty by default.
+    pub extra_fields: Vec<String>,
+    /// Names of the taxonomies whose terms are included in the search index. Empty by default.
+    pub taxonomies: Vec<String>,
+    /// Adds a document for each heading of the pages and sections, pointing to its anchor.
+    /// `false` by default.
+    pub index_headings: bool,
+    /// Boost of each field when searching, e.g. `title = 2`. Not used by Fuse. Empty by default.
+    pub boosts: HashMap<String, u8>,
     /// Format of the search index to be produced. 'elasticlunr_javascript' by default.
     pub index_format: IndexFormat,
+    /// Only used by the `chunked` format: terms are grouped in shards by their first
+    /// `shard_prefix_length` characters. `2` by default.
+    pub shard_prefix_length: usize,
+    /// Only used by the `chunked` format: how many documents are stored in each fragment file.
+    /// `100` by default.
+    pub fragment_size: usize,
 }
 
 impl Default for Search {
@@ -56,12 +81,45 @@ impl Default for Search {
             include_path: false,
             include_date: false,
             truncate_content_length: None,
+            extra_fields: Vec::new(),
+            taxonomies: Vec::new(),
+            index_headings: false,
+            boosts: HashMap::new(),
             index_format: Default::default(),
+            shard_prefix_length: 2,
+            fragment_size: 100,
         }
     }
 }
 
 impl Search {
+    pub fn validate(&self) -> Result<()> {
+        if self.shard_prefix_length == 0 {
+            bail!("`search.shard_prefix_length` must be greater than 0");
+        }
+        if self.fragment_size == 0 {
+            bail!("`search.fragment_size` must be greater than 0");
+        }
+        for (field, boost) in &self.boosts {
+            let is_known = match field.split_once('.') {
+                Some(("extra", name)) => self.extra_fields.iter().any(|f| f == name),
+                Some(("taxonomies", name)) => self.taxonomies.iter().any(|t| t == name),
+                Some(_) => false,
+                None => ["title", "description", "date", "path", "body"].contains(&field.as_str()),
+            };
+            if !is_known {
+                bail!(
+                    "`search.boosts` contains `{}` which is not a field of the search index",
+                    field
+                );
+            }
+            if *boost == 0 {
+                bail!("`search.boosts.{}` must be greater than 0", field);
+            }
+        }
+        Ok(())
+    }
+
     pub fn serialize(&self) -> SerializedSearch {
         SerializedSearch { index_format: &self.index_format }
     }
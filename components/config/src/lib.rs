@@ -5,10 +5,16 @@ mod theme;
 use std::path::Path;
 
 pub use crate::config::{
+    image_processing::{ImageMetadata, ImageProcessing},
     languages::LanguageOptions,
     link_checker::LinkChecker,
     link_checker::LinkCheckerLevel,
-    markup::{BoolWithPath, MathRenderingEngine},
+    link_checker::PrefixOptions,
+    load_data::LoadData,
+    markup::{
+        BoolWithPath, CalloutKind, Diagrams, HighlighterBackend, MarkdownFilter,
+        MarkdownFilterFormat, MathRenderingEngine, TableOfContents,
+    },
     search::{IndexFormat, Search},
     slugify::Slugify,
     taxonomies::TaxonomyConfig,
@@ -1,5 +1,6 @@
 use std::env;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use libs::atty;
 use libs::once_cell::sync::Lazy;
@@ -11,24 +12,43 @@ use libs::termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor}
 static COLOR_CHOICE: Lazy<ColorChoice> =
     Lazy::new(|| if has_color() { ColorChoice::Always } else { ColorChoice::Never });
 
+/// Whether the messages usually printed to stdout go to stderr instead
+static USE_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Prints all the messages to stderr from now on, leaving stdout for a report meant to be
+/// parsed by another program
+pub fn use_stderr() {
+    USE_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Stdout, unless `use_stderr` was called
+fn stdout() -> StandardStream {
+    if USE_STDERR.load(Ordering::Relaxed) {
+        StandardStream::stderr(*COLOR_CHOICE)
+    } else {
+        StandardStream::stdout(*COLOR_CHOICE)
+    }
+}
+
+/// Print a message without any formatting
+pub fn plain(message: &str) {
+    colorize(message, &ColorSpec::new(), stdout());
+}
+
 pub fn info(message: &str) {
-    colorize(message, ColorSpec::new().set_bold(true), StandardStream::stdout(*COLOR_CHOICE));
+    colorize(message, ColorSpec::new().set_bold(true), stdout());
 }
 
 pub fn warn(message: &str) {
     colorize(
         &format!("{}{}", "Warning: ", message),
         ColorSpec::new().set_bold(true).set_fg(Some(Color::Yellow)),
-        StandardStream::stdout(*COLOR_CHOICE),
+        stdout(),
     );
 }
 
 pub fn success(message: &str) {
-    colorize(
-        message,
-        ColorSpec::new().set_bold(true).set_fg(Some(Color::Green)),
-        StandardStream::stdout(*COLOR_CHOICE),
-    );
+    colorize(message, ColorSpec::new().set_bold(true).set_fg(Some(Color::Green)), stdout());
 }
 
 pub fn error(message: &str) {
@@ -39,7 +59,7 @@ pub fn error(message: &str) {
     );
 }
 
-/// Print a colorized message to stdout
+/// Print a colorized message to the given stream
 fn colorize(message: &str, color: &ColorSpec, mut stream: StandardStream) {
     stream.set_color(color).unwrap();
     write!(stream, "{}", message).unwrap();
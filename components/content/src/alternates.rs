@@ -0,0 +1,179 @@
+use serde::Serialize;
+
+use config::Config;
+use libs::ahash::AHashMap;
+
+use crate::Taxonomy;
+
+/// The `hreflang` of the alternate used for the languages that are not available
+const X_DEFAULT: &str = "x-default";
+
+/// The version of some content in one of the languages of the site
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Alternate {
+    pub lang: String,
+    pub permalink: String,
+    pub title: Option<String>,
+    /// The paginate path and number of pagers of that version if it is paginated
+    #[serde(skip)]
+    pagination: Option<(String, usize)>,
+}
+
+impl Alternate {
+    pub fn new(lang: &str, permalink: &str, title: Option<&str>) -> Self {
+        Alternate {
+            lang: lang.to_string(),
+            permalink: permalink.to_string(),
+            title: title.map(|t| t.to_string()),
+            pagination: None,
+        }
+    }
+
+    pub(crate) fn with_pagination(mut self, paginate_path: &str, pages: usize, by: usize) -> Self {
+        let number_pagers = pages.div_ceil(by).max(1);
+        self.pagination = Some((paginate_path.to_string(), number_pagers));
+        self
+    }
+}
+
+/// Sorts the versions by language and adds the `x-default` one if it is configured.
+/// Content that only exists in one language has no alternates.
+pub(crate) fn finalize_alternates(mut versions: Vec<Alternate>, config: &Config) -> Vec<Alternate> {
+    if versions.len() < 2 {
+        return Vec::new();
+    }
+
+    versions.sort_by(|a, b| a.lang.cmp(&b.lang));
+    if let Some(ref lang) = config.hreflang_x_default {
+        if let Some(version) = versions.iter().find(|a| &a.lang == lang) {
+            let x_default = Alternate { lang: X_DEFAULT.to_string(), ..version.clone() };
+            versions.push(x_default);
+        }
+    }
+    versions
+}
+
+/// The alternates of the pager `index` (1-indexed) of some paginated content: the same pager in
+/// every language that has that many of them
+pub fn pager_alternates(alternates: &[Alternate], index: usize) -> Vec<Alternate> {
+    if index == 1 {
+        return alternates.to_vec();
+    }
+
+    let pagers: Vec<_> = alternates
+        .iter()
+        .filter_map(|a| {
+            let (paginate_path, number_pagers) = a.pagination.as_ref()?;
+            if index > *number_pagers {
+                return None;
+            }
+            let permalink = if paginate_path.is_empty() {
+                format!("{}{}/", a.permalink, index)
+            } else {
+                format!("{}{}/{}/", a.permalink, paginate_path, index)
+            };
+            Some(Alternate { permalink, ..a.clone() })
+        })
+        .collect();
+
+    if pagers.iter().filter(|a| a.lang != X_DEFAULT).count() < 2 {
+        return Vec::new();
+    }
+    pagers
+}
+
+/// Taxonomies with the same name in several languages are translations of each other, as are
+/// their terms with the same slug
+pub(crate) fn fill_taxonomy_alternates(taxonomies: &mut [Taxonomy], config: &Config) {
+    let mut taxonomy_versions: AHashMap<String, Vec<Alternate>> = AHashMap::new();
+    let mut term_versions: AHashMap<(String, String), Vec<Alternate>> = AHashMap::new();
+    for taxonomy in taxonomies.iter() {
+        taxonomy_versions.entry(taxonomy.kind.name.clone()).or_default().push(Alternate::new(
+            &taxonomy.lang,
+            &taxonomy.permalink,
+            Some(&taxonomy.kind.name),
+        ));
+        for item in &taxonomy.items {
+            let mut version = Alternate::new(&taxonomy.lang, &item.permalink, Some(&item.name));
+            if let Some(paginate_by) = taxonomy.kind.paginate_by {
+                version = version.with_pagination(
+                    taxonomy.kind.paginate_path(),
+                    item.pages.len(),
+                    paginate_by,
+                );
+            }
+            term_versions
+                .entry((taxonomy.kind.name.clone(), item.slug.clone()))
+                .or_default()
+                .push(version);
+        }
+    }
+
+    for taxonomy in taxonomies.iter_mut() {
+        taxonomy.alternates =
+            finalize_alternates(taxonomy_versions[&taxonomy.kind.name].clone(), config);
+        for item in taxonomy.items.iter_mut() {
+            let key = (taxonomy.kind.name.clone(), item.slug.clone());
+            item.alternates = finalize_alternates(term_versions[&key].clone(), config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alternates(config: &Config) -> Vec<Alternate> {
+        finalize_alternates(
+            vec![
+                Alternate::new("fr", "https://example.com/fr/blog/", Some("Blog FR"))
+                    .with_pagination("page", 3, 2),
+                Alternate::new("en", "https://example.com/blog/", Some("Blog"))
+                    .with_pagination("page", 5, 2),
+                Alternate::new("it", "https://example.com/it/blog/", None),
+            ],
+            config,
+        )
+    }
+
+    #[test]
+    fn sorts_alternates_and_adds_x_default() {
+        let mut config = Config::default_for_test();
+        let langs: Vec<_> = alternates(&config).into_iter().map(|a| a.lang).collect();
+        assert_eq!(langs, vec!["en", "fr", "it"]);
+
+        config.hreflang_x_default = Some("en".to_string());
+        let alternates = alternates(&config);
+        assert_eq!(alternates.len(), 4);
+        assert_eq!(alternates[3].lang, "x-default");
+        assert_eq!(alternates[3].permalink, "https://example.com/blog/");
+    }
+
+    #[test]
+    fn content_in_a_single_language_has_no_alternates() {
+        let config = Config::default_for_test();
+        let versions = vec![Alternate::new("en", "https://example.com/", None)];
+        assert!(finalize_alternates(versions, &config).is_empty());
+    }
+
+    #[test]
+    fn can_find_pager_alternates() {
+        let mut config = Config::default_for_test();
+        config.hreflang_x_default = Some("en".to_string());
+        let alternates = alternates(&config);
+
+        assert_eq!(pager_alternates(&alternates, 1), alternates);
+        let second: Vec<_> =
+            pager_alternates(&alternates, 2).into_iter().map(|a| (a.lang, a.permalink)).collect();
+        assert_eq!(
+            second,
+            vec![
+                ("en".to_string(), "https://example.com/blog/page/2/".to_string()),
+                ("fr".to_string(), "https://example.com/fr/blog/page/2/".to_string()),
+                ("x-default".to_string(), "https://example.com/blog/page/2/".to_string()),
+            ]
+        );
+        // Only the English version has a third pager
+        assert!(pager_alternates(&alternates, 3).is_empty());
+    }
+}
@@ -1,7 +1,9 @@
 mod page;
 mod section;
 mod split;
+mod toc;
 
 pub use page::PageFrontMatter;
 pub use section::SectionFrontMatter;
 pub use split::{split_page_content, split_section_content};
+pub use toc::TocFrontMatter;
@@ -10,6 +10,7 @@ use errors::{bail, Result};
 use utils::de::{fix_toml_dates, from_unknown_datetime};
 
 use crate::front_matter::split::RawFrontMatter;
+use crate::front_matter::TocFrontMatter;
 
 /// The front matter of every page
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
@@ -50,6 +51,10 @@ pub struct PageFrontMatter {
     /// otherwise is set after parsing front matter and sections
     /// Can't be an empty string if present
     pub path: Option<String>,
+    /// Groups the page with its translations instead of its filename, for translations that
+    /// don't share the same filename
+    /// Can't be an empty string if present
+    pub translation_key: Option<String>,
     pub taxonomies: HashMap<String, Vec<String>>,
     /// Integer to use to order content. Highest is at the bottom, lowest first
     pub weight: Option<usize>,
@@ -66,6 +71,8 @@ pub struct PageFrontMatter {
     /// Defaults to `true` but is only used if search if explicitly enabled in the config.
     #[serde(skip_serializing)]
     pub in_search_index: bool,
+    /// Which headings are in the table of contents and whether they are numbered
+    pub toc: TocFrontMatter,
     /// Any extra parameter present in the front matter
     pub extra: Map<String, Value>,
 }
@@ -102,6 +109,14 @@ impl PageFrontMatter {
             }
         }
 
+        if let Some(ref translation_key) = f.translation_key {
+            if translation_key.is_empty() {
+                bail!("`translation_key` can't be empty if present")
+            }
+        }
+
+        f.toc.validate()?;
+
         f.extra = match fix_toml_dates(f.extra) {
             Value::Object(o) => o,
             _ => unreachable!("Got something other than a table in page extra"),
@@ -158,11 +173,13 @@ impl Default for PageFrontMatter {
             render: true,
             slug: None,
             path: None,
+            translation_key: None,
             taxonomies: HashMap::new(),
             weight: None,
             authors: Vec::new(),
             aliases: Vec::new(),
             template: None,
+            toc: TocFrontMatter::default(),
             extra: Map::new(),
         }
     }
@@ -209,6 +226,37 @@ description: hey there
 
     #[test_case(&RawFrontMatter::Toml(r#"
 title = "Hello"
+toc = { max_level = 3, numbering = true }
+"#); "toml")]
+    #[test_case(&RawFrontMatter::Yaml(r#"
+title: Hello
+toc:
+  max_level: 3
+  numbering: true
+"#); "yaml")]
+    fn can_parse_toc_options(content: &RawFrontMatter) {
+        let res = PageFrontMatter::parse(content).unwrap();
+        assert_eq!(res.toc.min_level, None);
+        assert_eq!(res.toc.max_level, Some(3));
+        assert_eq!(res.toc.numbering, Some(true));
+    }
+
+    #[test_case(&RawFrontMatter::Toml(r#"
+title = "Hello"
+toc = { min_level = 4, max_level = 2 }
+"#); "toml")]
+    #[test_case(&RawFrontMatter::Yaml(r#"
+title: Hello
+toc:
+  max_level: 7
+"#); "yaml")]
+    fn errors_on_invalid_toc_levels(content: &RawFrontMatter) {
+        let res = PageFrontMatter::parse(content);
+        assert!(res.is_err());
+    }
+
+    #[test_case(&RawFrontMatter::Toml(r#"
+title = "Hello"
 description = "hey there"
 slug = ""
 "#); "toml")]
@@ -6,6 +6,7 @@ use utils::de::fix_toml_dates;
 use utils::types::InsertAnchor;
 
 use crate::front_matter::split::RawFrontMatter;
+use crate::front_matter::TocFrontMatter;
 use crate::SortBy;
 
 const DEFAULT_PAGINATE_PATH: &str = "page";
@@ -70,6 +71,9 @@ pub struct SectionFrontMatter {
     /// Whether to generate a feed for the current section
     #[serde(skip_serializing)]
     pub generate_feeds: bool,
+    /// Which headings are in the table of contents and whether they are numbered
+    #[serde(skip_serializing)]
+    pub toc: TocFrontMatter,
     /// Any extra parameter present in the front matter
     pub extra: Map<String, Value>,
 }
@@ -83,6 +87,8 @@ impl SectionFrontMatter {
             _ => unreachable!("Got something other than a table in section extra"),
         };
 
+        f.toc.validate()?;
+
         Ok(f)
     }
 
@@ -114,6 +120,7 @@ impl Default for SectionFrontMatter {
             page_template: None,
             aliases: Vec::new(),
             generate_feeds: false,
+            toc: TocFrontMatter::default(),
             extra: Map::new(),
             draft: false,
         }
@@ -0,0 +1,63 @@
+use config::TableOfContents;
+use serde::{Deserialize, Serialize};
+
+use errors::Result;
+
+/// The `[toc]` table of the front matter, overriding the `[markdown.toc]` config for that
+/// page or section
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TocFrontMatter {
+    pub min_level: Option<u32>,
+    pub max_level: Option<u32>,
+    pub numbering: Option<bool>,
+}
+
+impl TocFrontMatter {
+    /// The table of contents options to use, falling back to the config ones for everything
+    /// not set in the front matter
+    pub fn apply(&self, config: &TableOfContents) -> TableOfContents {
+        TableOfContents {
+            min_level: self.min_level.unwrap_or(config.min_level),
+            max_level: self.max_level.unwrap_or(config.max_level),
+            numbering: self.numbering.unwrap_or(config.numbering),
+        }
+    }
+
+    /// Only checks the levels set in the front matter since the other ones come from the config
+    /// which is validated on its own
+    pub fn validate(&self) -> Result<()> {
+        TableOfContents {
+            min_level: self.min_level.unwrap_or(1),
+            max_level: self.max_level.unwrap_or(6),
+            numbering: false,
+        }
+        .validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_the_config() {
+        let config = TableOfContents { min_level: 2, max_level: 4, numbering: true };
+        let toc = TocFrontMatter { max_level: Some(3), ..Default::default() };
+        assert_eq!(
+            toc.apply(&config),
+            TableOfContents { min_level: 2, max_level: 3, numbering: true }
+        );
+        assert_eq!(TocFrontMatter::default().apply(&config), config);
+    }
+
+    #[test]
+    fn errors_on_invalid_levels() {
+        assert!(TocFrontMatter { min_level: Some(0), ..Default::default() }.validate().is_err());
+        assert!(TocFrontMatter { max_level: Some(7), ..Default::default() }.validate().is_err());
+        assert!(TocFrontMatter { min_level: Some(3), max_level: Some(2), ..Default::default() }
+            .validate()
+            .is_err());
+        assert!(TocFrontMatter { min_level: Some(2), ..Default::default() }.validate().is_ok());
+    }
+}
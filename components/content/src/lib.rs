@@ -1,5 +1,6 @@
 mod front_matter;
 
+mod alternates;
 mod file_info;
 mod library;
 mod page;
@@ -11,6 +12,7 @@ mod taxonomies;
 mod types;
 mod utils;
 
+pub use alternates::{pager_alternates, Alternate};
 pub use file_info::FileInfo;
 pub use front_matter::{PageFrontMatter, SectionFrontMatter};
 pub use library::Library;
@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 use config::Config;
 use libs::ahash::{AHashMap, AHashSet};
 
+use crate::alternates::{fill_taxonomy_alternates, finalize_alternates, Alternate};
 use crate::ser::TranslatedContent;
 use crate::sorting::sort_pages;
 use crate::taxonomies::{Taxonomy, TaxonomyFound};
@@ -168,6 +169,10 @@ impl Library {
             }
         }
 
+        if config.is_multilingual() {
+            fill_taxonomy_alternates(&mut taxonomies, config);
+        }
+
         taxonomies
     }
 
@@ -299,7 +304,7 @@ impl Library {
                 continue;
             }
             let parent_filename = &index_filename_by_lang[&page.lang];
-            add_translation(&page.file.canonical, path);
+            add_translation(page.translation_key(), path);
             let mut parent_section_path = page.file.parent.join(parent_filename);
 
             while let Some(parent_section) = self.sections.get_mut(&parent_section_path) {
@@ -336,6 +341,53 @@ impl Library {
 
         // And once we have all the pages assigned to their section, we sort them
         self.sort_section_pages();
+        self.fill_alternates(config);
+    }
+
+    /// Sets the `alternates` of every page and section from their translations
+    fn fill_alternates(&mut self, config: &Config) {
+        let mut alternates = Vec::new();
+        for paths in self.translations.values() {
+            let versions = paths
+                .iter()
+                .filter_map(|path| match self.sections.get(path) {
+                    // Sections that are not rendered have no URL to link to
+                    Some(s) if !s.meta.render => None,
+                    Some(s) => {
+                        let version =
+                            Alternate::new(&s.lang, &s.permalink, s.meta.title.as_deref());
+                        Some(match s.paginate_by() {
+                            Some(by) => {
+                                version.with_pagination(&s.meta.paginate_path, s.pages.len(), by)
+                            }
+                            None => version,
+                        })
+                    }
+                    None => {
+                        let p = &self.pages[path];
+                        Some(Alternate::new(&p.lang, &p.permalink, p.meta.title.as_deref()))
+                    }
+                })
+                .collect();
+            let versions = finalize_alternates(versions, config);
+            for path in paths {
+                alternates.push((path.clone(), versions.clone()));
+            }
+        }
+
+        for section in self.sections.values_mut() {
+            section.alternates.clear();
+        }
+        for page in self.pages.values_mut() {
+            page.alternates.clear();
+        }
+        for (path, versions) in alternates {
+            if let Some(s) = self.sections.get_mut(&path) {
+                s.alternates = versions;
+            } else if let Some(p) = self.pages.get_mut(&path) {
+                p.alternates = versions;
+            }
+        }
     }
 
     /// Find all the orphan pages: pages that are in a folder without an `_index.md`
@@ -613,6 +665,40 @@ mod tests {
         assert!(translations[1].title.is_some());
     }
 
+    #[test]
+    fn can_link_translations_with_translation_key() {
+        let mut config = Config::default_for_test();
+        config.languages.insert("fr".to_owned(), LanguageOptions::default());
+        let mut library = Library::default();
+        library.insert_section(create_section("content/_index.md", "en", 0, false, SortBy::None));
+        library.insert_section(create_section(
+            "content/_index.fr.md",
+            "fr",
+            0,
+            false,
+            SortBy::None,
+        ));
+        let mut about = create_page("content/about.md", "en", PageSort::None);
+        about.meta.translation_key = Some("about".to_owned());
+        library.insert_page(about);
+        let mut a_propos = create_page("content/a-propos.fr.md", "fr", PageSort::None);
+        a_propos.meta.translation_key = Some("about".to_owned());
+        library.insert_page(a_propos);
+        library.insert_page(create_page("content/contact.md", "en", PageSort::None));
+        library.populate_sections(&config, Path::new("content"));
+
+        let translations = library.find_translations(Path::new("about"));
+        assert_eq!(translations.len(), 2);
+        assert!(translations.iter().any(|t| t.path == Path::new("content/a-propos.fr.md")));
+        assert!(library.find_translations(Path::new("content/about")).is_empty());
+        assert_eq!(library.find_translations(Path::new("content/contact")).len(), 1);
+
+        let about = &library.pages[&PathBuf::from("content/about.md")];
+        let langs: Vec<_> = about.alternates.iter().map(|a| a.lang.as_str()).collect();
+        assert_eq!(langs, vec!["en", "fr"]);
+        assert!(library.pages[&PathBuf::from("content/contact.md")].alternates.is_empty());
+    }
+
     macro_rules! taxonomies {
         ($config:expr, [$($page:expr),+]) => {{
             let mut library = Library::new(&$config);
@@ -716,6 +802,40 @@ mod tests {
         assert_eq!(fr_categories.items[0].permalink, "http://a-website.com/fr/catégories/rust/");
     }
 
+    #[test]
+    fn can_find_alternates_of_taxonomy_terms() {
+        let mut config = Config::default_for_test();
+        config.languages.insert("fr".to_owned(), LanguageOptions::default());
+        config.hreflang_x_default = Some("en".to_owned());
+        for lang in ["en", "fr"] {
+            config.languages.get_mut(lang).unwrap().taxonomies =
+                vec![TaxonomyConfig { name: "tags".to_string(), ..TaxonomyConfig::default() }];
+        }
+        config.slugify_taxonomies();
+
+        let page1 = create_page_w_taxa("a.md", "en", vec![("tags", vec!["rust", "python"])]);
+        let page2 = create_page_w_taxa("b.fr.md", "fr", vec![("tags", vec!["rust"])]);
+        let taxonomies = taxonomies!(config, [page1, page2]);
+
+        let tags = taxonomies.iter().find(|t| t.lang == "en").unwrap();
+        let alternates: Vec<_> =
+            tags.alternates.iter().map(|a| (a.lang.as_str(), a.permalink.as_str())).collect();
+        assert_eq!(
+            alternates,
+            vec![
+                ("en", "http://a-website.com/tags/"),
+                ("fr", "http://a-website.com/fr/tags/"),
+                ("x-default", "http://a-website.com/tags/"),
+            ]
+        );
+        let rust = tags.items.iter().find(|i| i.slug == "rust").unwrap();
+        assert_eq!(rust.alternates.len(), 3);
+        assert_eq!(rust.alternates[1].permalink, "http://a-website.com/fr/tags/rust/");
+        assert_eq!(rust.alternates[1].title.as_deref(), Some("rust"));
+        let python = tags.items.iter().find(|i| i.slug == "python").unwrap();
+        assert!(python.alternates.is_empty());
+    }
+
     #[test]
     fn taxonomies_with_unic_are_grouped_with_default_slugify_strategy() {
         let mut config = Config::default_for_test();
@@ -17,6 +17,7 @@ use utils::table_of_contents::Heading;
 use utils::templates::{render_template, ShortcodeDefinition};
 use utils::types::InsertAnchor;
 
+use crate::alternates::Alternate;
 use crate::file_info::FileInfo;
 use crate::front_matter::{split_page_content, PageFrontMatter};
 use crate::library::Library;
@@ -44,6 +45,8 @@ pub struct Page {
     pub ancestors: Vec<String>,
     /// The actual content of the page, in markdown
     pub raw_content: String,
+    /// The number of lines before `raw_content` in the file, used to report positions
+    pub content_line_offset: usize,
     /// All the non-md files we found next to the .md file
     pub assets: Vec<PathBuf>,
     /// All the non-md files we found next to the .md file
@@ -79,12 +82,17 @@ pub struct Page {
     pub lang: String,
     /// Contains all the translated version of that page
     pub translations: Vec<PathBuf>,
+    /// The versions of that page in every language, empty if it is not translated
+    pub alternates: Vec<Alternate>,
     /// The list of all internal links (as path to markdown file), with optional anchor fragments.
     /// We can only check the anchor after all pages have been built and their ToC compiled.
     /// The page itself should exist otherwise it would have errored before getting there.
     pub internal_links: Vec<(String, Option<String>)>,
     /// The list of all links to external webpages. They can be validated by the `link_checker`.
     pub external_links: Vec<String>,
+    /// The files read while rendering the content, such as the ones included in code blocks.
+    /// `zola serve` renders the content again when one of them changes.
+    pub dependencies: Vec<PathBuf>,
 }
 
 impl Page {
@@ -103,6 +111,7 @@ impl Page {
         config: &Config,
         base_path: &Path,
     ) -> Result<Page> {
+        let file_content = content;
         let (meta, content) = split_page_content(file_path, content)?;
         let mut page = Page::new(file_path, meta, base_path);
 
@@ -110,10 +119,13 @@ impl Page {
             page.file.find_language(&config.default_language, &config.other_languages_codes())?;
 
         page.raw_content = content.to_string();
+        page.content_line_offset =
+            file_content[..file_content.len() - content.len()].matches('\n').count();
         let (word_count, reading_time) = get_reading_analytics(&page.raw_content);
         page.word_count = Some(word_count);
         page.reading_time = Some(reading_time);
 
+        let slugify = config.slugify_for(&page.lang);
         let mut slug_from_dated_filename = None;
 
         let file_path_for_slug = if page.file.name == "index" {
@@ -127,7 +139,7 @@ impl Page {
         };
 
         if let Some(ref caps) = RFC3339_DATE.captures(&file_path_for_slug) {
-            if !config.slugify.paths_keep_dates {
+            if !slugify.paths_keep_dates {
                 slug_from_dated_filename = Some(caps.name("slug").unwrap().as_str().to_string());
             }
             if page.meta.date.is_none() {
@@ -138,11 +150,11 @@ impl Page {
 
         page.slug = {
             if let Some(ref slug) = page.meta.slug {
-                slugify_paths(slug, config.slugify.paths)
+                slugify_paths(slug, slugify.paths)
             } else if let Some(slug) = slug_from_dated_filename {
-                slugify_paths(&slug, config.slugify.paths)
+                slugify_paths(&slug, slugify.paths)
             } else {
-                slugify_paths(&file_path_for_slug, config.slugify.paths)
+                slugify_paths(&file_path_for_slug, slugify.paths)
             }
         };
 
@@ -165,8 +177,9 @@ impl Page {
                 format!("{}/{}", page.file.components.join("/"), page.slug)
             };
 
-            if page.lang != config.default_language {
-                path = format!("{}/{}", page.lang, path);
+            let url_prefix = config.url_prefix(&page.lang);
+            if !url_prefix.is_empty() {
+                path = format!("{}/{}", url_prefix, path);
             }
 
             format!("/{}", path)
@@ -213,6 +226,7 @@ impl Page {
         permalinks: &HashMap<String, String>,
         tera: &Tera,
         config: &Config,
+        base_path: &Path,
         anchor_insert: InsertAnchor,
         shortcode_definitions: &HashMap<String, ShortcodeDefinition>,
         caches: Option<Arc<Caches>>,
@@ -228,6 +242,9 @@ impl Page {
         );
         context.set_shortcode_definitions(shortcode_definitions);
         context.set_current_page_path(&self.file.relative);
+        context.set_content_line_offset(self.content_line_offset);
+        context.set_base_path(base_path);
+        context.set_toc(self.meta.toc.apply(&config.markdown.toc));
 
         context.set_parent_absolute(
             &self.file.parent,
@@ -245,6 +262,7 @@ impl Page {
         self.toc = res.toc;
         self.external_links = res.external_links;
         self.internal_links = res.internal_links;
+        self.dependencies = res.dependencies;
 
         Ok(())
     }
@@ -260,6 +278,7 @@ impl Page {
         context.insert("config", &config.serialize(&self.lang));
         context.insert("current_url", &self.permalink);
         context.insert("current_path", &self.path);
+        context.insert("alternates", &self.alternates);
         context.insert("page", &self.serialize(library));
         context.insert("lang", &self.lang);
 
@@ -289,6 +308,12 @@ impl Page {
             .collect()
     }
 
+    /// The key shared by this page and its translations: the `translation_key` of its front
+    /// matter if it has one, its canonical path otherwise
+    pub fn translation_key(&self) -> &Path {
+        self.meta.translation_key.as_deref().map(Path::new).unwrap_or(&self.file.canonical)
+    }
+
     pub fn has_anchor(&self, anchor: &str) -> bool {
         has_anchor(&self.toc, anchor)
     }
@@ -339,6 +364,7 @@ Hello world"#;
             &HashMap::default(),
             &ZOLA_TERA,
             &config,
+            &PathBuf::new(),
             InsertAnchor::None,
             &HashMap::new(),
             None,
@@ -351,6 +377,33 @@ Hello world"#;
         assert_eq!(page.content, "<p>Hello world</p>\n".to_string());
     }
 
+    #[test]
+    fn reports_shortcode_locations_in_the_file() {
+        let config = Config::default_for_test();
+        let content = r#"
++++
+title = "Hello"
++++
+Hello world
+
+{{ unknown() }}"#;
+        let mut page =
+            Page::parse(Path::new("content/post.md"), content, &config, &PathBuf::new()).unwrap();
+        assert_eq!(page.content_line_offset, 4);
+        let err = page
+            .render_markdown(
+                &HashMap::default(),
+                &ZOLA_TERA,
+                &config,
+                &PathBuf::new(),
+                InsertAnchor::None,
+                &HashMap::new(),
+                None,
+            )
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("content/post.md:7:1"), "{:#}", err);
+    }
+
     #[test]
     fn can_parse_author() {
         let config = Config::default_for_test();
@@ -368,6 +421,7 @@ Hello world"#;
             &HashMap::default(),
             &ZOLA_TERA,
             &config,
+            &PathBuf::new(),
             InsertAnchor::None,
             &HashMap::new(),
             None,
@@ -539,6 +593,7 @@ Hello world
             &HashMap::default(),
             &ZOLA_TERA,
             &config,
+            &PathBuf::new(),
             InsertAnchor::None,
             &HashMap::new(),
             None,
@@ -574,6 +629,7 @@ And here's another. [^3]
             &HashMap::default(),
             &ZOLA_TERA,
             &config,
+            &PathBuf::new(),
             InsertAnchor::None,
             &HashMap::new(),
             None,
@@ -915,6 +971,38 @@ Bonjour le monde"#
         assert_eq!(page.permalink, "http://a-website.com/fr/hello/");
     }
 
+    #[test]
+    fn can_use_url_prefix_and_slugify_of_the_language() {
+        let mut config = Config::default();
+        config.slugify.paths = SlugifyStrategy::On;
+        config.languages.insert(
+            "pt-BR".to_owned(),
+            LanguageOptions {
+                url_prefix: Some("br".to_owned()),
+                slugify: Some(config::Slugify {
+                    paths: SlugifyStrategy::Safe,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+        let content = r#"
++++
++++
+Olá mundo"#
+            .to_string();
+        let page = Page::parse(Path::new("Olá Mundo.pt-BR.md"), &content, &config, &PathBuf::new())
+            .unwrap();
+        assert_eq!(page.lang, "pt-BR");
+        assert_eq!(page.slug, "Olá Mundo");
+        assert_eq!(page.path, "/br/Olá Mundo/");
+
+        let page =
+            Page::parse(Path::new("Hello World.md"), &content, &config, &PathBuf::new()).unwrap();
+        assert_eq!(page.slug, "hello-world");
+        assert_eq!(page.path, "/hello-world/");
+    }
+
     #[test]
     fn i18n_frontmatter_path_overrides_default_permalink() {
         let mut config = Config::default();
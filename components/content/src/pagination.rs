@@ -8,6 +8,7 @@ use errors::{Context as ErrorContext, Result};
 use libs::tera::{to_value, Context, Tera, Value};
 use utils::templates::{check_template_fallbacks, render_template};
 
+use crate::alternates::pager_alternates;
 use crate::library::Library;
 use crate::ser::{SectionSerMode, SerializingPage, SerializingSection};
 use crate::taxonomies::{Taxonomy, TaxonomyTerm};
@@ -244,6 +245,11 @@ impl<'a> Paginator<'a> {
         };
         context.insert("current_url", &pager.permalink);
         context.insert("current_path", &pager.path);
+        let alternates = match self.root {
+            PaginationRoot::Section(s) => &s.alternates,
+            PaginationRoot::Taxonomy(_, item) => &item.alternates,
+        };
+        context.insert("alternates", &pager_alternates(alternates, pager.index));
         context.insert("paginator", &self.build_paginator_context(pager));
 
         render_template(&self.template, tera, context, &config.theme)
@@ -398,6 +404,7 @@ mod tests {
             path: "/some-tags/something/".to_string(),
             permalink: "https://vincent.is/some-tags/something/".to_string(),
             pages: library.pages.keys().cloned().collect(),
+            alternates: Vec::new(),
         };
         let taxonomy = Taxonomy {
             kind: taxonomy_def,
@@ -406,6 +413,7 @@ mod tests {
             path: "/some-tags/".to_string(),
             permalink: "https://vincent.is/some-tags/".to_string(),
             items: vec![taxonomy_item.clone()],
+            alternates: Vec::new(),
         };
         let paginator = Paginator::from_taxonomy(&taxonomy, &taxonomy_item, &library, &tera, &None);
         assert_eq!(paginator.pagers.len(), 2);
@@ -8,11 +8,13 @@ use config::Config;
 use errors::{Context, Result};
 use markdown::context::Caches;
 use markdown::{render_content, RenderContext};
+use utils::anchors::has_anchor_id;
 use utils::fs::read_file;
 use utils::net::is_external_link;
 use utils::table_of_contents::Heading;
 use utils::templates::{render_template, ShortcodeDefinition};
 
+use crate::alternates::Alternate;
 use crate::file_info::FileInfo;
 use crate::front_matter::{split_section_content, SectionFrontMatter};
 use crate::library::Library;
@@ -34,6 +36,8 @@ pub struct Section {
     pub permalink: String,
     /// The actual content of the page, in markdown
     pub raw_content: String,
+    /// The number of lines before `raw_content` in the file, used to report positions
+    pub content_line_offset: usize,
     /// The HTML rendered of the page
     pub content: String,
     /// All the non-md files we found next to the .md file
@@ -58,12 +62,17 @@ pub struct Section {
     /// The language of that section. Equal to the default lang if the user doesn't setup `languages` in config.
     /// Corresponds to the lang in the _index.{lang}.md file scheme
     pub lang: String,
+    /// The versions of that section in every language, empty if it is not translated
+    pub alternates: Vec<Alternate>,
     /// The list of all internal links (as path to markdown file), with optional anchor fragments.
     /// We can only check the anchor after all pages have been built and their ToC compiled.
     /// The page itself should exist otherwise it would have errored before getting there.
     pub internal_links: Vec<(String, Option<String>)>,
     /// The list of all links to external webpages. They can be validated by the `link_checker`.
     pub external_links: Vec<String>,
+    /// The files read while rendering the content, such as the ones included in code blocks.
+    /// `zola serve` renders the content again when one of them changes.
+    pub dependencies: Vec<PathBuf>,
 }
 
 impl Section {
@@ -83,22 +92,22 @@ impl Section {
         config: &Config,
         base_path: &Path,
     ) -> Result<Section> {
+        let file_content = content;
         let (meta, content) = split_section_content(file_path, content)?;
         let mut section = Section::new(file_path, meta, base_path);
         section.lang = section
             .file
             .find_language(&config.default_language, &config.other_languages_codes())?;
         section.raw_content = content.to_string();
+        section.content_line_offset =
+            file_content[..file_content.len() - content.len()].matches('\n').count();
         let (word_count, reading_time) = get_reading_analytics(&section.raw_content);
         section.word_count = Some(word_count);
         section.reading_time = Some(reading_time);
 
         let path = section.file.components.join("/");
-        let lang_path = if section.lang != config.default_language {
-            format!("/{}", section.lang)
-        } else {
-            "".into()
-        };
+        let url_prefix = config.url_prefix(&section.lang);
+        let lang_path = if url_prefix.is_empty() { "".into() } else { format!("/{}", url_prefix) };
         section.path = if path.is_empty() {
             format!("{}/", lang_path)
         } else {
@@ -151,6 +160,7 @@ impl Section {
         permalinks: &HashMap<String, String>,
         tera: &Tera,
         config: &Config,
+        base_path: &Path,
         shortcode_definitions: &HashMap<String, ShortcodeDefinition>,
         caches: Option<Arc<Caches>>,
     ) -> Result<()> {
@@ -165,6 +175,9 @@ impl Section {
         );
         context.set_shortcode_definitions(shortcode_definitions);
         context.set_current_page_path(&self.file.relative);
+        context.set_content_line_offset(self.content_line_offset);
+        context.set_base_path(base_path);
+        context.set_toc(self.meta.toc.apply(&config.markdown.toc));
         context.set_parent_absolute(
             &self.file.parent,
             self.file.colocated_path.as_ref(),
@@ -187,6 +200,7 @@ impl Section {
         }
 
         self.internal_links = res.internal_links;
+        self.dependencies = res.dependencies;
 
         Ok(())
     }
@@ -199,6 +213,7 @@ impl Section {
         context.insert("config", &config.serialize(&self.lang));
         context.insert("current_url", &self.permalink);
         context.insert("current_path", &self.path);
+        context.insert("alternates", &self.alternates);
         context.insert("section", &SerializingSection::new(self, SectionSerMode::Full(library)));
         context.insert("lang", &self.lang);
 
@@ -225,6 +240,10 @@ impl Section {
         has_anchor(&self.toc, anchor)
     }
 
+    pub fn has_anchor_id(&self, id: &str) -> bool {
+        has_anchor_id(&self.content, id)
+    }
+
     pub fn paginate_by(&self) -> Option<usize> {
         match self.meta.paginate_by {
             None => None,
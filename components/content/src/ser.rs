@@ -3,6 +3,7 @@ use std::path::Path;
 
 use serde::Serialize;
 
+use crate::alternates::Alternate;
 use crate::library::Library;
 use crate::{Page, Section};
 use libs::tera::{Map, Value};
@@ -69,6 +70,7 @@ pub struct SerializingPage<'a> {
     lower: Option<Box<SerializingPage<'a>>>,
     higher: Option<Box<SerializingPage<'a>>>,
     translations: Vec<TranslatedContent<'a>>,
+    alternates: &'a [Alternate],
     backlinks: Vec<BackLink<'a>>,
 }
 
@@ -88,7 +90,7 @@ impl<'a> SerializingPage<'a> {
         let mut backlinks = vec![];
 
         if let Some(lib) = library {
-            translations = lib.find_translations(&page.file.canonical);
+            translations = lib.find_translations(page.translation_key());
 
             if include_siblings {
                 lower = page
@@ -133,6 +135,7 @@ impl<'a> SerializingPage<'a> {
             lower,
             higher,
             translations,
+            alternates: &page.alternates,
             backlinks,
         }
     }
@@ -159,6 +162,7 @@ pub struct SerializingSection<'a> {
     pages: Vec<SerializingPage<'a>>,
     subsections: Vec<&'a str>,
     translations: Vec<TranslatedContent<'a>>,
+    alternates: &'a [Alternate],
     backlinks: Vec<BackLink<'a>>,
     generate_feeds: bool,
     transparent: bool,
@@ -227,6 +231,7 @@ impl<'a> SerializingSection<'a> {
             pages,
             subsections,
             translations,
+            alternates: &section.alternates,
             backlinks,
             paginate_by: &section.meta.paginate_by,
             paginate_reversed: section.meta.paginate_reversed,
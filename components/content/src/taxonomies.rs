@@ -10,6 +10,7 @@ use libs::tera::{Context, Tera};
 use utils::slugs::slugify_paths;
 use utils::templates::{check_template_fallbacks, render_template};
 
+use crate::alternates::Alternate;
 use crate::library::Library;
 use crate::ser::SerializingPage;
 use crate::{Page, SortBy};
@@ -24,6 +25,7 @@ pub struct SerializedTaxonomyTerm<'a> {
     permalink: &'a str,
     pages: Vec<SerializingPage<'a>>,
     page_count: usize,
+    alternates: &'a [Alternate],
 }
 
 impl<'a> SerializedTaxonomyTerm<'a> {
@@ -43,6 +45,7 @@ impl<'a> SerializedTaxonomyTerm<'a> {
             permalink: &item.permalink,
             pages,
             page_count: item.pages.len(),
+            alternates: &item.alternates,
         }
     }
 }
@@ -55,6 +58,8 @@ pub struct TaxonomyTerm {
     pub path: String,
     pub permalink: String,
     pub pages: Vec<PathBuf>,
+    /// The same term in the other languages, empty if it is not translated
+    pub alternates: Vec<Alternate>,
 }
 
 impl TaxonomyTerm {
@@ -65,11 +70,12 @@ impl TaxonomyTerm {
         taxo_pages: &[&Page],
         config: &Config,
     ) -> Self {
-        let item_slug = slugify_paths(name, config.slugify.taxonomies);
-        let path = if lang != config.default_language {
-            format!("/{}/{}/{}/", lang, taxo_slug, item_slug)
-        } else {
+        let item_slug = slugify_paths(name, config.slugify_for(lang).taxonomies);
+        let url_prefix = config.url_prefix(lang);
+        let path = if url_prefix.is_empty() {
             format!("/{}/{}/", taxo_slug, item_slug)
+        } else {
+            format!("/{}/{}/{}/", url_prefix, taxo_slug, item_slug)
         };
         let permalink = config.make_permalink(&path);
 
@@ -79,7 +85,14 @@ impl TaxonomyTerm {
         let (mut pages, ignored_pages) = sort_pages(taxo_pages, SortBy::Date);
         // We still append pages without dates at the end
         pages.extend(ignored_pages);
-        TaxonomyTerm { name: name.to_string(), permalink, path, slug: item_slug, pages }
+        TaxonomyTerm {
+            name: name.to_string(),
+            permalink,
+            path,
+            slug: item_slug,
+            pages,
+            alternates: Vec::new(),
+        }
     }
 
     pub fn serialize<'a>(&'a self, library: &'a Library) -> SerializedTaxonomyTerm<'a> {
@@ -139,6 +152,8 @@ pub struct Taxonomy {
     pub permalink: String,
     // this vec is sorted by the count of item
     pub items: Vec<TaxonomyTerm>,
+    /// The same taxonomy in the other languages, empty if it is not translated
+    pub alternates: Vec<Alternate>,
 }
 
 impl Taxonomy {
@@ -165,10 +180,11 @@ impl Taxonomy {
                 false
             }
         });
-        let path = if tax_found.lang != config.default_language {
-            format!("/{}/{}/", tax_found.lang, slug)
-        } else {
+        let url_prefix = config.url_prefix(tax_found.lang);
+        let path = if url_prefix.is_empty() {
             format!("/{}/", slug)
+        } else {
+            format!("/{}/{}/", url_prefix, slug)
         };
         let permalink = config.make_permalink(&path);
 
@@ -179,6 +195,7 @@ impl Taxonomy {
             path,
             permalink,
             items: sorted_items,
+            alternates: Vec::new(),
         }
     }
 
@@ -213,6 +230,7 @@ impl Taxonomy {
         context.insert("taxonomy", &self.kind);
         context.insert("current_url", &item.permalink);
         context.insert("current_path", &item.path);
+        context.insert("alternates", &item.alternates);
         context
     }
 
@@ -234,6 +252,7 @@ impl Taxonomy {
         context.insert("taxonomy", &self.kind);
         context.insert("current_url", &self.permalink);
         context.insert("current_path", &self.path);
+        context.insert("alternates", &self.alternates);
 
         // Check for taxon-specific template, or use generic as fallback.
         let specific_template = format!("{}/list.html", self.kind.name);
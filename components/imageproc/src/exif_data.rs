@@ -0,0 +1,238 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use exif::{Exif, In, Tag, Value};
+use serde::Serialize;
+
+use crate::helpers::read_exif_container;
+
+const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+const IPTC_RESOURCE_ID: u16 = 0x0404;
+
+/// GPS location in decimal degrees, altitude in meters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct GpsLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+/// The most commonly used EXIF and IPTC fields of a photo, used in `get_image_metadata()`
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ExifData {
+    /// When the photo was taken, as `YYYY-MM-DDTHH:MM:SS` with the offset if it is known
+    pub date_time: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    /// Exposure time in seconds, as written by photographers: `1/250` or `2`
+    pub exposure_time: Option<String>,
+    pub f_number: Option<f64>,
+    /// Focal length in millimeters
+    pub focal_length: Option<f64>,
+    pub iso: Option<u32>,
+    pub gps: Option<GpsLocation>,
+    pub title: Option<String>,
+    /// IPTC caption, falling back to the EXIF image description
+    pub caption: Option<String>,
+    pub artist: Option<String>,
+    pub copyright: Option<String>,
+    pub keywords: Vec<String>,
+}
+
+/// Reads the EXIF and IPTC metadata of the image at `path`.
+/// Missing or unreadable metadata results in empty fields rather than an error.
+pub fn read_exif_data<P: AsRef<Path>>(path: P) -> ExifData {
+    let path = path.as_ref();
+    let mut data = ExifData::default();
+
+    if let Some(exif) = read_exif_container(path) {
+        data.date_time = exif_ascii(&exif, Tag::DateTimeOriginal)
+            .or_else(|| exif_ascii(&exif, Tag::DateTime))
+            .and_then(|d| format_exif_date(&d, exif_ascii(&exif, Tag::OffsetTimeOriginal)));
+        data.make = exif_ascii(&exif, Tag::Make);
+        data.model = exif_ascii(&exif, Tag::Model);
+        data.lens = exif_ascii(&exif, Tag::LensModel);
+        data.exposure_time = exif_rational(&exif, Tag::ExposureTime).map(format_exposure_time);
+        data.f_number = exif_rational(&exif, Tag::FNumber).map(|r| r.to_f64());
+        data.focal_length = exif_rational(&exif, Tag::FocalLength).map(|r| r.to_f64());
+        data.iso = exif
+            .get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0));
+        data.gps = read_gps(&exif);
+        data.caption = exif_ascii(&exif, Tag::ImageDescription);
+        data.artist = exif_ascii(&exif, Tag::Artist);
+        data.copyright = exif_ascii(&exif, Tag::Copyright);
+    }
+
+    if let Some(iptc) = read_jpeg_iptc(path) {
+        // IPTC fields are usually the ones edited by hand so they take precedence
+        for (dataset, value) in iptc {
+            match dataset {
+                5 => data.title = Some(value),
+                25 => data.keywords.push(value),
+                80 => data.artist = Some(value),
+                116 => data.copyright = Some(value),
+                120 => data.caption = Some(value),
+                _ => (),
+            }
+        }
+    }
+
+    data
+}
+
+fn exif_ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim_end_matches('\0').trim().to_string())
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    }
+}
+
+fn exif_rational(exif: &Exif, tag: Tag) -> Option<exif::Rational> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) => values.first().copied().filter(|r| r.denom != 0),
+        _ => None,
+    }
+}
+
+/// Turns `2023:07:14 18:32:05` into `2023-07-14T18:32:05`
+fn format_exif_date(date: &str, offset: Option<String>) -> Option<String> {
+    let bytes = date.as_bytes();
+    if bytes.len() != 19 || bytes[4] != b':' || bytes[7] != b':' || bytes[10] != b' ' {
+        return None;
+    }
+    let mut out = format!("{}-{}-{}T{}", &date[0..4], &date[5..7], &date[8..10], &date[11..]);
+    if let Some(offset) = offset {
+        out.push_str(&offset);
+    }
+    Some(out)
+}
+
+fn format_exposure_time(exposure: exif::Rational) -> String {
+    fn gcd(a: u32, b: u32) -> u32 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    if exposure.num >= exposure.denom {
+        return format!("{}", exposure.to_f64());
+    }
+    let divisor = gcd(exposure.num, exposure.denom).max(1);
+    format!("{}/{}", exposure.num / divisor, exposure.denom / divisor)
+}
+
+fn read_gps(exif: &Exif) -> Option<GpsLocation> {
+    fn coordinate(exif: &Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+        let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Rational(v) if v.len() >= 3 && v.iter().all(|r| r.denom != 0) => {
+                v[0].to_f64() + v[1].to_f64() / 60.0 + v[2].to_f64() / 3600.0
+            }
+            _ => return None,
+        };
+        if exif_ascii(exif, ref_tag).as_deref() == Some(negative_ref) {
+            Some(-degrees)
+        } else {
+            Some(degrees)
+        }
+    }
+
+    let latitude = coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?;
+    let longitude = coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+    let altitude = exif_rational(exif, Tag::GPSAltitude).map(|r| {
+        // A reference of 1 means below sea level
+        let below_sea_level = exif
+            .get_field(Tag::GPSAltitudeRef, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+            .is_some_and(|r| r == 1);
+        if below_sea_level {
+            -r.to_f64()
+        } else {
+            r.to_f64()
+        }
+    });
+
+    Some(GpsLocation { latitude, longitude, altitude })
+}
+
+/// Returns the IPTC datasets of the application record (2:xx) as `(dataset, value)`.
+/// IPTC is stored in a Photoshop APP13 segment in JPEG files, other formats are not supported.
+fn read_jpeg_iptc(path: &Path) -> Option<Vec<(u8, String)>> {
+    let mut reader = BufReader::new(File::open(path).ok()?);
+    let mut marker = [0u8; 2];
+    reader.read_exact(&mut marker).ok()?;
+    if marker != [0xFF, 0xD8] {
+        return None;
+    }
+
+    loop {
+        reader.read_exact(&mut marker).ok()?;
+        // Start of scan or end of image: there are no more metadata segments
+        if marker[0] != 0xFF || marker[1] == 0xDA || marker[1] == 0xD9 {
+            return None;
+        }
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len).ok()?;
+        let len = (u16::from_be_bytes(len) as usize).checked_sub(2)?;
+        if marker[1] != 0xED {
+            reader.seek(SeekFrom::Current(len as i64)).ok()?;
+            continue;
+        }
+
+        let mut segment = vec![0u8; len];
+        reader.read_exact(&mut segment).ok()?;
+        if let Some(iptc) = segment.strip_prefix(PHOTOSHOP_HEADER).and_then(find_iptc_resource) {
+            return Some(parse_iptc(iptc));
+        }
+    }
+}
+
+/// Finds the IPTC block in a list of Photoshop `8BIM` image resources
+fn find_iptc_resource(mut data: &[u8]) -> Option<&[u8]> {
+    while data.len() >= 12 && data.starts_with(b"8BIM") {
+        let id = u16::from_be_bytes([data[4], data[5]]);
+        // The name is a Pascal string padded to an even length
+        let name_len = data[6] as usize + 1;
+        let offset = 6 + name_len + name_len % 2;
+        let size_bytes = data.get(offset..offset + 4)?;
+        let size = u32::from_be_bytes(size_bytes.try_into().ok()?) as usize;
+        let start = offset + 4;
+        let resource = data.get(start..start + size)?;
+        if id == IPTC_RESOURCE_ID {
+            return Some(resource);
+        }
+        data = data.get(start + size + size % 2..)?;
+    }
+    None
+}
+
+fn parse_iptc(mut data: &[u8]) -> Vec<(u8, String)> {
+    let mut datasets = Vec::new();
+    while data.len() >= 5 && data[0] == 0x1C {
+        let (record, dataset) = (data[1], data[2]);
+        let len = u16::from_be_bytes([data[3], data[4]]) as usize;
+        // Extended datasets are only used for binary data we don't care about
+        if len & 0x8000 != 0 {
+            break;
+        }
+        let value = match data.get(5..5 + len) {
+            Some(v) => v,
+            None => break,
+        };
+        if record == 2 {
+            let value = String::from_utf8_lossy(value).trim().to_string();
+            if !value.is_empty() {
+                datasets.push((dataset, value));
+            }
+        }
+        data = &data[5 + len..];
+    }
+    datasets
+}
@@ -1,7 +1,12 @@
+use config::ImageProcessing;
 use errors::{anyhow, Result};
 use std::hash::{Hash, Hasher};
 
-const DEFAULT_Q_JPG: u8 = 75;
+/// The encoder settings used before they were configurable. They are left out of the hash
+/// when unchanged so the existing filenames stay the same.
+pub(crate) const DEFAULT_AVIF_QUALITY: u8 = 70;
+const DEFAULT_AVIF_SPEED: u8 = 10;
+const DEFAULT_WEBP_METHOD: u8 = 4;
 
 /// Thumbnail image format
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -10,19 +15,26 @@ pub enum Format {
     Jpeg(u8),
     /// PNG
     Png,
-    /// WebP, The `u8` argument is WebP quality (in percent), None meaning lossless.
-    WebP(Option<u8>),
-    /// AVIF, The `u8` argument is AVIF quality (in percent), None meaning lossless.
-    Avif(Option<u8>),
+    /// WebP, `quality` is in percent, None meaning lossless.
+    /// `method` is the compression method, from 0 (fast) to 6 (slow).
+    WebP { quality: Option<u8>, method: u8 },
+    /// AVIF, `quality` is in percent, None meaning the default quality.
+    /// `speed` goes from 1 (slow) to 10 (fast).
+    Avif { quality: Option<u8>, speed: u8 },
 }
 
 impl Format {
-    pub fn from_args(is_lossy: bool, format: &str, quality: Option<u8>) -> Result<Format> {
+    pub fn from_args(
+        is_lossy: bool,
+        format: &str,
+        quality: Option<u8>,
+        settings: &ImageProcessing,
+    ) -> Result<Format> {
         use Format::*;
         if let Some(quality) = quality {
             assert!(quality > 0 && quality <= 100, "Quality must be within the range [1; 100]");
         }
-        let jpg_quality = quality.unwrap_or(DEFAULT_Q_JPG);
+        let jpg_quality = quality.unwrap_or(settings.jpeg_quality);
         match format {
             "auto" => {
                 if is_lossy {
@@ -33,8 +45,15 @@ impl Format {
             }
             "jpeg" | "jpg" => Ok(Jpeg(jpg_quality)),
             "png" => Ok(Png),
-            "webp" => Ok(WebP(quality)),
-            "avif" => Ok(Avif(quality)),
+            "webp" => Ok(WebP {
+                quality: quality.or(settings.webp_quality),
+                method: settings.webp_method,
+            }),
+            "avif" => Ok(Avif {
+                quality: quality
+                    .or(Some(settings.avif_quality).filter(|q| *q != DEFAULT_AVIF_QUALITY)),
+                speed: settings.avif_speed,
+            }),
             _ => Err(anyhow!("Invalid image format: {}", format)),
         }
     }
@@ -46,8 +65,8 @@ impl Format {
         match *self {
             Png => "png",
             Jpeg(_) => "jpg",
-            WebP(_) => "webp",
-            Avif(_) => "avif",
+            WebP { .. } => "webp",
+            Avif { .. } => "avif",
         }
     }
 }
@@ -60,13 +79,18 @@ impl Hash for Format {
         let q = match *self {
             Png => 0,
             Jpeg(q) => 1001 + q as u16,
-            WebP(None) => 2000,
-            WebP(Some(q)) => 2001 + q as u16,
-            Avif(None) => 3000,
-            Avif(Some(q)) => 3001 + q as u16,
+            WebP { quality: None, .. } => 2000,
+            WebP { quality: Some(q), .. } => 2001 + q as u16,
+            Avif { quality: None, .. } => 3000,
+            Avif { quality: Some(q), .. } => 3001 + q as u16,
         };
 
         hasher.write_u16(q);
+        match *self {
+            WebP { method, .. } if method != DEFAULT_WEBP_METHOD => hasher.write_u8(method),
+            Avif { speed, .. } if speed != DEFAULT_AVIF_SPEED => hasher.write_u8(speed),
+            _ => (),
+        }
         hasher.write(self.extension().as_bytes());
     }
 }
@@ -1,19 +1,32 @@
 use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
+use std::io::Cursor;
 use std::path::Path;
 
+use config::ImageMetadata;
+use libs::image::{DynamicImage, ImageDecoder, ImageReader};
+
 use crate::format::Format;
 use crate::ResizeOperation;
-use libs::image::DynamicImage;
 
-/// Apply image rotation based on EXIF data
-/// Returns `None` if no transformation is needed
-pub fn fix_orientation(img: &DynamicImage, path: &Path) -> Option<DynamicImage> {
+/// Maximum payload of a JPEG marker segment: 65535 minus the 2 bytes of the length itself
+const JPEG_SEGMENT_MAX_LEN: usize = 65533;
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+/// Reads the EXIF data embedded in the image at `path`, if any
+pub(crate) fn read_exif_container(path: &Path) -> Option<exif::Exif> {
     let file = std::fs::File::open(path).ok()?;
     let mut buf_reader = std::io::BufReader::new(&file);
     let exif_reader = exif::Reader::new();
-    let exif = exif_reader.read_from_container(&mut buf_reader).ok()?;
+    exif_reader.read_from_container(&mut buf_reader).ok()
+}
+
+/// Apply image rotation based on EXIF data
+/// Returns `None` if no transformation is needed
+pub fn fix_orientation(img: &DynamicImage, path: &Path) -> Option<DynamicImage> {
+    let exif = read_exif_container(path)?;
     let orientation =
         exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?.value.get_uint(0)?;
     match orientation {
@@ -32,6 +45,105 @@ pub fn fix_orientation(img: &DynamicImage, path: &Path) -> Option<DynamicImage>
     }
 }
 
+/// Metadata of a source image that should be copied to the processed image
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PreservedMetadata {
+    /// The raw ICC colour profile
+    pub icc_profile: Option<Vec<u8>>,
+    /// EXIF data, encoded as TIFF
+    pub exif: Option<Vec<u8>>,
+}
+
+impl PreservedMetadata {
+    /// Reads the metadata to keep according to `policy`.
+    /// Metadata that cannot be read is silently dropped.
+    pub fn read(path: &Path, policy: ImageMetadata, keep_gps: bool) -> Self {
+        match policy {
+            ImageMetadata::Strip => Self::default(),
+            ImageMetadata::ColorProfile => Self { icc_profile: read_icc_profile(path), exif: None },
+            ImageMetadata::Keep => {
+                Self { icc_profile: read_icc_profile(path), exif: read_exif(path, keep_gps) }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icc_profile.is_none() && self.exif.is_none()
+    }
+
+    /// Inserts the metadata as APP1/APP2 segments right after the SOI marker of `jpeg`
+    pub fn embed_in_jpeg(&self, jpeg: Vec<u8>) -> Vec<u8> {
+        if self.is_empty() || !jpeg.starts_with(&[0xFF, 0xD8]) {
+            return jpeg;
+        }
+
+        let mut out = Vec::with_capacity(jpeg.len());
+        out.extend_from_slice(&jpeg[..2]);
+        if let Some(exif) = &self.exif {
+            // EXIF cannot be split over several segments, drop it if it's too big
+            if JPEG_EXIF_HEADER.len() + exif.len() <= JPEG_SEGMENT_MAX_LEN {
+                write_jpeg_segment(&mut out, 0xE1, &[JPEG_EXIF_HEADER, exif]);
+            }
+        }
+        if let Some(icc) = &self.icc_profile {
+            // ICC profiles are split in chunks, each prefixed by its (1-based) index and the count
+            let chunk_len = JPEG_SEGMENT_MAX_LEN - JPEG_ICC_HEADER.len() - 2;
+            let chunks: Vec<_> = icc.chunks(chunk_len).collect();
+            if chunks.len() <= u8::MAX as usize {
+                for (i, chunk) in chunks.iter().enumerate() {
+                    let position = [(i + 1) as u8, chunks.len() as u8];
+                    write_jpeg_segment(&mut out, 0xE2, &[JPEG_ICC_HEADER, &position, chunk]);
+                }
+            }
+        }
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+}
+
+fn write_jpeg_segment(out: &mut Vec<u8>, marker: u8, parts: &[&[u8]]) {
+    let len: usize = parts.iter().map(|p| p.len()).sum::<usize>() + 2;
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&(len as u16).to_be_bytes());
+    for part in parts {
+        out.extend_from_slice(part);
+    }
+}
+
+fn read_icc_profile(path: &Path) -> Option<Vec<u8>> {
+    let reader = ImageReader::open(path).ok()?.with_guessed_format().ok()?;
+    let mut decoder = reader.into_decoder().ok()?;
+    decoder.icc_profile().ok()?
+}
+
+/// Reads the EXIF data of the primary image and re-encodes it.
+/// The orientation is dropped since it is applied when processing the image, as is
+/// the GPS location unless `keep_gps` is set.
+fn read_exif(path: &Path, keep_gps: bool) -> Option<Vec<u8>> {
+    let exif = read_exif_container(path)?;
+
+    let mut writer = exif::experimental::Writer::new();
+    let mut has_fields = false;
+    for field in exif.fields() {
+        let exif::Tag(context, _) = field.tag;
+        if field.ifd_num != exif::In::PRIMARY
+            || field.tag == exif::Tag::Orientation
+            || (context == exif::Context::Gps && !keep_gps)
+        {
+            continue;
+        }
+        writer.push_field(field);
+        has_fields = true;
+    }
+    if !has_fields {
+        return None;
+    }
+
+    let mut buf = Cursor::new(Vec::new());
+    writer.write(&mut buf, exif.little_endian()).ok()?;
+    Some(buf.into_inner())
+}
+
 /// We only use the input_path to get the file stem.
 /// Hashing the resolved `input_path` would include the absolute path to the image
 /// with all filesystem components.
@@ -40,11 +152,19 @@ pub fn get_processed_filename(
     input_src: &str,
     op: &ResizeOperation,
     format: &Format,
+    metadata: ImageMetadata,
+    keep_gps: bool,
 ) -> String {
     let mut hasher = DefaultHasher::new();
     hasher.write(input_src.as_ref());
     op.hash(&mut hasher);
     format.hash(&mut hasher);
+    // Only hash the metadata settings when they differ from the default and the format keeps
+    // the metadata, to keep existing filenames stable
+    if metadata != ImageMetadata::Strip && matches!(format, Format::Jpeg(_)) {
+        metadata.hash(&mut hasher);
+        keep_gps.hash(&mut hasher);
+    }
     let hash = hasher.finish();
     let filename = input_path
         .file_stem()
@@ -1,9 +1,11 @@
+mod exif_data;
 mod format;
 mod helpers;
 mod meta;
 mod ops;
 mod processor;
 
+pub use exif_data::{read_exif_data, ExifData, GpsLocation};
 pub use helpers::fix_orientation;
 pub use meta::{read_image_metadata, ImageMeta, ImageMetaResponse};
 pub use ops::{ResizeInstructions, ResizeOperation};
@@ -3,7 +3,7 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
-use config::Config;
+use config::{Config, ImageMetadata, ImageProcessing};
 use errors::{anyhow, Context, Result};
 use libs::ahash::{HashMap, HashSet};
 use libs::image::codecs::avif::AvifEncoder;
@@ -16,8 +16,8 @@ use libs::{image, webp};
 use serde::{Deserialize, Serialize};
 use utils::fs as ufs;
 
-use crate::format::Format;
-use crate::helpers::get_processed_filename;
+use crate::format::{Format, DEFAULT_AVIF_QUALITY};
+use crate::helpers::{get_processed_filename, PreservedMetadata};
 use crate::{fix_orientation, ImageMeta, ResizeInstructions, ResizeOperation};
 
 pub const RESIZED_SUBDIR: &str = "processed_images";
@@ -29,6 +29,9 @@ pub struct ImageOp {
     output_path: PathBuf,
     instr: ResizeInstructions,
     format: Format,
+    /// Which metadata of the input image to copy to the output
+    metadata: ImageMetadata,
+    keep_gps: bool,
     /// Whether we actually want to perform that op.
     /// In practice we set it to true if the output file already
     /// exists and is not stale. We do need to keep the ImageOp around for pruning though.
@@ -61,25 +64,35 @@ impl ImageOp {
                 img.write_to(&mut buffered_f, ImageFormat::Png)?;
             }
             Format::Jpeg(q) => {
-                let mut encoder = JpegEncoder::new_with_quality(&mut buffered_f, q);
+                let mut jpeg: Vec<u8> = Vec::new();
+                let mut encoder = JpegEncoder::new_with_quality(&mut jpeg, q);
                 encoder.encode_image(&img)?;
+                let metadata =
+                    PreservedMetadata::read(&self.input_path, self.metadata, self.keep_gps);
+                buffered_f.write_all(&metadata.embed_in_jpeg(jpeg))?;
             }
-            Format::WebP(q) => {
+            Format::WebP { quality, method } => {
                 let encoder = webp::Encoder::from_image(&img)
                     .map_err(|_| anyhow!("Unable to load this kind of image with webp"))?;
-                let memory = match q {
-                    Some(q) => encoder.encode(q as f32),
-                    None => encoder.encode_lossless(),
-                };
+                let mut config = webp::WebPConfig::new()
+                    .map_err(|_| anyhow!("Unable to initialize the webp encoder"))?;
+                config.lossless = quality.is_none() as i32;
+                config.alpha_compression = quality.is_some() as i32;
+                config.quality = quality.unwrap_or(75) as f32;
+                config.method = method as i32;
+                let memory = encoder
+                    .encode_advanced(&config)
+                    .map_err(|e| anyhow!("Unable to encode the image as webp: {:?}", e))?;
                 buffered_f.write_all(memory.as_bytes())?;
             }
-            Format::Avif(q) => {
+            Format::Avif { quality, speed } => {
                 let mut avif: Vec<u8> = Vec::new();
                 let color_type = match img.color().has_alpha() {
                     true => ExtendedColorType::Rgba8,
                     false => ExtendedColorType::Rgb8,
                 };
-                let encoder = AvifEncoder::new_with_speed_quality(&mut avif, 10, q.unwrap_or(70));
+                let quality = quality.unwrap_or(DEFAULT_AVIF_QUALITY);
+                let encoder = AvifEncoder::new_with_speed_quality(&mut avif, speed, quality);
                 encoder.write_image(
                     &img.as_bytes(),
                     img.dimensions().0,
@@ -132,9 +145,12 @@ impl EnqueueResponse {
 pub struct Processor {
     base_url: String,
     output_dir: PathBuf,
+    settings: ImageProcessing,
     img_ops: HashSet<ImageOp>,
     /// We want to make sure we only ever get metadata for an image once
     meta_cache: HashMap<PathBuf, ImageMeta>,
+    /// Whether we already warned that only JPEG images keep their metadata
+    warned_about_metadata: bool,
 }
 
 impl Processor {
@@ -142,8 +158,10 @@ impl Processor {
         Processor {
             output_dir: base_path.join("static").join(RESIZED_SUBDIR),
             base_url: config.make_permalink(RESIZED_SUBDIR),
+            settings: config.image_processing.clone(),
             img_ops: HashSet::default(),
             meta_cache: HashMap::default(),
+            warned_about_metadata: false,
         }
     }
 
@@ -172,9 +190,22 @@ impl Processor {
         // We will have inserted it just above
         let meta = &self.meta_cache[&input_path];
         // We get the output format
-        let format = Format::from_args(meta.is_lossy(), format, quality)?;
+        let format = Format::from_args(meta.is_lossy(), format, quality, &self.settings)?;
         // Now we have all the data we need to generate the output filename and the response
-        let filename = get_processed_filename(&input_path, &input_src, &op, &format);
+        let metadata = self.settings.metadata;
+        let keep_gps = self.settings.keep_gps;
+        if metadata != ImageMetadata::Strip
+            && matches!(format, Format::WebP { .. } | Format::Avif { .. })
+            && !self.warned_about_metadata
+        {
+            self.warned_about_metadata = true;
+            eprintln!(
+                "Warning: `image_processing.metadata` is only applied to JPEG images, the metadata of the WebP and AVIF images such as {} is stripped",
+                input_src
+            );
+        }
+        let filename =
+            get_processed_filename(&input_path, &input_src, &op, &format, metadata, keep_gps);
         let url = format!("{}{}", self.base_url, filename);
         let static_path = Path::new("static").join(RESIZED_SUBDIR).join(&filename);
         let output_path = self.output_dir.join(&filename);
@@ -186,6 +217,8 @@ impl Processor {
             output_path,
             instr,
             format,
+            metadata,
+            keep_gps,
         };
         self.img_ops.insert(img_op);
 
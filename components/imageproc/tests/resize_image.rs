@@ -1,8 +1,13 @@
+use std::collections::hash_map::DefaultHasher;
 use std::env;
+use std::hash::{Hash, Hasher};
 use std::path::{PathBuf, MAIN_SEPARATOR as SLASH};
 
 use config::Config;
-use imageproc::{fix_orientation, ImageMetaResponse, Processor, ResizeOperation};
+use imageproc::{
+    fix_orientation, read_exif_data, ExifData, GpsLocation, ImageMetaResponse, Processor,
+    ResizeOperation,
+};
 use libs::image::{self, DynamicImage, GenericImageView, Pixel};
 use libs::once_cell::sync::Lazy;
 
@@ -197,6 +202,38 @@ fn read_image_metadata_avif() {
     );
 }
 
+#[test]
+fn read_exif_data_jpg() {
+    let data = read_exif_data(TEST_IMGS.join("exif_iptc.jpg"));
+    let gps = data.gps.unwrap();
+    assert!((gps.latitude - 48.8567).abs() < 0.0001);
+    assert!((gps.longitude + 2.35).abs() < 0.0001);
+    assert_eq!(
+        data,
+        ExifData {
+            date_time: Some("2023-07-14T18:32:05".to_string()),
+            make: Some("Fujifilm".to_string()),
+            model: Some("X100V".to_string()),
+            lens: Some("Fujinon 23mm".to_string()),
+            exposure_time: Some("1/250".to_string()),
+            f_number: Some(2.8),
+            focal_length: Some(23.0),
+            iso: Some(200),
+            gps: Some(GpsLocation { altitude: Some(35.0), ..gps }),
+            title: Some("Tower".to_string()),
+            caption: Some("Eiffel tower at night".to_string()),
+            artist: Some("Jane Doe".to_string()),
+            copyright: None,
+            keywords: vec!["paris".to_string(), "night".to_string()],
+        }
+    );
+}
+
+#[test]
+fn read_exif_data_without_metadata() {
+    assert_eq!(read_exif_data(TEST_IMGS.join("png.png")), ExifData::default());
+}
+
 #[test]
 fn fix_orientation_test() {
     fn load_img_and_fix_orientation(img_name: &str) -> DynamicImage {
@@ -288,3 +325,86 @@ fn asymmetric_resize_with_exif_orientations() {
     // 8: Rotate 270 CW
     image_op_test("exif_8.jpg", "scale", Some(16), Some(32), "auto", "jpg", 16, 32, 16, 16);
 }
+
+fn resize_and_read_exif(source_img: &str, image_processing: &str) -> Option<exif::Exif> {
+    let source_path = TEST_IMGS.join(source_img);
+    let tmpdir = tempfile::tempdir().unwrap().into_path();
+    let config =
+        Config::parse(&format!("{}\n[image_processing]\n{}", CONFIG, image_processing)).unwrap();
+    let mut proc = Processor::new(tmpdir.clone(), &config);
+    let resize_op = ResizeOperation::from_args("scale", Some(16), Some(16)).unwrap();
+
+    let resp = proc.enqueue(resize_op, source_img.into(), source_path, "jpg", None).unwrap();
+
+    proc.do_process().unwrap();
+    let processed_path = tmpdir.join(PathBuf::from(&resp.static_path));
+    assert!(check_img(image::open(&processed_path).unwrap()));
+    let file = std::fs::File::open(processed_path).unwrap();
+    exif::Reader::new().read_from_container(&mut std::io::BufReader::new(file)).ok()
+}
+
+#[test]
+fn resize_image_strips_metadata_by_default() {
+    assert!(resize_and_read_exif("exif_6.jpg", "").is_none());
+}
+
+#[test]
+fn resize_image_can_keep_exif() {
+    let exif = resize_and_read_exif("exif_6.jpg", "metadata = \"keep\"").unwrap();
+    assert!(exif.get_field(exif::Tag::XResolution, exif::In::PRIMARY).is_some());
+    // The rotation has already been applied to the pixels
+    assert!(exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY).is_none());
+}
+
+#[test]
+fn resize_image_with_custom_encoder_settings() {
+    let source_path = TEST_IMGS.join("png.png");
+    let tmpdir = tempfile::tempdir().unwrap().into_path();
+    let config = Config::parse(&format!(
+        "{}\n[image_processing]\navif_speed = 8\nwebp_quality = 80\nwebp_method = 6",
+        CONFIG
+    ))
+    .unwrap();
+    let mut proc = Processor::new(tmpdir.clone(), &config);
+
+    for format in ["webp", "avif"] {
+        let resize_op = ResizeOperation::from_args("scale", Some(150), Some(150)).unwrap();
+        let resp =
+            proc.enqueue(resize_op, "png.png".into(), source_path.clone(), format, None).unwrap();
+        assert_processed_path_matches(&resp.static_path, PROCESSED_PREFIX.as_str(), format);
+    }
+    proc.do_process().unwrap();
+}
+
+fn processed_filename(image_processing: &str, format: &str) -> String {
+    let tmpdir = tempfile::tempdir().unwrap().into_path();
+    let config =
+        Config::parse(&format!("{}\n[image_processing]\n{}", CONFIG, image_processing)).unwrap();
+    let mut proc = Processor::new(tmpdir, &config);
+    let resize_op = ResizeOperation::from_args("scale", Some(150), Some(150)).unwrap();
+    let resp =
+        proc.enqueue(resize_op, "png.png".into(), TEST_IMGS.join("png.png"), format, None).unwrap();
+    resp.static_path
+}
+
+#[test]
+fn default_encoder_settings_keep_filenames_stable() {
+    // The hash used before the encoder settings were configurable
+    let resize_op = ResizeOperation::from_args("scale", Some(150), Some(150)).unwrap();
+    let mut hasher = DefaultHasher::new();
+    hasher.write(b"png.png");
+    resize_op.hash(&mut hasher);
+    hasher.write_u16(3000);
+    hasher.write(b"avif");
+    let expected = format!("png.{:016x}.avif", hasher.finish());
+    assert!(processed_filename("", "avif").ends_with(&expected));
+
+    // The metadata is only kept, and so only hashed, for JPEG
+    for format in ["png", "webp", "avif"] {
+        assert_eq!(
+            processed_filename("", format),
+            processed_filename("metadata = \"keep\"", format)
+        );
+    }
+    assert_ne!(processed_filename("", "jpg"), processed_filename("metadata = \"keep\"", "jpg"));
+}
@@ -12,10 +12,15 @@ pub use base64;
 pub use csv;
 pub use elasticlunr;
 pub use filetime;
+pub use fixed_decimal;
+pub use fluent_bundle;
+pub use fluent_syntax;
 pub use gh_emoji;
 pub use glob;
 pub use globset;
 pub use grass;
+pub use icu;
+pub use icu_experimental;
 pub use image;
 pub use lexical_sort;
 pub use minify_html;
@@ -45,3 +50,4 @@ pub use unicode_segmentation;
 pub use url;
 pub use walkdir;
 pub use webp;
+pub use writeable;
@@ -1,23 +1,32 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::result;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use libs::once_cell::sync::Lazy;
-use libs::reqwest::header::{HeaderMap, ACCEPT};
-use libs::reqwest::{blocking::Client, StatusCode};
+use libs::reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, LOCATION, RETRY_AFTER};
+use libs::reqwest::redirect::Policy;
+use libs::reqwest::{blocking::Client, blocking::Response, Method, StatusCode};
+use libs::serde_json::{self, json, Map, Value};
+use libs::sha2::{Digest, Sha256};
+use libs::time::format_description::well_known::Rfc2822;
+use libs::time::OffsetDateTime;
+use libs::url::Url;
 
 use config::LinkChecker;
 use errors::anyhow;
 
 use utils::anchors::has_anchor_id;
+use utils::fs::{create_file, read_file};
 
 pub type Result = result::Result<StatusCode, String>;
 
+/// A link is valid if it returned a success status code, `304 Not Modified` or a status code
+/// accepted for its prefix
 pub fn is_valid(res: &Result) -> bool {
-    match res {
-        Ok(ref code) => code.is_success() || *code == StatusCode::NOT_MODIFIED,
-        Err(_) => false,
-    }
+    res.is_ok()
 }
 
 pub fn message(res: &Result) -> String {
@@ -27,74 +36,371 @@ pub fn message(res: &Result) -> String {
     }
 }
 
+/// The outcome of checking a link
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkCheck {
+    pub result: Result,
+    /// The URL the link ends up at if it permanently redirects (`301` or `308`)
+    pub permanent_redirect: Option<String>,
+}
+
+/// The result of a link check and when it was made, in seconds since the epoch
+#[derive(Clone, Debug)]
+struct CheckedLink {
+    checked_at: u64,
+    check: LinkCheck,
+}
+
+impl CheckedLink {
+    fn is_fresh(&self, config: &LinkChecker) -> bool {
+        let ttl =
+            if is_valid(&self.check.result) { config.cache_ttl } else { config.cache_failure_ttl };
+        now() < self.checked_at.saturating_add(ttl)
+    }
+
+    fn to_json(&self) -> Value {
+        let mut value = match self.check.result {
+            Ok(ref code) => json!({ "checked_at": self.checked_at, "status": code.as_u16() }),
+            Err(ref error) => json!({ "checked_at": self.checked_at, "error": error }),
+        };
+        if let Some(ref redirect) = self.check.permanent_redirect {
+            value["permanent_redirect"] = json!(redirect);
+        }
+        value
+    }
+
+    fn from_json(value: &Value) -> Option<Self> {
+        let checked_at = value.get("checked_at")?.as_u64()?;
+        let result = match value.get("error").and_then(|e| e.as_str()) {
+            Some(error) => Err(error.to_string()),
+            None => {
+                let status = u16::try_from(value.get("status")?.as_u64()?).ok()?;
+                Ok(StatusCode::from_u16(status).ok()?)
+            }
+        };
+        let permanent_redirect =
+            value.get("permanent_redirect").and_then(|r| r.as_str()).map(|r| r.to_string());
+        Some(CheckedLink { checked_at, check: LinkCheck { result, permanent_redirect } })
+    }
+}
+
+/// How many redirects are followed before giving up
+const MAX_REDIRECTS: usize = 10;
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
 // Keep history of link checks so a rebuild doesn't have to check again
-static LINKS: Lazy<Arc<RwLock<HashMap<String, Result>>>> =
+static LINKS: Lazy<Arc<RwLock<HashMap<String, CheckedLink>>>> =
     Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
-// Make sure to create only a single Client so that we can reuse the connections
+// The results of the previous runs, loaded from the on-disk cache
+static PERSISTED_LINKS: Lazy<RwLock<HashMap<String, CheckedLink>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+// When the next request to a given domain (host and port) can start
+static NEXT_REQUEST_BY_DOMAIN: Lazy<Mutex<HashMap<String, Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+// Make sure to create only a single Client so that we can reuse the connections.
+// Redirects are followed by hand to find the permanent ones and to only send the headers of a
+// prefix to the URLs starting with it
 static CLIENT: Lazy<Client> = Lazy::new(|| {
     Client::builder()
         .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+        .redirect(Policy::none())
         .build()
         .expect("reqwest client build")
 });
 
-pub fn check_url(url: &str, config: &LinkChecker) -> Result {
-    {
-        let guard = LINKS.read().unwrap();
-        if let Some(res) = guard.get(url) {
-            return res.clone();
+/// Loads the results of the previous runs from the cache file, if there is one.
+/// A cache that can't be read is ignored: the links are checked again.
+pub fn load_cache(path: &Path) {
+    let links = read_file(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Map<String, Value>>(&content).ok())
+        .unwrap_or_default();
+    let mut persisted = PERSISTED_LINKS.write().unwrap();
+    for (url, value) in links {
+        if let Some(link) = CheckedLink::from_json(&value) {
+            persisted.insert(url, link);
+        }
+    }
+}
+
+/// Writes the results that are still fresh to the cache file
+pub fn save_cache(path: &Path, config: &LinkChecker) -> errors::Result<()> {
+    let mut links = Map::new();
+    for (url, link) in PERSISTED_LINKS.read().unwrap().iter() {
+        if link.is_fresh(config) {
+            links.insert(url.clone(), link.to_json());
         }
     }
+    for (url, link) in LINKS.read().unwrap().iter() {
+        links.insert(url.clone(), link.to_json());
+    }
+    create_file(path, serde_json::to_string_pretty(&links)?)
+}
+
+/// The requests are spaced out by host and port, so two sites on different ports of the same
+/// host don't slow each other down
+fn domain_key(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| Some(format!("{}:{}", u.host_str()?, u.port_or_known_default()?)))
+        .unwrap_or_default()
+}
+
+/// The key of a link in the caches. The result of a check depends on the prefix options that
+/// apply to the link so they are part of the key, hashed to not write the headers to disk.
+fn cache_key(url: &str, config: &LinkChecker) -> String {
+    let mut options = config.options_for(url).peekable();
+    if options.peek().is_none() {
+        return url.to_string();
+    }
+    let mut hasher = Sha256::new();
+    for option in options {
+        let mut headers: Vec<_> = option.headers.iter().collect();
+        headers.sort();
+        hasher.update(
+            format!("{}\n{:?}\n{:?}\n", option.prefix, headers, option.accepted_status).as_bytes(),
+        );
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    format!("{} {}", url, &hash[..16])
+}
+
+/// Waits until a request to that domain can start, respecting the minimum interval between
+/// two requests to the same domain
+fn wait_for_domain(domain: &str, interval: Duration) {
+    let start = {
+        let mut next_requests = NEXT_REQUEST_BY_DOMAIN.lock().unwrap();
+        let now = Instant::now();
+        let start = next_requests.get(domain).map_or(now, |next| (*next).max(now));
+        next_requests.insert(domain.to_string(), start + interval);
+        start
+    };
+    thread::sleep(start.saturating_duration_since(Instant::now()));
+}
+
+/// Prevents any request to that domain before the given delay, eg when it sent a `Retry-After`
+fn delay_domain(domain: &str, delay: Duration) {
+    let mut next_requests = NEXT_REQUEST_BY_DOMAIN.lock().unwrap();
+    let until = Instant::now() + delay;
+    let next = next_requests.entry(domain.to_string()).or_insert(until);
+    *next = (*next).max(until);
+}
+
+/// The `Retry-After` header is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    // HTTP dates are always in GMT, which is written as an offset in RFC 2822
+    let date = value.strip_suffix("GMT").map(|v| format!("{}+0000", v))?;
+    let date = OffsetDateTime::parse(&date, &Rfc2822).ok()?;
+    let seconds = (date - OffsetDateTime::now_utc()).whole_seconds();
+    Some(Duration::from_secs(seconds.max(0) as u64))
+}
+
+/// How long to wait before trying again, or `None` if the request should not be retried
+fn retry_delay(
+    response: &result::Result<Response, libs::reqwest::Error>,
+    attempt: u32,
+    config: &LinkChecker,
+) -> Option<Duration> {
+    if attempt >= config.retries {
+        return None;
+    }
+    let backoff = Duration::from_millis(config.retry_backoff.saturating_mul(1 << attempt.min(16)));
+
+    match response {
+        Ok(response) => match response.status() {
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+                let retry_after = response
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after);
+                match retry_after {
+                    Some(delay) if delay > Duration::from_secs(config.max_retry_after) => None,
+                    Some(delay) => Some(delay),
+                    None => Some(backoff),
+                }
+            }
+            StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => Some(backoff),
+            _ => None,
+        },
+        Err(e) if e.is_timeout() => Some(backoff),
+        Err(_) => None,
+    }
+}
 
+/// The headers sent to that URL: the default ones and the ones of its prefixes
+fn headers_for(url: &str, config: &LinkChecker) -> HeaderMap {
     let mut headers = HeaderMap::new();
     headers.insert(ACCEPT, "text/html".parse().unwrap());
     headers.append(ACCEPT, "*/*".parse().unwrap());
+    for options in config.options_for(url) {
+        for (name, value) in &options.headers {
+            // Both are validated when loading the config
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value))
+            {
+                headers.insert(name, value);
+            }
+        }
+    }
+    headers
+}
 
-    // TODO: pass the client to the check_url, do not pass the config
-
-    let check_anchor = !config.skip_anchor_prefixes.iter().any(|prefix| url.starts_with(prefix));
+/// Makes a single request, waiting for its turn on the domain and retrying when the server
+/// is overloaded or rate limiting
+fn send(
+    method: &Method,
+    url: &str,
+    config: &LinkChecker,
+) -> result::Result<Response, libs::reqwest::Error> {
+    let domain = domain_key(url);
+    let headers = headers_for(url, config);
+
+    let mut attempt = 0;
+    loop {
+        wait_for_domain(&domain, Duration::from_millis(config.request_interval));
+        let response = CLIENT.request(method.clone(), url).headers(headers.clone()).send();
+        match retry_delay(&response, attempt, config) {
+            Some(delay) => {
+                delay_domain(&domain, delay);
+                attempt += 1;
+            }
+            None => return response,
+        }
+    }
+}
 
-    // Need to actually do the link checking
-    let res = match CLIENT.get(url).headers(headers).send() {
-        Ok(ref mut response) if check_anchor && has_anchor(url) => {
-            let body = {
-                let mut buf: Vec<u8> = vec![];
-                response.copy_to(&mut buf).unwrap();
-                match String::from_utf8(buf) {
-                    Ok(s) => s,
-                    Err(_) => return Err("The page didn't return valid UTF-8".to_string()),
+/// Requests the URL, following the redirects.
+/// Also returns where the last permanent redirect of the chain points to, if there is one.
+fn fetch(
+    method: &Method,
+    url: &str,
+    config: &LinkChecker,
+) -> result::Result<(Response, Option<String>), String> {
+    let mut current = url.to_string();
+    let mut permanent = None;
+    for hop in 0..=MAX_REDIRECTS {
+        let response = send(method, &current, config).map_err(|e| e.to_string())?;
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .and_then(|l| Url::parse(&current).ok()?.join(l).ok());
+        match location {
+            Some(location) if status.is_redirection() && hop < MAX_REDIRECTS => {
+                current = location.to_string();
+                if status == StatusCode::MOVED_PERMANENTLY
+                    || status == StatusCode::PERMANENT_REDIRECT
+                {
+                    permanent = Some(current.clone());
                 }
-            };
+            }
+            _ => return Ok((response, permanent)),
+        }
+    }
+    Err(format!("Too many redirects (more than {})", MAX_REDIRECTS))
+}
+
+/// Whether the status means that the server doesn't handle HEAD requests. Some sites also
+/// forbid them while allowing a GET to the same URL.
+fn rejects_head(status: StatusCode) -> bool {
+    status == StatusCode::METHOD_NOT_ALLOWED
+        || status == StatusCode::NOT_IMPLEMENTED
+        || status == StatusCode::FORBIDDEN
+}
+
+fn status_error(status: StatusCode) -> String {
+    if status.is_informational() {
+        format!("Informational status code ({}) received", status)
+    } else if status.is_redirection() {
+        format!("Redirection status code ({}) received", status)
+    } else if status.is_client_error() {
+        format!("Client error status code ({}) received", status)
+    } else if status.is_server_error() {
+        format!("Server error status code ({}) received", status)
+    } else {
+        format!("Non-success status code ({}) received", status)
+    }
+}
+
+pub fn check_url(url: &str, config: &LinkChecker) -> Result {
+    check_link(url, config).result
+}
+
+/// Same as `check_url` but also returns where the link permanently redirects to, if it does
+pub fn check_link(url: &str, config: &LinkChecker) -> LinkCheck {
+    let key = cache_key(url, config);
+    {
+        let guard = LINKS.read().unwrap();
+        if let Some(link) = guard.get(&key) {
+            return link.check.clone();
+        }
+    }
+    if let Some(link) = PERSISTED_LINKS.read().unwrap().get(&key) {
+        if link.is_fresh(config) {
+            return link.check.clone();
+        }
+    }
+
+    // TODO: pass the client to the check_url, do not pass the config
+
+    let check_anchor = !config.skip_anchor_prefixes.iter().any(|prefix| url.starts_with(prefix))
+        && has_anchor(url);
+    let is_accepted = |status: StatusCode| {
+        status.is_success()
+            || status == StatusCode::NOT_MODIFIED
+            || config.options_for(url).any(|o| o.accepted_status.contains(&status.as_u16()))
+    };
 
-            match check_page_for_anchor(url, body) {
-                Ok(_) => Ok(response.status()),
-                Err(e) => Err(e.to_string()),
+    // Need to actually do the link checking.
+    // The anchors are in the body so they need a GET, otherwise a HEAD is enough to know if
+    // the link works. A GET is only made if the server doesn't seem to handle HEAD requests:
+    // any other status, like a 404, is the answer
+    let fetched = if check_anchor {
+        fetch(&Method::GET, url, config)
+    } else {
+        match fetch(&Method::HEAD, url, config) {
+            Ok((response, _)) if rejects_head(response.status()) => {
+                fetch(&Method::GET, url, config)
             }
+            // Some servers drop the connection on HEAD requests
+            Err(_) => fetch(&Method::GET, url, config),
+            fetched => fetched,
         }
-        Ok(response) => {
-            if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
-                Ok(response.status())
+    };
+
+    let (result, permanent_redirect) = match fetched {
+        Ok((mut response, redirect)) => {
+            let status = response.status();
+            let result = if !is_accepted(status) {
+                Err(status_error(status))
+            } else if check_anchor && status.is_success() {
+                let mut buf: Vec<u8> = vec![];
+                match response.copy_to(&mut buf).map(|_| String::from_utf8(buf)) {
+                    Ok(Ok(body)) => {
+                        check_page_for_anchor(url, body).map(|_| status).map_err(|e| e.to_string())
+                    }
+                    _ => Err("The page didn't return valid UTF-8".to_string()),
+                }
             } else {
-                let error_string = if response.status().is_informational() {
-                    format!("Informational status code ({}) received", response.status())
-                } else if response.status().is_redirection() {
-                    format!("Redirection status code ({}) received", response.status())
-                } else if response.status().is_client_error() {
-                    format!("Client error status code ({}) received", response.status())
-                } else if response.status().is_server_error() {
-                    format!("Server error status code ({}) received", response.status())
-                } else {
-                    format!("Non-success status code ({}) received", response.status())
-                };
-
-                Err(error_string)
-            }
+                Ok(status)
+            };
+            (result, redirect)
         }
-        Err(e) => Err(e.to_string()),
+        Err(e) => (Err(e), None),
     };
 
-    LINKS.write().unwrap().insert(url.to_string(), res.clone());
-    res
+    let check = LinkCheck { result, permanent_redirect };
+    LINKS.write().unwrap().insert(key, CheckedLink { checked_at: now(), check: check.clone() });
+    check
 }
 
 fn has_anchor(url: &str) -> bool {
@@ -120,9 +426,15 @@ fn check_page_for_anchor(url: &str, body: String) -> errors::Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use std::collections::HashMap;
+
     use super::{
-        check_page_for_anchor, check_url, has_anchor, is_valid, message, LinkChecker, LINKS,
+        check_link, check_page_for_anchor, check_url, domain_key, has_anchor, is_valid, load_cache,
+        message, parse_retry_after, save_cache, LinkChecker, LINKS,
     };
+    use config::PrefixOptions;
     use libs::reqwest::StatusCode;
 
     // NOTE: HTTP mock paths below are randomly generated to avoid name
@@ -391,4 +703,204 @@ mod tests {
         let nonexistent = format!("{}{}", server.url(), "/guvqcqwmth#nonexistent");
         assert!(!is_valid(&check_url(&nonexistent, &config)));
     }
+
+    #[test]
+    fn retries_when_rate_limited() {
+        let mut server = mockito::Server::new();
+        let m = server
+            .mock("GET", "/s0ajwq7ntr")
+            .with_status(429)
+            .with_header("Retry-After", "0")
+            .expect(3)
+            .create();
+
+        let config = LinkChecker { request_interval: 0, ..Default::default() };
+        let url = format!("{}{}", server.url(), "/s0ajwq7ntr");
+        let res = check_url(&url, &config);
+        m.assert();
+        assert!(!is_valid(&res));
+        assert_eq!(message(&res), "Client error status code (429 Too Many Requests) received");
+    }
+
+    #[test]
+    fn does_not_wait_for_too_long_retry_after() {
+        let mut server = mockito::Server::new();
+        let m = server
+            .mock("GET", "/x2mdk4qvhe")
+            .with_status(503)
+            .with_header("Retry-After", "3600")
+            .expect(1)
+            .create();
+
+        let config = LinkChecker { request_interval: 0, ..Default::default() };
+        let url = format!("{}{}", server.url(), "/x2mdk4qvhe");
+        assert!(!is_valid(&check_url(&url, &config)));
+        m.assert();
+    }
+
+    #[test]
+    fn can_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn can_reuse_results_of_previous_runs() {
+        let mut server = mockito::Server::new();
+        let ok = server.mock("GET", "/f8ykq2nd0b").with_status(200).expect(1).create();
+        let broken = server.mock("GET", "/pq4w7zjm1c").with_status(404).expect(2).create();
+        let ok_url = format!("{}{}", server.url(), "/f8ykq2nd0b");
+        let broken_url = format!("{}{}", server.url(), "/pq4w7zjm1c");
+
+        let config = LinkChecker { cache_failure_ttl: 0, ..Default::default() };
+        assert!(is_valid(&check_url(&ok_url, &config)));
+        assert!(!is_valid(&check_url(&broken_url, &config)));
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache_path = cache_dir.path().join("results.json");
+        save_cache(&cache_path, &config).unwrap();
+
+        // Simulates a new run
+        LINKS.write().unwrap().remove(&ok_url);
+        LINKS.write().unwrap().remove(&broken_url);
+        load_cache(&cache_path);
+
+        // Only the failure is checked again since its TTL is 0
+        assert!(is_valid(&check_url(&ok_url, &config)));
+        assert!(!is_valid(&check_url(&broken_url, &config)));
+        ok.assert();
+        broken.assert();
+    }
+
+    #[test]
+    fn uses_head_requests_first() {
+        let mut server = mockito::Server::new();
+        let head = server.mock("HEAD", "/m2ovz0gk5t").with_status(200).expect(1).create();
+        let get = server.mock("GET", "/m2ovz0gk5t").with_status(200).expect(0).create();
+
+        let url = format!("{}{}", server.url(), "/m2ovz0gk5t");
+        assert!(is_valid(&check_url(&url, &LinkChecker::default())));
+        head.assert();
+        get.assert();
+    }
+
+    #[test]
+    fn falls_back_to_get_when_head_fails() {
+        let mut server = mockito::Server::new();
+        let _head = server.mock("HEAD", "/4hdy1vq8xe").with_status(405).create();
+        let get = server.mock("GET", "/4hdy1vq8xe").with_status(200).expect(1).create();
+
+        let url = format!("{}{}", server.url(), "/4hdy1vq8xe");
+        assert!(is_valid(&check_url(&url, &LinkChecker::default())));
+        get.assert();
+    }
+
+    #[test]
+    fn does_not_fall_back_to_get_on_broken_links() {
+        let mut server = mockito::Server::new();
+        let head = server.mock("HEAD", "/c9vj4ebn2k").with_status(404).expect(1).create();
+        let get = server.mock("GET", "/c9vj4ebn2k").with_status(200).expect(0).create();
+
+        let url = format!("{}{}", server.url(), "/c9vj4ebn2k");
+        let res = check_url(&url, &LinkChecker::default());
+        assert_eq!(message(&res), "Client error status code (404 Not Found) received");
+        head.assert();
+        get.assert();
+    }
+
+    #[test]
+    fn can_find_permanent_redirects() {
+        let mut server = mockito::Server::new();
+        let _m1 = server
+            .mock("HEAD", "/gh5sk2pl0q")
+            .with_status(301)
+            .with_header("Location", "/gq7z3xnm1d")
+            .create();
+        let _m2 = server.mock("HEAD", "/gq7z3xnm1d").with_status(200).create();
+        let _m3 = server
+            .mock("HEAD", "/t3bq9wvx6l")
+            .with_status(302)
+            .with_header("Location", "/gq7z3xnm1d")
+            .create();
+
+        let url = format!("{}{}", server.url(), "/gh5sk2pl0q");
+        let check = check_link(&url, &LinkChecker::default());
+        assert!(is_valid(&check.result));
+        assert_eq!(check.permanent_redirect, Some(format!("{}{}", server.url(), "/gq7z3xnm1d")));
+
+        // Temporary redirects are fine
+        let url = format!("{}{}", server.url(), "/t3bq9wvx6l");
+        assert_eq!(check_link(&url, &LinkChecker::default()).permanent_redirect, None);
+
+        // A permanent redirect after a temporary one is found too
+        let _m4 = server
+            .mock("HEAD", "/w6ud2hcr8e")
+            .with_status(302)
+            .with_header("Location", "/gh5sk2pl0q")
+            .create();
+        let url = format!("{}{}", server.url(), "/w6ud2hcr8e");
+        assert_eq!(
+            check_link(&url, &LinkChecker::default()).permanent_redirect,
+            Some(format!("{}{}", server.url(), "/gq7z3xnm1d"))
+        );
+    }
+
+    #[test]
+    fn can_send_headers_and_accept_status_per_prefix() {
+        let mut server = mockito::Server::new();
+        let _m1 = server
+            .mock("HEAD", "/private/x0rk5cbw2y")
+            .match_header("Cookie", "session=1234")
+            .with_status(200)
+            .create();
+        let _m2 = server.mock("HEAD", "/bots/p8lc3ufy9s").with_status(403).create();
+        let _m3 = server.mock("GET", "/bots/p8lc3ufy9s").with_status(403).create();
+
+        let config = LinkChecker {
+            prefix_options: vec![
+                PrefixOptions {
+                    prefix: format!("{}/private/", server.url()),
+                    headers: HashMap::from([("Cookie".to_string(), "session=1234".to_string())]),
+                    ..Default::default()
+                },
+                PrefixOptions {
+                    prefix: format!("{}/bots/", server.url()),
+                    accepted_status: vec![403],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let private = format!("{}{}", server.url(), "/private/x0rk5cbw2y");
+        assert!(is_valid(&check_url(&private, &config)));
+        let bots = format!("{}{}", server.url(), "/bots/p8lc3ufy9s");
+        assert_eq!(check_url(&bots, &config), Ok(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn prefix_options_are_part_of_the_cache_key() {
+        let mut server = mockito::Server::new();
+        let m = server.mock("GET", "/r5tn0zqa8d").with_status(404).expect(2).create();
+        let url = format!("{}{}", server.url(), "/r5tn0zqa8d");
+
+        assert!(!is_valid(&check_url(&url, &LinkChecker::default())));
+        let config = LinkChecker {
+            prefix_options: vec![PrefixOptions {
+                prefix: server.url(),
+                accepted_status: vec![404],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(is_valid(&check_url(&url, &config)));
+        m.assert();
+    }
+
+    #[test]
+    fn rate_limits_by_host_and_port() {
+        assert_eq!(domain_key("http://127.0.0.1:1234/a"), "127.0.0.1:1234");
+        assert_eq!(domain_key("http://127.0.0.1:5678/a"), "127.0.0.1:5678");
+        assert_eq!(domain_key("https://example.com/a"), "example.com:443");
+    }
 }
@@ -38,13 +38,13 @@ where
         let cache = match Self::read_cache(&cache_file) {
             Ok(maybe_cache) => match maybe_cache {
                 Some(c) => {
-                    println!("Loaded cache from {:?} ({:?})", cache_file, c.len());
+                    console::plain(&format!("Loaded cache from {:?} ({:?})", cache_file, c.len()));
                     c
                 }
                 None => DashMap::new(),
             },
             Err(e) => {
-                println!("Failed to load cache: {}", e);
+                console::warn(&format!("Failed to load cache: {}", e));
                 DashMap::new()
             }
         };
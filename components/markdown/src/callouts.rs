@@ -1,91 +1,175 @@
-use libs::pulldown_cmark::{html::ToClass, AdmonitionTagCallback};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
 
-/// Performs case-insensitive byte string comparison using bitwise operation
-/// | 32 to lowercase the ASCII characters
-pub const fn case_insensitive_eq(a: &[u8], b: &[u8]) -> bool {
-    if a.len() != b.len() {
-        return false;
+use config::Config;
+use libs::once_cell::sync::Lazy;
+use libs::pulldown_cmark::{html::ToClass, AdmonitionTagCallback, Event, Options, Parser, Tag};
+use libs::regex::Regex;
+
+use crate::shortcode::SHORTCODE_PLACEHOLDER;
+
+/// `> [!kind]+ Some title`, possibly in nested block quotes
+static CALLOUT_HEADER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^([ \t]*(?:>[ \t]?)+)\[!([A-Za-z0-9_-]+)\]([+-]?)([ \t]*.*?)[ \t]*$").unwrap()
+});
+
+/// The name used in `> [!name]`, once it's been checked to be a known kind or alias
+#[derive(Clone, Debug, Copy, Eq, PartialEq)]
+pub struct CalloutTag<'input>(pub &'input str);
+
+impl<'input> CalloutTag<'input> {
+    pub fn as_str(&self) -> &'input str {
+        self.0
     }
+}
 
-    let mut i = 0;
-    while i < a.len() {
-        if a[i] | 32 != b[i] | 32 {
-            return false;
-        }
-        i += 1;
+impl<'a, 'input: 'a> ToClass<'a> for CalloutTag<'input> {
+    fn to_class(&self) -> &'a str {
+        self.0
     }
-    true
 }
 
-#[derive(Clone, Debug, Copy, Eq, PartialEq)]
-pub enum ObsidianCalloutType {
-    Note,
-    Tip,
-    Warning,
-    Important,
-    Info,
-    Question,
-    Error,
+/// Only turns block quotes into callouts for the built-in and configured kinds
+pub struct CalloutsHandler<'c> {
+    pub config: &'c Config,
 }
 
-impl ObsidianCalloutType {
-    const VARIANTS: &'static [(&'static str, Self)] = &[
-        ("note", Self::Note),
-        ("tip", Self::Tip),
-        ("warning", Self::Warning),
-        ("important", Self::Important),
-        ("info", Self::Info),
-        ("question", Self::Question),
-        ("error", Self::Error),
-    ];
-
-    /// Computes the maximum length of variant strings at compile-time
-    const MAX_VARIANT_LENGTH: usize = {
-        let mut max_len = 0;
-        let mut i = 0;
-        while i < Self::VARIANTS.len() {
-            if Self::VARIANTS[i].0.len() > max_len {
-                max_len = Self::VARIANTS[i].0.len();
-            }
-            i += 1;
-        }
-        max_len
-    };
-
-    pub fn as_str(&self) -> &'static str {
-        Self::VARIANTS
-            .iter()
-            .find(|(_, variant)| variant == self)
-            .map(|(name, _)| *name)
-            .expect("Variant should always have a string representation") // Safety: variant is always valid
+impl<'c, 'input> AdmonitionTagCallback<'input> for CalloutsHandler<'c> {
+    type DataKind = CalloutTag<'input>;
+
+    fn handle_admonition_tag(&mut self, tag: &'input str) -> Option<Self::DataKind> {
+        self.config.markdown.get_callout_kind(tag).map(|_| CalloutTag(tag))
     }
+}
 
-    pub fn from_str(s: &str) -> Option<Self> {
-        // Early return for strings that are too long
-        if s.len() > Self::MAX_VARIANT_LENGTH {
-            return None;
+/// What follows `[!kind]` on the first line of a callout
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CalloutHeader {
+    /// `Some(true)` for `+`, `Some(false)` for `-` and None if the callout can't be folded
+    pub fold: Option<bool>,
+    /// The custom title of the callout, if any
+    pub title: Option<String>,
+}
+
+/// The byte ranges of the fenced and indented code blocks of the content
+fn code_block_ranges(content: &str) -> Vec<Range<usize>> {
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_FOOTNOTES);
+    opts.insert(Options::ENABLE_STRIKETHROUGH);
+    opts.insert(Options::ENABLE_TASKLISTS);
+    opts.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    Parser::new_ext(content, opts)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// CommonMark only recognises `> [!kind]` alone on its line so the fold marker and the
+/// title are replaced by spaces, keeping every offset of the content intact for the
+/// shortcodes. The headers are returned by the offset of the start of their line.
+pub fn extract_callout_headers<'a>(
+    content: &'a str,
+    config: &Config,
+) -> (Cow<'a, str>, HashMap<usize, CalloutHeader>) {
+    let mut headers = HashMap::new();
+    // Avoids parsing the content again when there can't be any callout
+    if !content.contains("[!") {
+        return (Cow::Borrowed(content), headers);
+    }
+    let mut out: Option<String> = None;
+    let code_blocks = code_block_ranges(content);
+    let mut previous_depth = 0;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        let unquoted = line.trim_start_matches(['>', ' ', '\t']);
+        let depth = line[..line.len() - unquoted.len()].matches('>').count();
+        // A callout can only start on the first line of a block quote
+        let starts_quote = depth > previous_depth;
+        previous_depth = depth;
+        // Fenced code blocks start on their own line but indented ones can start after the `>`
+        let in_code = code_blocks.iter().any(|r| r.start < offset && r.end > line_start);
+        if in_code || !starts_quote {
+            continue;
+        }
+
+        let Some(caps) = CALLOUT_HEADER_RE.captures(line) else {
+            continue;
+        };
+        if config.markdown.get_callout_kind(&caps[2]).is_none()
+            || caps[4].contains(SHORTCODE_PLACEHOLDER)
+        {
+            continue;
+        }
+
+        let fold = match &caps[3] {
+            "+" => Some(true),
+            "-" => Some(false),
+            _ => None,
+        };
+        let title = caps[4].trim();
+        let title = if title.is_empty() { None } else { Some(title.to_string()) };
+        if fold.is_none() && title.is_none() {
+            continue;
         }
 
-        let input_bytes = s.as_bytes();
-        Self::VARIANTS
-            .iter()
-            .find(|(name, _)| case_insensitive_eq(input_bytes, name.as_bytes()))
-            .map(|(_, variant)| *variant)
+        let tail = caps.get(3).unwrap().start()..caps.get(4).unwrap().end();
+        let out = out.get_or_insert_with(|| content.to_string());
+        let tail = (line_start + tail.start)..(line_start + tail.end);
+        out.replace_range(tail.clone(), &" ".repeat(tail.len()));
+        headers.insert(line_start, CalloutHeader { fold, title });
+    }
+
+    match out {
+        Some(out) => (Cow::Owned(out), headers),
+        None => (Cow::Borrowed(content), headers),
     }
 }
 
-impl<'input> AdmonitionTagCallback<'input> for ObsidianCalloutsHandler {
-    type DataKind = ObsidianCalloutType;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn handle_admonition_tag(&mut self, tag: &'input str) -> Option<Self::DataKind> {
-        ObsidianCalloutType::from_str(tag)
+    #[test]
+    fn can_extract_callout_headers() {
+        let config = Config::default();
+        let content = "Hello\n\n> [!tip]- Some *title*\n> Body\n\n> > [!NOTE]+\n> > Hey\n";
+        let (out, headers) = extract_callout_headers(content, &config);
+        assert_eq!(out, "Hello\n\n> [!tip]              \n> Body\n\n> > [!NOTE] \n> > Hey\n");
+        assert_eq!(
+            headers[&7],
+            CalloutHeader { fold: Some(false), title: Some("Some *title*".to_string()) }
+        );
+        assert_eq!(headers[&38], CalloutHeader { fold: Some(true), title: None });
     }
-}
 
-impl<'a> ToClass<'a> for ObsidianCalloutType {
-    fn to_class(&self) -> &'a str {
-        self.as_str()
+    #[test]
+    fn leaves_other_block_quotes_alone() {
+        let config = Config::default();
+        let content =
+            "> [!nope] Title\n\n> Text\n> [!tip] Title\n\n```\n> [!tip] Title\n```\n\n> [!tip]\n";
+        let (out, headers) = extract_callout_headers(content, &config);
+        assert_eq!(out, content);
+        assert!(headers.is_empty());
     }
-}
 
-pub struct ObsidianCalloutsHandler;
+    #[test]
+    fn leaves_indented_code_blocks_alone() {
+        let config = Config::default();
+        let content =
+            "Text\n\n    > [!tip] Title\n\n>     > [!note]- Title\n\n> Quote\n>\n>     > [!tip]+\n";
+        let (out, headers) = extract_callout_headers(content, &config);
+        assert_eq!(out, content);
+        assert!(headers.is_empty());
+    }
+}
@@ -1,7 +1,16 @@
 use std::{ops::RangeInclusive, path::PathBuf};
 
+use errors::{bail, Context, Result};
+use libs::once_cell::sync::Lazy;
+use libs::regex::Regex;
 use utils::fs::read_file;
 
+/// `// region: name` and `// endregion`, also matching `#region name` and other comments
+static REGION_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[ \t]*(?://|#|--|;+|/\*|<!--)[ \t]*#?(end)?region\b[ \t]*:?[ \t]*([\w.-]*)")
+        .unwrap()
+});
+
 fn parse_range(s: &str) -> Option<RangeInclusive<usize>> {
     match s.find('-') {
         Some(dash) => {
@@ -19,6 +28,60 @@ fn parse_range(s: &str) -> Option<RangeInclusive<usize>> {
     }
 }
 
+/// Keeps the lines inside the region, without the region markers. An `endregion` without a
+/// name closes the last region opened.
+fn extract_region(content: &str, name: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut open = Vec::new();
+    let mut found = false;
+
+    for line in content.split_inclusive('\n') {
+        if let Some(caps) = REGION_RE.captures(line) {
+            let marker = caps.get(2).map_or("", |m| m.as_str());
+            if caps.get(1).is_some() {
+                if let Some(i) = open.iter().rposition(|n| marker.is_empty() || *n == marker) {
+                    open.remove(i);
+                }
+            } else {
+                found |= marker == name;
+                open.push(marker);
+            }
+            continue;
+        }
+        if open.contains(&name) {
+            out.push_str(line);
+        }
+    }
+
+    found.then_some(out)
+}
+
+/// Keeps the lines in the ranges of the `lines` option, which all have to be valid and within
+/// the content
+fn select_lines(content: &str, lines: &str) -> Result<String> {
+    let count = content.split_inclusive('\n').count();
+    let mut ranges = Vec::new();
+    for range in lines.split(' ').filter(|r| !r.is_empty()) {
+        let Some(parsed) = parse_range(range) else {
+            bail!("Invalid range `{}` in `lines={}`", range, lines);
+        };
+        if *parsed.start() == 0 || *parsed.end() > count {
+            bail!("The range `{}` of `lines` is not within the {} lines of the code", range, count);
+        }
+        ranges.push(parsed);
+    }
+    if ranges.is_empty() {
+        bail!("`lines` needs at least one range");
+    }
+
+    Ok(content
+        .split_inclusive('\n')
+        .enumerate()
+        .filter(|(i, _)| ranges.iter().any(|r| r.contains(&(i + 1))))
+        .map(|(_, line)| line)
+        .collect())
+}
+
 #[derive(Debug)]
 pub struct FenceSettings<'a> {
     pub language: Option<&'a str>,
@@ -26,9 +89,17 @@ pub struct FenceSettings<'a> {
     pub line_number_start: usize,
     pub highlight_lines: Vec<RangeInclusive<usize>>,
     pub hide_lines: Vec<RangeInclusive<usize>>,
+    pub focus_lines: Vec<RangeInclusive<usize>>,
+    pub diff: bool,
+    pub annotate: bool,
+    /// Whether the `[!code ...]` comments of the lines are applied
+    pub markers: bool,
     pub name: Option<&'a str>,
     pub enable_copy: bool,
     pub include: Option<&'a str>,
+    /// Only checked when including the file, to be able to error on invalid ranges
+    pub include_lines: Option<&'a str>,
+    pub include_region: Option<&'a str>,
 }
 
 impl<'a> FenceSettings<'a> {
@@ -39,9 +110,15 @@ impl<'a> FenceSettings<'a> {
             line_number_start: 1,
             highlight_lines: Vec::new(),
             hide_lines: Vec::new(),
+            focus_lines: Vec::new(),
+            diff: false,
+            annotate: false,
+            markers: false,
             name: None,
             enable_copy: false,
             include: None,
+            include_lines: None,
+            include_region: None,
         };
 
         for token in FenceIter::new(fence_info) {
@@ -51,20 +128,62 @@ impl<'a> FenceSettings<'a> {
                 FenceToken::InitialLineNumber(l) => me.line_number_start = l,
                 FenceToken::HighlightLines(lines) => me.highlight_lines.extend(lines),
                 FenceToken::HideLines(lines) => me.hide_lines.extend(lines),
+                FenceToken::FocusLines(lines) => me.focus_lines.extend(lines),
+                FenceToken::Diff => me.diff = true,
+                FenceToken::Annotate => me.annotate = true,
+                FenceToken::Markers => me.markers = true,
                 FenceToken::Name(n) => me.name = Some(n),
                 FenceToken::EnableCopy => me.enable_copy = true,
                 FenceToken::Include(file) => me.include = Some(file),
+                FenceToken::IncludeLines(lines) => me.include_lines = Some(lines),
+                FenceToken::IncludeRegion(region) => me.include_region = Some(region),
             }
         }
+        // A ```diff block is highlighted as a diff rather than in another language
+        if me.diff && me.language.is_none() {
+            me.language = Some("diff");
+            me.diff = false;
+        }
 
         me
     }
 
-    pub fn include(&self, base: Option<&PathBuf>) -> Option<String> {
-        let path = base?.join(self.include.as_ref()?);
-        let res = read_file(&path);
+    /// Reads the file of the `include` option from the directory of the page or from `dirs`,
+    /// only looking in `dirs` if it starts with a `/`, and keeps the `region` and `lines` if set.
+    /// Returns the path of the file and the code.
+    pub fn include(
+        &self,
+        base: Option<&PathBuf>,
+        dirs: &[PathBuf],
+    ) -> Result<Option<(PathBuf, String)>> {
+        let Some(file) = self.include else {
+            return Ok(None);
+        };
+        let (file, base) = match file.strip_prefix('/') {
+            Some(file) => (file, None),
+            None => (file, base),
+        };
+        let path = base.into_iter().chain(dirs).map(|dir| dir.join(file)).find(|p| p.is_file());
+        let Some(path) = path else {
+            bail!(
+                "Included file `{}` not found in the page directory, the site root or the `include_roots`",
+                file
+            );
+        };
 
-        res.ok()
+        let mut content = read_file(&path)?;
+        if let Some(region) = self.include_region {
+            content = match extract_region(&content, region) {
+                Some(content) => content,
+                None => bail!("Region `{}` not found in the included file `{}`", region, file),
+            };
+        }
+        if let Some(lines) = self.include_lines {
+            content = select_lines(&content, lines)
+                .with_context(|| format!("Invalid `lines` for the included file `{}`", file))?;
+        }
+
+        Ok(Some((path, content)))
     }
 }
 
@@ -75,9 +194,15 @@ enum FenceToken<'a> {
     InitialLineNumber(usize),
     HighlightLines(Vec<RangeInclusive<usize>>),
     HideLines(Vec<RangeInclusive<usize>>),
+    FocusLines(Vec<RangeInclusive<usize>>),
+    Diff,
+    Annotate,
+    Markers,
     Name(&'a str),
     EnableCopy,
     Include(&'a str),
+    IncludeLines(&'a str),
+    IncludeRegion(&'a str),
 }
 
 struct FenceIter<'a> {
@@ -124,6 +249,13 @@ impl<'a> Iterator for FenceIter<'a> {
                     let ranges = Self::parse_ranges(tok_split.next());
                     return Some(FenceToken::HideLines(ranges));
                 }
+                "focus_lines" => {
+                    let ranges = Self::parse_ranges(tok_split.next());
+                    return Some(FenceToken::FocusLines(ranges));
+                }
+                "diff" => return Some(FenceToken::Diff),
+                "annotate" => return Some(FenceToken::Annotate),
+                "markers" => return Some(FenceToken::Markers),
                 "name" => {
                     if let Some(n) = tok_split.next() {
                         return Some(FenceToken::Name(n));
@@ -132,7 +264,15 @@ impl<'a> Iterator for FenceIter<'a> {
                 "copy" => return Some(FenceToken::EnableCopy),
                 "include" => {
                     if let Some(file) = tok_split.next() {
-                        return Some(FenceToken::Include(file));
+                        return Some(FenceToken::Include(file.trim().trim_matches('"')));
+                    }
+                }
+                "lines" => {
+                    return Some(FenceToken::IncludeLines(tok_split.next().unwrap_or("").trim()));
+                }
+                "region" => {
+                    if let Some(region) = tok_split.next() {
+                        return Some(FenceToken::IncludeRegion(region.trim().trim_matches('"')));
                     }
                 }
                 lang => {
@@ -146,3 +286,38 @@ impl<'a> Iterator for FenceIter<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "use a;\n// region: setup\nlet x = 1;\n# region inner\nlet y = 2;\n# endregion\nlet z = 3;\n// endregion: setup\nend();\n";
+
+    #[test]
+    fn can_extract_regions() {
+        assert_eq!(extract_region(CODE, "setup").unwrap(), "let x = 1;\nlet y = 2;\nlet z = 3;\n");
+        assert_eq!(extract_region(CODE, "inner").unwrap(), "let y = 2;\n");
+        assert!(extract_region(CODE, "nope").is_none());
+    }
+
+    #[test]
+    fn can_select_lines() {
+        assert_eq!(select_lines(CODE, "1 9-8").unwrap(), "use a;\n// endregion: setup\nend();\n");
+    }
+
+    #[test]
+    fn errors_on_invalid_lines() {
+        for lines in ["abc", "1 2-x", "", "0-2", "8-10", "500-600"] {
+            assert!(select_lines(CODE, lines).is_err(), "{}", lines);
+        }
+    }
+
+    #[test]
+    fn can_parse_include_options() {
+        let fence = FenceSettings::new("rust,include=\"src/lib.rs\",lines=1-3 5,region=setup");
+        assert_eq!(fence.language, Some("rust"));
+        assert_eq!(fence.include, Some("src/lib.rs"));
+        assert_eq!(fence.include_lines, Some("1-3 5"));
+        assert_eq!(fence.include_region, Some("setup"));
+    }
+}
@@ -12,8 +12,11 @@ use libs::syntect::parsing::{
 };
 use libs::tera::escape_html;
 
+#[cfg(feature = "tree-sitter")]
+use super::treesitter::TreeSitterHighlighter;
+
 /// Not public, but from syntect::html
-fn write_css_color(s: &mut String, c: Color) {
+pub(super) fn write_css_color(s: &mut String, c: Color) {
     if c.a != 0xFF {
         write!(s, "#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a).unwrap();
     } else {
@@ -129,6 +132,8 @@ impl<'config> InlineHighlighter<'config> {
 pub(crate) enum SyntaxHighlighter<'config> {
     Inlined(InlineHighlighter<'config>),
     Classed(ClassHighlighter<'config>),
+    #[cfg(feature = "tree-sitter")]
+    TreeSitter(TreeSitterHighlighter<'config>),
     Ansi(Converter),
     /// We might not want highlighting but we want line numbers or to hide some lines
     NoHighlight,
@@ -147,66 +152,87 @@ impl<'config> SyntaxHighlighter<'config> {
         }
     }
 
+    /// Gives the whole code block to the highlighters needing it before `highlight_line`
+    #[cfg_attr(not(feature = "tree-sitter"), allow(unused_variables))]
+    pub fn prepare(&mut self, code: &str) {
+        #[cfg(feature = "tree-sitter")]
+        if let SyntaxHighlighter::TreeSitter(h) = self {
+            h.prepare(code);
+        }
+    }
+
+    /// Moves past a line that is hidden, for the highlighters having prepared the lines
+    pub fn skip_line(&mut self) {
+        #[cfg(feature = "tree-sitter")]
+        if let SyntaxHighlighter::TreeSitter(h) = self {
+            h.skip_line();
+        }
+    }
+
     pub fn highlight_line(&mut self, line: &str) -> String {
         use SyntaxHighlighter::*;
 
         match self {
             Inlined(h) => h.highlight_line(line),
             Classed(h) => h.highlight_line(line),
+            #[cfg(feature = "tree-sitter")]
+            TreeSitter(h) => h.highlight_line(line),
             Ansi(c) => c.convert(line).unwrap(),
             NoHighlight => escape_html(line),
         }
     }
 
-    /// Inlined needs to set the background/foreground colour on <pre>
-    pub fn pre_style(&self) -> Option<String> {
+    /// The theme of the highlighters using inline styles
+    fn inline_theme(&self) -> Option<&Theme> {
         use SyntaxHighlighter::*;
 
         match self {
             Classed(_) | NoHighlight | Ansi(_) => None,
-            Inlined(h) => {
-                let mut styles = String::from("background-color:");
-                write_css_color(&mut styles, h.theme.settings.background.unwrap_or(Color::WHITE));
-                styles.push_str(";color:");
-                write_css_color(&mut styles, h.theme.settings.foreground.unwrap_or(Color::BLACK));
-                styles.push(';');
-                Some(styles)
-            }
+            Inlined(h) => Some(h.theme),
+            #[cfg(feature = "tree-sitter")]
+            TreeSitter(h) => h.theme,
         }
     }
 
+    /// Inlined needs to set the background/foreground colour on <pre>
+    pub fn pre_style(&self) -> Option<String> {
+        let theme = self.inline_theme()?;
+        let mut styles = String::from("background-color:");
+        write_css_color(&mut styles, theme.settings.background.unwrap_or(Color::WHITE));
+        styles.push_str(";color:");
+        write_css_color(&mut styles, theme.settings.foreground.unwrap_or(Color::BLACK));
+        styles.push(';');
+        Some(styles)
+    }
+
     /// Classed needs to set a class on the pre
     pub fn pre_class(&self) -> Option<String> {
         use SyntaxHighlighter::*;
 
         match self {
-            Classed(_) | Ansi(_) => {
+            NoHighlight => None,
+            // Inlined and the tree-sitter highlighter with a theme use inline styles
+            _ if self.inline_theme().is_some() => None,
+            _ => {
                 if let ClassStyle::SpacedPrefixed { prefix } = CLASS_STYLE {
                     Some(format!("{}code", prefix))
                 } else {
                     unreachable!()
                 }
             }
-            Inlined(_) | NoHighlight => None,
         }
     }
 
     /// Inlined needs to set the background/foreground colour
     pub fn mark_style(&self) -> Option<String> {
-        use SyntaxHighlighter::*;
-
-        match self {
-            Classed(_) | NoHighlight | Ansi(_) => None,
-            Inlined(h) => {
-                let mut styles = String::from("background-color:");
-                write_css_color(
-                    &mut styles,
-                    h.theme.settings.line_highlight.unwrap_or(Color { r: 255, g: 255, b: 0, a: 0 }),
-                );
-                styles.push(';');
-                Some(styles)
-            }
-        }
+        let theme = self.inline_theme()?;
+        let mut styles = String::from("background-color:");
+        write_css_color(
+            &mut styles,
+            theme.settings.line_highlight.unwrap_or(Color { r: 255, g: 255, b: 0, a: 0 }),
+        );
+        styles.push(';');
+        Some(styles)
     }
 }
 
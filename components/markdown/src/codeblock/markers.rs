@@ -0,0 +1,141 @@
+//! Markers in the code of a block changing how its lines are rendered: the `+` and `-`
+//! starting the lines of `diff` blocks, the `[!code ...]` comments of `markers` blocks and the
+//! `(1)` comments of `annotate` blocks. The markers are removed before the code is highlighted,
+//! the other blocks being left untouched.
+use libs::once_cell::sync::Lazy;
+use libs::regex::Regex;
+
+/// A marker in a comment ending the line, eg `// [!code ++]`, `# (2) Some note` or
+/// `<!-- [!code focus] -->`
+static MARKER_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"[ \t]*(?://|#|--|;+|/\*|<!--)[ \t]*(?:\[!code (\+\+|--|focus|highlight)\]|\((\d+)\)[ \t]*(.*?))[ \t]*(?:\*/|-->)?[ \t]*$",
+    )
+    .unwrap()
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum DiffKind {
+    Inserted,
+    Deleted,
+}
+
+impl DiffKind {
+    pub fn class(self) -> &'static str {
+        match self {
+            DiffKind::Inserted => "ins",
+            DiffKind::Deleted => "del",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct LineMarkers {
+    /// The `+` or `-` starting the line in a `diff` block, which is not highlighted
+    pub prefix: Option<char>,
+    pub diff: Option<DiffKind>,
+    pub focus: bool,
+    pub highlight: bool,
+    /// The number and the text of the note of the line
+    pub annotation: Option<(usize, String)>,
+}
+
+/// Returns the code without its markers and the markers of each of its lines
+pub(super) fn extract_markers(
+    code: &str,
+    diff: bool,
+    annotate: bool,
+    markers: bool,
+) -> (String, Vec<LineMarkers>) {
+    let mut out = String::with_capacity(code.len());
+    let mut all_markers = Vec::new();
+
+    for line in code.split_inclusive('\n') {
+        let mut body = line.trim_end_matches(['\n', '\r']);
+        let ending = &line[body.len()..];
+        let mut line_markers = LineMarkers::default();
+
+        if diff {
+            let kind = match body.chars().next() {
+                Some('+') => Some(DiffKind::Inserted),
+                Some('-') => Some(DiffKind::Deleted),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                line_markers.prefix = body.chars().next();
+                line_markers.diff = Some(kind);
+                body = &body[1..];
+            }
+        }
+
+        if let Some(caps) = MARKER_RE.captures(body) {
+            let mut found = true;
+            match caps.get(1).map(|m| m.as_str()) {
+                Some("++") if markers => line_markers.diff = Some(DiffKind::Inserted),
+                Some("--") if markers => line_markers.diff = Some(DiffKind::Deleted),
+                Some("focus") if markers => line_markers.focus = true,
+                Some("highlight") if markers => line_markers.highlight = true,
+                None if annotate => {
+                    let number = caps[2].parse().unwrap_or_default();
+                    line_markers.annotation = Some((number, caps[3].to_string()));
+                }
+                _ => found = false,
+            }
+            if found {
+                body = &body[..caps.get(0).unwrap().start()];
+            }
+        }
+
+        out.push_str(body);
+        out.push_str(ending);
+        all_markers.push(line_markers);
+    }
+
+    (out, all_markers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_extract_diff_prefixes() {
+        let (code, markers) = extract_markers("fn a() {\n-    1\n+    2\n}\n", true, false, false);
+        assert_eq!(code, "fn a() {\n    1\n    2\n}\n");
+        assert_eq!(markers[0], LineMarkers::default());
+        assert_eq!(markers[1].prefix, Some('-'));
+        assert_eq!(markers[1].diff, Some(DiffKind::Deleted));
+        assert_eq!(markers[2].prefix, Some('+'));
+        assert_eq!(markers[2].diff, Some(DiffKind::Inserted));
+    }
+
+    #[test]
+    fn can_extract_comment_markers() {
+        let code = "let a = 1; // [!code ++]\r\nlet b = 2; # [!code --]\n<p></p> <!-- [!code focus] -->\nc /* [!code highlight] */\n-d";
+        let (out, markers) = extract_markers(code, false, false, true);
+        assert_eq!(out, "let a = 1;\r\nlet b = 2;\n<p></p>\nc\n-d");
+        assert_eq!(markers[0].diff, Some(DiffKind::Inserted));
+        assert_eq!(markers[1].diff, Some(DiffKind::Deleted));
+        assert!(markers[2].focus);
+        assert!(markers[3].highlight);
+        assert_eq!(markers[4], LineMarkers::default());
+
+        // Only the blocks with the `markers` option use them
+        let (out, markers) = extract_markers(code, false, true, false);
+        assert_eq!(out, code);
+        assert!(markers.iter().all(|m| *m == LineMarkers::default()));
+    }
+
+    #[test]
+    fn only_extracts_annotations_when_annotating() {
+        let code = "let a = 1; // (1) The *first* one\nb # (2)\n";
+        let (out, markers) = extract_markers(code, false, false, true);
+        assert_eq!(out, code);
+        assert!(markers.iter().all(|m| m.annotation.is_none()));
+
+        let (out, markers) = extract_markers(code, false, true, false);
+        assert_eq!(out, "let a = 1;\nb\n");
+        assert_eq!(markers[0].annotation, Some((1, "The *first* one".to_string())));
+        assert_eq!(markers[1].annotation, Some((2, String::new())));
+    }
+}
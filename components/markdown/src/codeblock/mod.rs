@@ -1,15 +1,24 @@
 mod fence;
 mod highlight;
+mod markers;
+#[cfg(feature = "tree-sitter")]
+mod treesitter;
 
 use std::ops::RangeInclusive;
 
 use ansi_to_html::Converter;
 use errors::{bail, Result};
 use libs::syntect::util::LinesWithEndings;
+use libs::tera::escape_html;
 
 use crate::codeblock::highlight::SyntaxHighlighter;
+use crate::codeblock::markers::extract_markers;
+#[cfg(feature = "tree-sitter")]
+use crate::codeblock::treesitter::TreeSitterHighlighter;
 use config::highlighting::{resolve_syntax_and_theme, HighlightSource};
 use config::Config;
+#[cfg(feature = "tree-sitter")]
+use config::HighlighterBackend;
 pub(crate) use fence::FenceSettings;
 
 fn opening_html(
@@ -79,6 +88,31 @@ fn opening_html(
     html
 }
 
+/// The tree-sitter highlighter of the code block when it's enabled, the languages without a
+/// bundled grammar being highlighted by syntect
+#[cfg(feature = "tree-sitter")]
+fn tree_sitter_highlighter<'config>(
+    language: Option<&str>,
+    config: &'config Config,
+) -> Option<SyntaxHighlighter<'config>> {
+    if !config.markdown.highlight_code
+        || config.markdown.highlighter != HighlighterBackend::TreeSitter
+    {
+        return None;
+    }
+    TreeSitterHighlighter::new(language?, config.markdown.get_highlight_theme())
+        .map(SyntaxHighlighter::TreeSitter)
+}
+
+/// Zola was built without the tree-sitter highlighter, which the config rejects
+#[cfg(not(feature = "tree-sitter"))]
+fn tree_sitter_highlighter<'config>(
+    _language: Option<&str>,
+    _config: &'config Config,
+) -> Option<SyntaxHighlighter<'config>> {
+    None
+}
+
 pub struct CodeBlock<'config> {
     highlighter: SyntaxHighlighter<'config>,
     // fence options
@@ -86,6 +120,12 @@ pub struct CodeBlock<'config> {
     line_number_start: usize,
     highlight_lines: Vec<RangeInclusive<usize>>,
     hide_lines: Vec<RangeInclusive<usize>>,
+    focus_lines: Vec<RangeInclusive<usize>>,
+    diff: bool,
+    annotate: bool,
+    markers: bool,
+    // the notes of the `annotate` blocks, by number
+    annotations: Vec<(usize, String)>,
 }
 
 impl<'config> CodeBlock<'config> {
@@ -98,6 +138,8 @@ impl<'config> CodeBlock<'config> {
         // println!("Rendering fence: {:#?}", fence);
         let highlighter = if matches!(fence.language, Some("ansi")) {
             SyntaxHighlighter::Ansi(Converter::new().four_bit_var_prefix(Some("ansi-".to_string())))
+        } else if let Some(tree_sitter) = tree_sitter_highlighter(fence.language, config) {
+            tree_sitter
         } else {
             let syntax_and_theme = resolve_syntax_and_theme(fence.language, config);
             if syntax_and_theme.source == HighlightSource::NotFound
@@ -133,6 +175,11 @@ impl<'config> CodeBlock<'config> {
                 line_number_start: fence.line_number_start,
                 highlight_lines: fence.highlight_lines.clone(),
                 hide_lines: fence.hide_lines.clone(),
+                focus_lines: fence.focus_lines.clone(),
+                diff: fence.diff,
+                annotate: fence.annotate,
+                markers: fence.markers,
+                annotations: Vec::new(),
             },
             html_start,
         ))
@@ -145,9 +192,12 @@ impl<'config> CodeBlock<'config> {
         if self.line_numbers {
             buffer.push_str("<table><tbody>");
         }
+        let (code, markers) = extract_markers(content, self.diff, self.annotate, self.markers);
+        let has_focus = !self.focus_lines.is_empty() || markers.iter().any(|m| m.focus);
+        self.highlighter.prepare(&code);
 
         // syntect leaking here in this file
-        for (i, line) in LinesWithEndings::from(content).enumerate() {
+        for (i, (line, markers)) in LinesWithEndings::from(&code).zip(&markers).enumerate() {
             let one_indexed = i + 1;
             // first do we need to skip that line?
             let mut skip = false;
@@ -158,16 +208,19 @@ impl<'config> CodeBlock<'config> {
                 }
             }
             if skip {
+                self.highlighter.skip_line();
                 continue;
             }
 
             // Next is it supposed to be higlighted?
-            let mut is_higlighted = false;
+            let mut is_higlighted = markers.highlight;
             for range in &self.highlight_lines {
                 if range.contains(&one_indexed) {
                     is_higlighted = true;
                 }
             }
+            let is_focused =
+                markers.focus || self.focus_lines.iter().any(|r| r.contains(&one_indexed));
 
             let maybe_mark = |buffer: &mut String, s: &str| {
                 if is_higlighted {
@@ -193,7 +246,30 @@ impl<'config> CodeBlock<'config> {
                 buffer.push_str("</td><td>");
             }
 
-            let highlighted_line = self.highlighter.highlight_line(line);
+            let mut highlighted_line = self.highlighter.highlight_line(line);
+            if let Some((number, ref note)) = markers.annotation {
+                // before the line ending so the marker shows at the end of the line
+                let marker = format!("<span class=\"code-annotation\">{}</span>", number);
+                match highlighted_line.rfind('\n') {
+                    Some(end) => highlighted_line.insert_str(end, &marker),
+                    None => highlighted_line.push_str(&marker),
+                }
+                self.annotations.push((number, note.clone()));
+            }
+            if let Some(prefix) = markers.prefix {
+                highlighted_line.insert(0, prefix);
+            }
+            let mut classes = Vec::new();
+            if let Some(diff) = markers.diff {
+                classes.push(diff.class());
+            }
+            if has_focus && !is_focused {
+                classes.push("dim");
+            }
+            if !classes.is_empty() {
+                highlighted_line =
+                    format!("<span class=\"{}\">{}</span>", classes.join(" "), highlighted_line);
+            }
             maybe_mark(&mut buffer, &highlighted_line);
 
             if self.line_numbers {
@@ -207,4 +283,22 @@ impl<'config> CodeBlock<'config> {
 
         buffer
     }
+
+    /// The notes of the `(1)` markers of the block, to render after it
+    pub fn annotations(&self) -> Option<String> {
+        if self.annotations.is_empty() {
+            return None;
+        }
+
+        let mut annotations = self.annotations.clone();
+        annotations.sort_by_key(|(number, _)| *number);
+        let mut html = String::from("<ol class=\"code-annotations\">");
+        for (number, note) in annotations {
+            html.push_str(&format!("<li value=\"{}\">", number));
+            html.push_str(&escape_html(&note));
+            html.push_str("</li>");
+        }
+        html.push_str("</ol>\n");
+        Some(html)
+    }
 }
@@ -0,0 +1,382 @@
+//! Highlighting with the bundled tree-sitter grammars. The tree-sitter captures are mapped to
+//! the TextMate scopes used by the syntect themes so the output is the same kind of HTML:
+//! spans with `z-` classes when highlighting with CSS or with inline styles otherwise.
+use config::highlighting::CLASS_STYLE;
+use errors::{Context, Result};
+use libs::once_cell::sync::Lazy;
+use libs::syntect::highlighting::{FontStyle, Highlighter, Theme};
+use libs::syntect::html::ClassStyle;
+use libs::syntect::parsing::Scope;
+use libs::tera::escape_html;
+use tree_sitter::Language;
+use tree_sitter_highlight::{
+    HighlightConfiguration, HighlightEvent, Highlighter as TreeSitterParser,
+};
+
+use super::highlight::write_css_color;
+
+/// The tree-sitter captures we highlight and the TextMate scope they correspond to.
+/// Captures not in that list use the longest one they start with, eg `function.method.call`
+/// uses `function.method`.
+const CAPTURES: &[(&str, &str)] = &[
+    ("attribute", "entity.other.attribute-name"),
+    ("boolean", "constant.language"),
+    ("comment", "comment"),
+    ("comment.documentation", "comment.block.documentation"),
+    ("constant", "constant.other"),
+    ("constant.builtin", "constant.language"),
+    ("constructor", "entity.name.type"),
+    ("embedded", "source"),
+    ("escape", "constant.character.escape"),
+    ("function", "entity.name.function"),
+    ("function.builtin", "support.function"),
+    ("function.macro", "entity.name.function.macro"),
+    ("function.method", "entity.name.function"),
+    ("keyword", "keyword"),
+    ("keyword.operator", "keyword.operator"),
+    ("label", "entity.name.label"),
+    ("module", "entity.name.namespace"),
+    ("namespace", "entity.name.namespace"),
+    ("number", "constant.numeric"),
+    ("operator", "keyword.operator"),
+    ("property", "variable.other.member"),
+    ("punctuation", "punctuation"),
+    ("punctuation.bracket", "punctuation.section"),
+    ("punctuation.delimiter", "punctuation.separator"),
+    ("punctuation.special", "punctuation.definition"),
+    ("string", "string.quoted"),
+    ("string.special", "string.other"),
+    ("tag", "entity.name.tag"),
+    ("type", "entity.name.type"),
+    ("type.builtin", "storage.type"),
+    ("variable", "variable.other"),
+    ("variable.builtin", "variable.language"),
+    ("variable.parameter", "variable.parameter"),
+];
+
+struct Grammar {
+    /// The languages of the code blocks using that grammar
+    tokens: &'static [&'static str],
+    config: HighlightConfiguration,
+}
+
+fn grammar(
+    tokens: &'static [&'static str],
+    language: impl Into<Language>,
+    highlights: &[&str],
+    injections: &str,
+    locals: &str,
+) -> Result<Grammar> {
+    let mut config = HighlightConfiguration::new(
+        language.into(),
+        tokens[0],
+        &highlights.join("\n"),
+        injections,
+        locals,
+    )
+    .with_context(|| format!("Invalid tree-sitter queries for {}", tokens[0]))?;
+    let names: Vec<&str> = CAPTURES.iter().map(|(name, _)| *name).collect();
+    config.configure(&names);
+    Ok(Grammar { tokens, config })
+}
+
+fn load_grammars() -> Vec<Result<Grammar>> {
+    vec![
+        grammar(
+            &["bash", "sh", "shell", "zsh"],
+            tree_sitter_bash::LANGUAGE,
+            &[tree_sitter_bash::HIGHLIGHT_QUERY],
+            "",
+            "",
+        ),
+        grammar(&["css"], tree_sitter_css::LANGUAGE, &[tree_sitter_css::HIGHLIGHTS_QUERY], "", ""),
+        grammar(
+            &["go", "golang"],
+            tree_sitter_go::LANGUAGE,
+            &[tree_sitter_go::HIGHLIGHTS_QUERY],
+            "",
+            "",
+        ),
+        grammar(
+            &["html", "htm"],
+            tree_sitter_html::LANGUAGE,
+            &[tree_sitter_html::HIGHLIGHTS_QUERY],
+            tree_sitter_html::INJECTIONS_QUERY,
+            "",
+        ),
+        grammar(
+            &["javascript", "js", "mjs", "cjs"],
+            tree_sitter_javascript::LANGUAGE,
+            &[tree_sitter_javascript::HIGHLIGHT_QUERY],
+            tree_sitter_javascript::INJECTIONS_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        ),
+        grammar(
+            &["jsx"],
+            tree_sitter_javascript::LANGUAGE,
+            &[tree_sitter_javascript::JSX_HIGHLIGHT_QUERY, tree_sitter_javascript::HIGHLIGHT_QUERY],
+            tree_sitter_javascript::INJECTIONS_QUERY,
+            tree_sitter_javascript::LOCALS_QUERY,
+        ),
+        grammar(
+            &["json"],
+            tree_sitter_json::LANGUAGE,
+            &[tree_sitter_json::HIGHLIGHTS_QUERY],
+            "",
+            "",
+        ),
+        grammar(&["nix"], tree_sitter_nix::LANGUAGE, &[tree_sitter_nix::HIGHLIGHTS_QUERY], "", ""),
+        grammar(
+            &["python", "py"],
+            tree_sitter_python::LANGUAGE,
+            &[tree_sitter_python::HIGHLIGHTS_QUERY],
+            "",
+            "",
+        ),
+        grammar(
+            &["rust", "rs"],
+            tree_sitter_rust::LANGUAGE,
+            &[tree_sitter_rust::HIGHLIGHTS_QUERY],
+            tree_sitter_rust::INJECTIONS_QUERY,
+            "",
+        ),
+        grammar(
+            &["svelte"],
+            tree_sitter_svelte_ng::LANGUAGE,
+            &[tree_sitter_svelte_ng::HIGHLIGHTS_QUERY],
+            tree_sitter_svelte_ng::INJECTIONS_QUERY,
+            "",
+        ),
+        grammar(
+            &["toml"],
+            tree_sitter_toml_ng::LANGUAGE,
+            &[tree_sitter_toml_ng::HIGHLIGHTS_QUERY],
+            "",
+            "",
+        ),
+        // The TypeScript queries only add to the JavaScript ones, in the same order as the
+        // tree-sitter configuration of the grammar
+        grammar(
+            &["typescript", "ts", "mts", "cts"],
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+            &[tree_sitter_javascript::HIGHLIGHT_QUERY, tree_sitter_typescript::HIGHLIGHTS_QUERY],
+            "",
+            &[tree_sitter_javascript::LOCALS_QUERY, tree_sitter_typescript::LOCALS_QUERY]
+                .join("\n"),
+        ),
+        grammar(
+            &["tsx"],
+            tree_sitter_typescript::LANGUAGE_TSX,
+            &[
+                tree_sitter_javascript::JSX_HIGHLIGHT_QUERY,
+                tree_sitter_javascript::HIGHLIGHT_QUERY,
+                tree_sitter_typescript::HIGHLIGHTS_QUERY,
+            ],
+            "",
+            &[tree_sitter_javascript::LOCALS_QUERY, tree_sitter_typescript::LOCALS_QUERY]
+                .join("\n"),
+        ),
+        grammar(
+            &["zig"],
+            tree_sitter_zig::LANGUAGE,
+            &[tree_sitter_zig::HIGHLIGHTS_QUERY],
+            tree_sitter_zig::INJECTIONS_QUERY,
+            "",
+        ),
+    ]
+}
+
+/// The grammars failing to load are skipped, their languages being highlighted by syntect
+static GRAMMARS: Lazy<Vec<Grammar>> = Lazy::new(|| {
+    load_grammars()
+        .into_iter()
+        .filter_map(|grammar| {
+            grammar.map_err(|e| console::warn(&format!("{:#}, using syntect instead", e))).ok()
+        })
+        .collect()
+});
+
+/// Finds the bundled grammar for the language of a code block, or of an injected language
+fn find_grammar(language: &str) -> Option<&'static HighlightConfiguration> {
+    GRAMMARS
+        .iter()
+        .find(|g| g.tokens.iter().any(|t| t.eq_ignore_ascii_case(language)))
+        .map(|g| &g.config)
+}
+
+pub(crate) struct TreeSitterHighlighter<'config> {
+    config: &'static HighlightConfiguration,
+    pub(crate) theme: Option<&'config Theme>,
+    /// The opening span of each capture, computed once
+    spans: Vec<String>,
+    /// The highlighted lines of the code block, in reverse order to pop them
+    lines: Vec<String>,
+}
+
+impl<'config> TreeSitterHighlighter<'config> {
+    pub fn new(language: &str, theme: Option<&'config Theme>) -> Option<Self> {
+        let config = find_grammar(language)?;
+        let spans = CAPTURES
+            .iter()
+            .map(|(_, scope)| match theme {
+                Some(theme) => Self::styled_span(theme, scope),
+                None => Self::classed_span(scope),
+            })
+            .collect();
+        Some(Self { config, theme, spans, lines: Vec::new() })
+    }
+
+    /// `<span class="z-entity z-name z-function">`, like syntect's classed HTML
+    fn classed_span(scope: &str) -> String {
+        let prefix = match CLASS_STYLE {
+            ClassStyle::SpacedPrefixed { prefix } => prefix,
+            _ => "",
+        };
+        let classes: Vec<String> =
+            scope.split('.').map(|atom| format!("{}{}", prefix, atom)).collect();
+        format!("<span class=\"{}\">", classes.join(" "))
+    }
+
+    /// The style the theme gives to that scope, like syntect's inline HTML. The foreground
+    /// is left out when it's the default one since the `<pre>` already sets it.
+    fn styled_span(theme: &Theme, scope: &str) -> String {
+        let highlighter = Highlighter::new(theme);
+        let scope = Scope::new(scope).expect("valid TextMate scope");
+        let style = highlighter.style_for_stack(&[scope]);
+        let mut css = String::new();
+        if Some(style.foreground) != theme.settings.foreground {
+            css.push_str("color:");
+            write_css_color(&mut css, style.foreground);
+            css.push(';');
+        }
+        if style.font_style.contains(FontStyle::BOLD) {
+            css.push_str("font-weight:bold;");
+        }
+        if style.font_style.contains(FontStyle::ITALIC) {
+            css.push_str("font-style:italic;");
+        }
+        if style.font_style.contains(FontStyle::UNDERLINE) {
+            css.push_str("text-decoration:underline;");
+        }
+        format!("<span style=\"{}\">", css)
+    }
+
+    /// Highlights the whole code block at once since tree-sitter needs all of it, splitting
+    /// the HTML into lines where the open spans are closed and reopened on the next line.
+    pub fn prepare(&mut self, code: &str) {
+        let mut parser = TreeSitterParser::new();
+        let mut lines = Vec::new();
+        let mut current = String::new();
+        let mut stack: Vec<usize> = Vec::new();
+
+        let events = parser.highlight(self.config, code.as_bytes(), None, find_grammar);
+        let events = match events {
+            Ok(events) => events,
+            Err(_) => {
+                self.lines = code.split_inclusive('\n').map(escape_html).rev().collect();
+                return;
+            }
+        };
+
+        for event in events {
+            match event {
+                Ok(HighlightEvent::HighlightStart(highlight)) => {
+                    current.push_str(&self.spans[highlight.0]);
+                    stack.push(highlight.0);
+                }
+                Ok(HighlightEvent::HighlightEnd) => {
+                    stack.pop();
+                    current.push_str("</span>");
+                }
+                Ok(HighlightEvent::Source { start, end }) => {
+                    for piece in code[start..end].split_inclusive('\n') {
+                        current.push_str(&escape_html(piece));
+                        if piece.ends_with('\n') {
+                            current.push_str(&"</span>".repeat(stack.len()));
+                            lines.push(std::mem::take(&mut current));
+                            for highlight in &stack {
+                                current.push_str(&self.spans[*highlight]);
+                            }
+                        }
+                    }
+                }
+                Err(_) => {
+                    self.lines = code.split_inclusive('\n').map(escape_html).rev().collect();
+                    return;
+                }
+            }
+        }
+        if !code.ends_with('\n') && !code.is_empty() {
+            current.push_str(&"</span>".repeat(stack.len()));
+            lines.push(current);
+        }
+
+        lines.reverse();
+        self.lines = lines;
+    }
+
+    pub fn skip_line(&mut self) {
+        self.lines.pop();
+    }
+
+    /// Returns the next line of the code block given to `prepare`
+    pub fn highlight_line(&mut self, line: &str) -> String {
+        self.lines.pop().unwrap_or_else(|| escape_html(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Config;
+
+    fn highlight(language: &str, code: &str, theme: Option<&Theme>) -> Vec<String> {
+        let mut highlighter = TreeSitterHighlighter::new(language, theme).unwrap();
+        highlighter.prepare(code);
+        code.split_inclusive('\n').map(|line| highlighter.highlight_line(line)).collect()
+    }
+
+    #[test]
+    fn can_load_all_grammars() {
+        for grammar in load_grammars() {
+            if let Err(e) = grammar {
+                panic!("{:#}", e);
+            }
+        }
+    }
+
+    #[test]
+    fn can_find_grammars() {
+        for language in ["rust", "ts", "TSX", "zig", "nix", "svelte", "sh"] {
+            assert!(find_grammar(language).is_some(), "{}", language);
+        }
+        assert!(find_grammar("cobol").is_none());
+    }
+
+    #[test]
+    fn can_highlight_with_classes() {
+        let lines = highlight("rust", "fn main() {\n    let s = \"a\nb\";\n}\n", None);
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("<span class=\"z-keyword\">fn</span>"));
+        assert!(lines[0].contains("<span class=\"z-entity z-name z-function\">main</span>"));
+        // The string spans two lines and is closed and reopened
+        assert!(lines[1].ends_with("</span>"));
+        assert!(lines[2].starts_with("<span class=\"z-string z-quoted\">b"));
+    }
+
+    #[test]
+    fn can_highlight_inline() {
+        let config = Config::default();
+        let theme = config.markdown.get_highlight_theme().unwrap();
+        let lines = highlight("typescript", "const a: number = 1;\n", Some(theme));
+        assert!(lines[0].starts_with("<span style=\""));
+        assert!(!lines[0].contains("class="));
+    }
+
+    #[test]
+    fn escapes_html() {
+        let lines = highlight("html", "<script>alert('<b>')</script>\n", None);
+        assert!(!lines[0].contains("<script>"));
+        assert!(!lines[0].contains("<b>"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Running the external commands configured by a site, which read their input on stdin
+//! and write their output on stdout.
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use errors::{bail, Context, Result};
+
+/// Runs the command with the given input and returns its output. The command is split like
+/// a shell would, so arguments can be quoted, but without going through a shell. It runs
+/// from the root of the site if there is one, relative programs such as `./filters/abbr`
+/// being found from there as well.
+pub fn run_with_input(
+    command: &str,
+    input: &str,
+    envs: &[(&str, &str)],
+    base_path: Option<&Path>,
+) -> Result<String> {
+    let Some(args) = shlex::split(command) else {
+        bail!("The command `{}` has an unclosed quote", command);
+    };
+    let Some((program, args)) = args.split_first() else {
+        bail!("The command is empty");
+    };
+
+    let mut cmd = match base_path {
+        Some(base_path) if program.contains(['/', '\\']) => Command::new(base_path.join(program)),
+        _ => Command::new(program),
+    };
+    if let Some(base_path) = base_path {
+        cmd.current_dir(base_path);
+    }
+    let mut child = cmd
+        .args(args)
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run `{}`, make sure it's in your PATH", program))?;
+    // Written from another thread so big outputs can't fill the pipe and block both ends
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output()?;
+    // The status of the command is what matters if it didn't read all of its input
+    let _ = writer.join();
+    if !output.status.success() {
+        bail!("`{}` failed: {}", command, String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn can_run_commands_with_quoted_arguments() {
+        let output = run_with_input("sh -c 'printf \"%s \" \"$1\"; cat' sh 'a  b'", "c", &[], None);
+        assert_eq!(output.unwrap(), "a  b c");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_commands_from_the_root_of_the_site() {
+        let root = std::env::temp_dir().canonicalize().unwrap();
+        let output = run_with_input("pwd -P", "", &[], Some(&root)).unwrap();
+        assert_eq!(Path::new(output.trim()), root);
+    }
+
+    #[test]
+    fn errors_on_invalid_commands() {
+        let err = run_with_input("sh -c 'cat", "", &[], None).unwrap_err();
+        assert_eq!(err.to_string(), "The command `sh -c 'cat` has an unclosed quote");
+        assert!(run_with_input("  ", "", &[], None).is_err());
+    }
+}
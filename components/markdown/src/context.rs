@@ -1,4 +1,4 @@
-use config::Config;
+use config::{Config, TableOfContents};
 use dirs::cache_dir;
 use libs::once_cell::sync::Lazy;
 use libs::tera::{Context, Tera};
@@ -9,6 +9,8 @@ use std::sync::Arc;
 use utils::templates::ShortcodeDefinition;
 use utils::types::InsertAnchor;
 
+use crate::diagram::DiagramCache;
+use crate::filters::FilterCache;
 use crate::math::MathCache;
 
 /// All the information from the zola site that is needed to render HTML from markdown
@@ -18,10 +20,15 @@ pub struct RenderContext<'a> {
     pub config: &'a Config,
     pub tera_context: Context,
     pub current_page_path: Option<&'a str>,
+    /// The number of lines before the content in the current page file, ie its front matter
+    pub content_line_offset: usize,
     pub parent_absolute: Option<PathBuf>,
+    /// The root directory of the site, where the external commands run
+    pub base_path: Option<&'a Path>,
     pub current_page_permalink: &'a str,
     pub permalinks: Cow<'a, HashMap<String, String>>,
     pub insert_anchor: InsertAnchor,
+    pub toc: TableOfContents,
     pub lang: &'a str,
     pub shortcode_definitions: Cow<'a, HashMap<String, ShortcodeDefinition>>,
     pub caches: Option<Arc<Caches>>,
@@ -31,6 +38,8 @@ pub struct RenderContext<'a> {
 pub struct Caches {
     pub typst: Arc<MathCache>,
     pub katex: Arc<MathCache>,
+    pub diagrams: Arc<DiagramCache>,
+    pub filters: Arc<FilterCache>,
 }
 
 impl Caches {
@@ -38,6 +47,8 @@ impl Caches {
         Self {
             typst: Arc::new(MathCache::new(cache_path, "typst").unwrap()),
             katex: Arc::new(MathCache::new(cache_path, "katex").unwrap()),
+            diagrams: Arc::new(DiagramCache::new(cache_path, "diagrams").unwrap()),
+            filters: Arc::new(FilterCache::new(cache_path, "filters").unwrap()),
         }
     }
 }
@@ -68,13 +79,16 @@ impl<'a> RenderContext<'a> {
             tera: Cow::Borrowed(tera),
             tera_context,
             current_page_path: None,
+            content_line_offset: 0,
             current_page_permalink,
             permalinks: Cow::Borrowed(permalinks),
             insert_anchor,
+            toc: config.markdown.toc,
             config,
             lang,
             shortcode_definitions: Cow::Owned(HashMap::new()),
             parent_absolute: None,
+            base_path: None,
             caches,
         }
     }
@@ -83,11 +97,26 @@ impl<'a> RenderContext<'a> {
         self.shortcode_definitions = Cow::Borrowed(def);
     }
 
+    /// Same as above
+    pub fn set_toc(&mut self, toc: TableOfContents) {
+        self.toc = toc;
+    }
+
     /// Same as above
     pub fn set_current_page_path(&mut self, path: &'a str) {
         self.current_page_path = Some(path);
     }
 
+    /// Same as above
+    pub fn set_base_path(&mut self, base_path: &'a Path) {
+        self.base_path = Some(base_path);
+    }
+
+    /// Same as above
+    pub fn set_content_line_offset(&mut self, offset: usize) {
+        self.content_line_offset = offset;
+    }
+
     /// Same as above
     pub fn set_parent_absolute(
         &mut self,
@@ -113,14 +142,17 @@ impl<'a> RenderContext<'a> {
             tera: Cow::Owned(Tera::default()),
             tera_context: Context::new(),
             current_page_path: None,
+            content_line_offset: 0,
             current_page_permalink: "",
             permalinks: Cow::Owned(HashMap::new()),
             insert_anchor: InsertAnchor::None,
+            toc: config.markdown.toc,
             config,
             lang: &config.default_language,
             shortcode_definitions: Cow::Owned(HashMap::new()),
             parent_absolute: None,
-            // We shouldn't need caches for this use case
+            base_path: None,
+            // The markdown filter sets the caches of the site if there is one
             caches: None,
         }
     }
@@ -0,0 +1,201 @@
+//! Renders the code blocks of diagram languages to SVG at build time, either in-process for
+//! Graphviz or with an external command for the other languages.
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use errors::{bail, Context, Error, Result};
+use layout::backends::svg::SVGWriter;
+use layout::gv::{DotParser, GraphBuilder};
+use twox_hash::XxHash64;
+
+use crate::cache::GenericCache;
+use crate::command::run_with_input;
+
+pub type DiagramCache = GenericCache<String, String>;
+
+pub trait DiagramRenderer {
+    /// Identifies the renderer in the cache keys so changing it renders the diagrams again
+    fn id(&self) -> &str;
+    fn render(&self, source: &str) -> Result<String>;
+}
+
+/// Lays out `dot` diagrams in-process
+pub struct GraphvizRenderer;
+
+impl DiagramRenderer for GraphvizRenderer {
+    fn id(&self) -> &str {
+        "graphviz"
+    }
+
+    fn render(&self, source: &str) -> Result<String> {
+        let mut parser = DotParser::new(source);
+        let graph = parser
+            .process()
+            .map_err(|e| Error::msg(format!("Failed to parse the Graphviz diagram: {}", e)))?;
+        // The layout panics on some graphs it doesn't support, which shouldn't stop the build
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut builder = GraphBuilder::new();
+            builder.visit_graph(&graph);
+            let mut visual_graph = builder.get();
+            let mut svg = SVGWriter::new();
+            visual_graph.do_it(false, false, false, &mut svg);
+            svg.finalize()
+        }))
+        .map_err(|_| Error::msg("Failed to lay out the Graphviz diagram"))
+    }
+}
+
+/// Pipes the diagram source through a command writing the SVG on its standard output
+pub struct CommandRenderer {
+    command: String,
+    /// The root of the site, where the command runs
+    base_path: Option<PathBuf>,
+}
+
+impl CommandRenderer {
+    pub fn new<S: Into<String>>(command: S, base_path: Option<&Path>) -> Self {
+        Self { command: command.into(), base_path: base_path.map(Path::to_path_buf) }
+    }
+}
+
+impl DiagramRenderer for CommandRenderer {
+    fn id(&self) -> &str {
+        &self.command
+    }
+
+    fn render(&self, source: &str) -> Result<String> {
+        run_with_input(&self.command, source, &[], self.base_path.as_deref())
+    }
+}
+
+/// Removes the XML declaration and doctype so the SVG can be inlined in HTML
+fn strip_svg_prolog(svg: &str) -> &str {
+    match svg.find("<svg") {
+        Some(start) => svg[start..].trim_end(),
+        None => svg.trim(),
+    }
+}
+
+/// The diagram renderers of a site, by language
+#[derive(Default)]
+pub struct Diagrams {
+    renderers: HashMap<String, Box<dyn DiagramRenderer>>,
+    cache: Option<Arc<DiagramCache>>,
+    /// Whether a diagram was added to the cache since it was last written
+    dirty: Cell<bool>,
+}
+
+impl Diagrams {
+    pub fn new(
+        config: &config::Diagrams,
+        cache: Option<Arc<DiagramCache>>,
+        base_path: Option<&Path>,
+    ) -> Self {
+        let mut renderers: HashMap<String, Box<dyn DiagramRenderer>> = HashMap::new();
+        if config.graphviz {
+            renderers.insert("dot".to_string(), Box::new(GraphvizRenderer));
+            renderers.insert("graphviz".to_string(), Box::new(GraphvizRenderer));
+        }
+        // Commands can replace the built-in Graphviz layout, eg to use the real `dot`
+        for (language, command) in &config.commands {
+            renderers.insert(
+                language.clone(),
+                Box::new(CommandRenderer::new(command.clone(), base_path)),
+            );
+        }
+        Self { renderers, cache, dirty: Cell::new(false) }
+    }
+
+    pub fn is_diagram(&self, language: &str) -> bool {
+        self.renderers.contains_key(language)
+    }
+
+    /// Renders the diagram to an inline SVG, reusing the cached SVG if the source and the
+    /// renderer didn't change
+    pub fn render(&self, language: &str, source: &str) -> Result<String> {
+        let Some(renderer) = self.renderers.get(language) else {
+            bail!("There is no renderer for `{}` diagrams", language);
+        };
+
+        let key = {
+            let mut hasher = XxHash64::with_seed(42);
+            language.hash(&mut hasher);
+            renderer.id().hash(&mut hasher);
+            source.hash(&mut hasher);
+            format!("{:x}", hasher.finish())
+        };
+        if let Some(svg) = self.cache.as_ref().and_then(|cache| cache.get(&key)) {
+            return Ok(svg);
+        }
+
+        let svg = renderer
+            .render(source)
+            .with_context(|| format!("Failed to render a `{}` diagram", language))?;
+        let html =
+            format!("<div class=\"diagram diagram-{}\">{}</div>", language, strip_svg_prolog(&svg));
+        if let Some(cache) = self.cache.as_ref() {
+            cache.insert(key, html.clone());
+            self.dirty.set(true);
+        }
+        Ok(html)
+    }
+
+    pub fn write_cache(&self) -> Result<()> {
+        if let Some(ref cache) = self.cache {
+            if self.dirty.replace(false) {
+                cache.write().context("Failed to write the diagram cache")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_render_graphviz_diagrams() {
+        let config = config::Diagrams { graphviz: true, ..Default::default() };
+        let diagrams = Diagrams::new(&config, None, None);
+        assert!(diagrams.is_diagram("dot"));
+        assert!(!diagrams.is_diagram("mermaid"));
+        let html = diagrams.render("dot", "digraph { a -> b }").unwrap();
+        assert!(html.starts_with("<div class=\"diagram diagram-dot\"><svg"));
+        assert!(html.ends_with("</svg></div>"));
+    }
+
+    #[test]
+    fn errors_on_invalid_graphviz_diagrams() {
+        let config = config::Diagrams { graphviz: true, ..Default::default() };
+        let diagrams = Diagrams::new(&config, None, None);
+        let err = diagrams.render("graphviz", "digraph { a -> ").unwrap_err();
+        assert_eq!(err.to_string(), "Failed to render a `graphviz` diagram");
+    }
+
+    #[test]
+    fn can_strip_svg_prolog() {
+        assert_eq!(
+            strip_svg_prolog("<?xml version=\"1.0\"?>\n<!DOCTYPE svg>\n<svg></svg>\n"),
+            "<svg></svg>"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_render_diagrams_with_commands() {
+        let mut config = config::Diagrams::default();
+        config.commands.insert("echo".to_string(), "cat".to_string());
+        config.commands.insert("broken".to_string(), "false".to_string());
+        let diagrams = Diagrams::new(&config, None, None);
+        assert_eq!(
+            diagrams.render("echo", "<svg>hi</svg>").unwrap(),
+            "<div class=\"diagram diagram-echo\"><svg>hi</svg></div>"
+        );
+        assert!(diagrams.render("broken", "").is_err());
+    }
+}
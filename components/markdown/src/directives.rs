@@ -0,0 +1,402 @@
+//! Markdown-native directives, an alternative syntax to call shortcodes:
+//!
+//! - `:::name{key=value}` ... `:::` for shortcodes with a body
+//! - `::name[text]{key=value}` for inline shortcodes, the text being the body if there is one
+//!
+//! The directives are rewritten into regular shortcode calls before the shortcodes are
+//! extracted so both syntaxes go through the exact same path.
+use std::collections::HashMap;
+use std::ops::Range;
+
+use errors::{bail, Result};
+use libs::once_cell::sync::Lazy;
+use libs::pulldown_cmark::{Event, Options, Parser, Tag};
+use libs::regex::Regex;
+
+static CONTAINER_START_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(\s*)(:{3,})\s*([A-Za-z_][A-Za-z0-9_]*)\s*(?:\[(.*?)\])?\s*(?:\{(.*)\})?\s*$")
+        .unwrap()
+});
+static CONTAINER_END_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(:{3,})\s*$").unwrap());
+static INLINE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(^|[^A-Za-z0-9_:])::([A-Za-z_][A-Za-z0-9_]*)(?:\[([^\]]*)\])?(?:\{([^}]*)\})?")
+        .unwrap()
+});
+static LEAF_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\s*::[A-Za-z_][A-Za-z0-9_]*\s*$").unwrap());
+static INT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-?[0-9]+$").unwrap());
+static FLOAT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-?[0-9]+\.[0-9]+$").unwrap());
+static KEY_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap());
+
+/// Quotes a string for a shortcode call with a delimiter not found in the value
+fn quote(value: &str) -> Result<String> {
+    for delimiter in ['"', '\'', '`'] {
+        if !value.contains(delimiter) {
+            return Ok(format!("{}{}{}", delimiter, value, delimiter));
+        }
+    }
+    bail!("Directive attribute value `{}` cannot contain all of \", ' and `", value)
+}
+
+/// Converts an unquoted attribute value to a shortcode literal, keeping its type
+fn literal(value: &str) -> Result<String> {
+    if value == "true" || value == "false" || INT_RE.is_match(value) || FLOAT_RE.is_match(value) {
+        Ok(value.to_string())
+    } else {
+        quote(value)
+    }
+}
+
+/// Parses `key=value key="quoted value" #id .class flag` into shortcode keyword arguments
+fn parse_attributes(input: &str) -> Result<Vec<(String, String)>> {
+    let mut args: Vec<(String, String)> = Vec::new();
+    let mut classes = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == ',') {
+            chars.next();
+        }
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+
+        let mut token = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == ',' || c == '=' {
+                break;
+            }
+            token.push(c);
+            chars.next();
+        }
+
+        if first == '#' || first == '.' {
+            let name = &token[1..];
+            if name.is_empty() {
+                bail!("Empty `{}` in directive attributes `{{{}}}`", first, input);
+            }
+            if first == '#' {
+                args.push(("id".to_string(), quote(name)?));
+            } else {
+                classes.push(name.to_string());
+            }
+            continue;
+        }
+
+        if !KEY_RE.is_match(&token) {
+            bail!("Invalid attribute name `{}` in directive attributes `{{{}}}`", token, input);
+        }
+
+        if chars.peek() != Some(&'=') {
+            args.push((token, "true".to_string()));
+            continue;
+        }
+        chars.next();
+
+        let value = match chars.peek() {
+            Some(&delimiter) if delimiter == '"' || delimiter == '\'' => {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == delimiter => break,
+                        Some(c) => value.push(c),
+                        None => bail!(
+                            "Unterminated string for attribute `{}` in directive attributes `{{{}}}`",
+                            token,
+                            input
+                        ),
+                    }
+                }
+                quote(&value)?
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ',' {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+                literal(&value)?
+            }
+        };
+        args.push((token, value));
+    }
+
+    if !classes.is_empty() {
+        args.push(("class".to_string(), quote(&classes.join(" "))?));
+    }
+    Ok(args)
+}
+
+fn shortcode_call(name: &str, label_arg: Option<&str>, attributes: Option<&str>) -> Result<String> {
+    let mut args = match attributes {
+        Some(attributes) => parse_attributes(attributes)?,
+        None => Vec::new(),
+    };
+    if let Some(label) = label_arg {
+        args.push(("label".to_string(), quote(label)?));
+    }
+    let args: Vec<_> = args.into_iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+    Ok(format!("{}({})", name, args.join(", ")))
+}
+
+/// Where the text of the rewritten lines comes from, to report the errors of the shortcodes
+/// at their position in the original content
+#[derive(Debug, Default)]
+pub struct ColumnMap {
+    /// The parts of the rewritten lines, by 1-based line number: the column each part starts at,
+    /// the column it comes from in the original line and whether it was copied as is
+    lines: HashMap<usize, Vec<(usize, usize, bool)>>,
+}
+
+impl ColumnMap {
+    /// The column in the original content of a position in the rewritten content. The text
+    /// generated for a directive points to the start of the directive.
+    pub fn original_column(&self, line: usize, column: usize) -> usize {
+        let Some(parts) = self.lines.get(&line) else {
+            return column;
+        };
+        match parts.iter().rev().find(|(start, _, _)| *start <= column) {
+            Some((start, original, true)) => original + (column - start),
+            Some((_, original, false)) => *original,
+            None => column,
+        }
+    }
+}
+
+/// A rewritten line, keeping track of where its parts come from
+#[derive(Default)]
+struct LineWriter {
+    text: String,
+    columns: usize,
+    parts: Vec<(usize, usize, bool)>,
+}
+
+impl LineWriter {
+    /// Adds some text of the original line, which starts at the given column
+    fn copy(&mut self, text: &str, original_column: usize) {
+        if text.is_empty() {
+            return;
+        }
+        self.parts.push((self.columns + 1, original_column, true));
+        self.push(text);
+    }
+
+    /// Adds the text generated for the directive starting at the given column
+    fn generate(&mut self, text: &str, original_column: usize) {
+        self.parts.push((self.columns + 1, original_column, false));
+        self.push(text);
+    }
+
+    fn push(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.columns += text.chars().count();
+    }
+}
+
+/// The 1-based column of a byte offset of a line
+fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// The byte ranges of the content where directives are left untouched: the code and HTML
+/// blocks, and the code spans and inline HTML
+fn protected_ranges(content: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_FOOTNOTES);
+    opts.insert(Options::ENABLE_STRIKETHROUGH);
+    opts.insert(Options::ENABLE_TASKLISTS);
+    opts.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+    let mut blocks = Vec::new();
+    let mut spans = Vec::new();
+    for (event, range) in Parser::new_ext(content, opts).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::HtmlBlock) => blocks.push(range),
+            Event::Code(_) | Event::InlineHtml(_) => spans.push(range),
+            _ => (),
+        }
+    }
+    (blocks, spans)
+}
+
+/// Rewrites the inline directives of a line, which starts at `start` in the content, that are
+/// not in code spans or inline HTML
+fn replace_inline_directives(
+    line: &str,
+    start: usize,
+    spans: &[Range<usize>],
+    out: &mut LineWriter,
+) -> Result<()> {
+    if LEAF_RE.is_match(line) {
+        let (indent, name) = line.trim_end().split_once("::").unwrap();
+        out.copy(indent, 1);
+        out.generate(&format!("{{{{ {}() }}}}", name.trim()), column(line, indent.len()));
+        return Ok(());
+    }
+
+    let end = start + line.len();
+    let mut last = 0;
+    for span in spans.iter().filter(|r| r.start < end && start < r.end) {
+        let span_start = span.start.saturating_sub(start).max(last);
+        let span_end = (span.end - start).min(line.len());
+        replace_in_text(&line[last..span_start], column(line, last), out)?;
+        out.copy(&line[span_start..span_end], column(line, span_start));
+        last = span_end;
+    }
+    replace_in_text(&line[last..], column(line, last), out)
+}
+
+/// Rewrites the inline directives of some text starting at the given column
+fn replace_in_text(text: &str, start_column: usize, out: &mut LineWriter) -> Result<()> {
+    let column = |offset: usize| start_column + text[..offset].chars().count();
+    let mut last = 0;
+    for caps in INLINE_RE.captures_iter(text) {
+        let (label, attributes) = (caps.get(3), caps.get(4));
+        // `::name` in the middle of text is not a directive, eg `std::fs`
+        if label.is_none() && attributes.is_none() {
+            continue;
+        }
+        let call = shortcode_call(&caps[2], None, attributes.map(|m| m.as_str()))?;
+        // The first group is the character before the directive, if any
+        let directive_start = caps.get(1).unwrap().end();
+        out.copy(&text[last..directive_start], column(last));
+        match label {
+            Some(label) => {
+                out.generate(&format!("{{% {} %}}", call), column(directive_start));
+                out.copy(label.as_str(), column(label.start()));
+                out.generate("{% end %}", column(directive_start));
+            }
+            None => out.generate(&format!("{{{{ {} }}}}", call), column(directive_start)),
+        }
+        last = caps.get(0).unwrap().end();
+    }
+    out.copy(&text[last..], column(last));
+    Ok(())
+}
+
+/// Rewrites the directives of the content into shortcode calls. Container directives are
+/// closed by a line with as many colons as the one opening them, which allows nesting them.
+/// The code and HTML blocks found by the markdown parser are left untouched.
+/// Every line stays on the same line, the returned `ColumnMap` gives the original columns.
+pub fn directives_to_shortcodes(content: &str) -> Result<(String, ColumnMap)> {
+    let (blocks, spans) = protected_ranges(content);
+    let mut out = String::with_capacity(content.len());
+    let mut columns = ColumnMap::default();
+    // The number of colons and line number of the open containers
+    let mut containers: Vec<(usize, usize)> = Vec::new();
+
+    let mut start = 0;
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        let line_start = start;
+        start += line.len();
+        let (line, newline) = match line.strip_suffix('\n') {
+            Some(l) => (l, "\n"),
+            None => (line, ""),
+        };
+        // An empty line still has to be found in a block
+        let line_end = (line_start + line.len()).max(line_start + 1);
+        if blocks.iter().any(|r| r.start < line_end && line_start < r.end) {
+            out.push_str(line);
+            out.push_str(newline);
+            continue;
+        }
+
+        let mut writer = LineWriter::default();
+        if let Some(caps) = CONTAINER_START_RE.captures(line) {
+            let call = shortcode_call(
+                &caps[3],
+                caps.get(4).map(|m| m.as_str()),
+                caps.get(5).map(|m| m.as_str()),
+            )?;
+            containers.push((caps[2].len(), i + 1));
+            writer.copy(&caps[1], 1);
+            writer.generate(&format!("{{% {} %}}", call), column(line, caps[1].len()));
+        } else if let Some(caps) = CONTAINER_END_RE
+            .captures(line)
+            .filter(|caps| containers.last().map(|(colons, _)| *colons) == Some(caps[1].len()))
+        {
+            containers.pop();
+            writer.generate("{% end %}", column(line, caps.get(1).unwrap().start()));
+        } else {
+            replace_inline_directives(line, line_start, &spans, &mut writer)?;
+        }
+
+        if writer.parts.iter().any(|(_, _, copied)| !copied) {
+            columns.lines.insert(i + 1, writer.parts);
+        }
+        out.push_str(&writer.text);
+        out.push_str(newline);
+    }
+
+    if let Some((colons, line)) = containers.pop() {
+        bail!(
+            "The container directive opened on line {} is never closed by a `{}` line",
+            line,
+            ":".repeat(colons)
+        );
+    }
+
+    Ok((out, columns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_convert_container_directives() {
+        let content =
+            "Hello\n:::note{title=\"Heads up\" level=2 #main .big .red open}\nSome **text**\n:::\n";
+        assert_eq!(
+            directives_to_shortcodes(content).unwrap().0,
+            "Hello\n{% note(title=\"Heads up\", level=2, id=\"main\", open=true, class=\"big red\") %}\nSome **text**\n{% end %}\n"
+        );
+    }
+
+    #[test]
+    fn can_nest_container_directives() {
+        let content = "::::tabs\n:::tab[Rust]\nA\n:::\n:::tab{title='Py'}\nB\n:::\n::::";
+        assert_eq!(
+            directives_to_shortcodes(content).unwrap().0,
+            "{% tabs() %}\n{% tab(label=\"Rust\") %}\nA\n{% end %}\n{% tab(title=\"Py\") %}\nB\n{% end %}\n{% end %}"
+        );
+    }
+
+    #[test]
+    fn can_convert_inline_directives() {
+        assert_eq!(
+            directives_to_shortcodes("Watch ::youtube{id=abc start=30} and ::abbr[HTML]{title=\"HyperText\"}")
+                .unwrap()
+                .0,
+            "Watch {{ youtube(id=\"abc\", start=30) }} and {% abbr(title=\"HyperText\") %}HTML{% end %}"
+        );
+        assert_eq!(directives_to_shortcodes("  ::toc\n").unwrap().0, "  {{ toc() }}\n");
+    }
+
+    #[test]
+    fn leaves_code_and_other_colons_alone() {
+        let content =
+            "Use `::youtube{id=a}` or std::fs::read{}\n```md\n:::note\n::youtube{id=a}\n```\n";
+        assert_eq!(directives_to_shortcodes(content).unwrap().0, content);
+    }
+
+    #[test]
+    fn errors_on_unclosed_containers() {
+        let err = directives_to_shortcodes("Hey\n:::note\nHello\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The container directive opened on line 2 is never closed by a `:::` line"
+        );
+    }
+
+    #[test]
+    fn errors_on_invalid_attributes() {
+        assert!(directives_to_shortcodes("::a{data-x=1}").is_err());
+        assert!(directives_to_shortcodes("::a{title=\"oops}").is_err());
+    }
+}
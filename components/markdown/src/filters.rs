@@ -0,0 +1,290 @@
+//! External commands transforming the rendered markdown, either as HTML or as a JSON
+//! array of the markdown events.
+//!
+//! In the JSON, the `text`, `code`, `html` and `inline_html` events have a `text` field and
+//! can be modified, removed or added freely. The other events, such as the start and end of
+//! tags, have an `index` field pointing to the original event which is what gets output:
+//! their other fields are only there to be read.
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Arc;
+
+use config::{MarkdownFilter, MarkdownFilterFormat};
+use errors::{bail, Context, Result};
+use libs::pulldown_cmark::{CodeBlockKind, Event, Tag};
+use libs::serde_json::{self, json, Map, Value};
+use twox_hash::XxHash64;
+
+use crate::cache::GenericCache;
+use crate::command::run_with_input;
+
+pub type FilterCache = GenericCache<String, String>;
+
+/// `Start(Heading { .. })` -> `start`, `TaskListMarker(true)` -> `task_list_marker`
+fn variant_name<T: Debug>(value: &T) -> String {
+    let debug = format!("{:?}", value);
+    let mut name = String::new();
+    for c in debug.chars().take_while(|c| c.is_ascii_alphanumeric()) {
+        if c.is_ascii_uppercase() && !name.is_empty() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+fn tag_to_json<AD: Debug>(tag: &Tag<'_, AD>, obj: &mut Map<String, Value>) {
+    obj.insert("tag".to_string(), Value::String(variant_name(tag)));
+    match tag {
+        Tag::Heading { level, id, .. } => {
+            obj.insert("level".to_string(), json!(*level as usize));
+            obj.insert("id".to_string(), json!(id.as_deref()));
+        }
+        Tag::CodeBlock(CodeBlockKind::Fenced(info)) => {
+            obj.insert("info".to_string(), json!(info.as_ref()));
+        }
+        Tag::List(start) => {
+            obj.insert("start".to_string(), json!(start));
+        }
+        Tag::FootnoteDefinition(label) => {
+            obj.insert("label".to_string(), json!(label.as_ref()));
+        }
+        Tag::Link { dest_url, title, .. } | Tag::Image { dest_url, title, .. } => {
+            obj.insert("url".to_string(), json!(dest_url.as_ref()));
+            obj.insert("title".to_string(), json!(title.as_ref()));
+        }
+        _ => (),
+    }
+}
+
+pub fn events_to_json<AD: Debug>(events: &[Event<'_, AD>]) -> Value {
+    let events: Vec<Value> = events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| {
+            let mut obj = Map::new();
+            obj.insert("type".to_string(), Value::String(variant_name(event)));
+            match event {
+                Event::Text(text)
+                | Event::Code(text)
+                | Event::Html(text)
+                | Event::InlineHtml(text) => {
+                    obj.insert("text".to_string(), json!(text.as_ref()));
+                    return Value::Object(obj);
+                }
+                Event::Start(tag) => tag_to_json(tag, &mut obj),
+                Event::End(tag) => {
+                    obj.insert("tag".to_string(), Value::String(variant_name(tag)));
+                }
+                Event::FootnoteReference(label) => {
+                    obj.insert("label".to_string(), json!(label.as_ref()));
+                }
+                Event::TaskListMarker(checked) => {
+                    obj.insert("checked".to_string(), json!(checked));
+                }
+                Event::InlineMath(text) | Event::DisplayMath(text) => {
+                    obj.insert("text".to_string(), json!(text.as_ref()));
+                }
+                _ => (),
+            }
+            obj.insert("index".to_string(), json!(index));
+            Value::Object(obj)
+        })
+        .collect();
+    Value::Array(events)
+}
+
+pub fn events_from_json<'a, AD: Clone>(
+    value: Value,
+    original: &[Event<'a, AD>],
+) -> Result<Vec<Event<'a, AD>>> {
+    let Value::Array(values) = value else {
+        bail!("Expected an array of events");
+    };
+
+    let mut events = Vec::with_capacity(values.len());
+    for value in values {
+        let ty = value.get("type").and_then(Value::as_str).unwrap_or_default();
+        let text = value.get("text").and_then(Value::as_str).map(|t| t.to_string().into());
+        let event = match (ty, text) {
+            ("text", Some(text)) => Event::Text(text),
+            ("code", Some(text)) => Event::Code(text),
+            ("html", Some(text)) => Event::Html(text),
+            ("inline_html", Some(text)) => Event::InlineHtml(text),
+            _ => {
+                let index = value.get("index").and_then(Value::as_u64);
+                match index.and_then(|i| original.get(i as usize)) {
+                    Some(event) => event.clone(),
+                    None => bail!(
+                        "Invalid event `{}`: only text, code, html and inline_html events can be created, the other ones need the `index` of an original event",
+                        value
+                    ),
+                }
+            }
+        };
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// The filters of a site, run on the markdown of a page or section
+pub struct Filters<'a> {
+    filters: &'a [MarkdownFilter],
+    cache: Option<Arc<FilterCache>>,
+    /// Passed to the filters in the `ZOLA_PATH` and `ZOLA_LANG` environment variables
+    path: &'a str,
+    lang: &'a str,
+    /// The root of the site, where the filters run
+    base_path: Option<&'a Path>,
+    /// Whether an output was added to the cache since it was last written
+    dirty: Cell<bool>,
+}
+
+impl<'a> Filters<'a> {
+    pub fn new(
+        filters: &'a [MarkdownFilter],
+        cache: Option<Arc<FilterCache>>,
+        path: &'a str,
+        lang: &'a str,
+        base_path: Option<&'a Path>,
+    ) -> Self {
+        Self { filters, cache, path, lang, base_path, dirty: Cell::new(false) }
+    }
+
+    fn of_format(&self, format: MarkdownFilterFormat) -> impl Iterator<Item = &MarkdownFilter> {
+        self.filters.iter().filter(move |f| f.format == format)
+    }
+
+    fn run(&self, filter: &MarkdownFilter, input: &str) -> Result<String> {
+        let key = {
+            let mut hasher = XxHash64::with_seed(42);
+            filter.command.hash(&mut hasher);
+            self.path.hash(&mut hasher);
+            self.lang.hash(&mut hasher);
+            input.hash(&mut hasher);
+            format!("{:x}", hasher.finish())
+        };
+        let cache = self.cache.as_ref().filter(|_| filter.cache);
+        if let Some(output) = cache.and_then(|cache| cache.get(&key)) {
+            return Ok(output);
+        }
+
+        let envs = [("ZOLA_PATH", self.path), ("ZOLA_LANG", self.lang)];
+        let output = run_with_input(&filter.command, input, &envs, self.base_path)
+            .with_context(|| format!("Failed to run the markdown filter `{}`", filter.command))?;
+        if let Some(cache) = cache {
+            cache.insert(key, output.clone());
+            self.dirty.set(true);
+        }
+        Ok(output)
+    }
+
+    pub fn apply_to_events<'e, AD: Clone + Debug>(
+        &self,
+        mut events: Vec<Event<'e, AD>>,
+    ) -> Result<Vec<Event<'e, AD>>> {
+        for filter in self.of_format(MarkdownFilterFormat::Events) {
+            let input = serde_json::to_string(&events_to_json(&events))?;
+            let output = self.run(filter, &input)?;
+            let value: Value = serde_json::from_str(&output).with_context(|| {
+                format!("The markdown filter `{}` didn't output valid JSON", filter.command)
+            })?;
+            events = events_from_json(value, &events).with_context(|| {
+                format!("The markdown filter `{}` output invalid events", filter.command)
+            })?;
+        }
+        Ok(events)
+    }
+
+    /// The summary of a page is a separate piece of HTML going through the filters on its own:
+    /// the HTML filters run twice for the pages having one
+    pub fn apply_to_html(&self, mut html: String) -> Result<String> {
+        for filter in self.of_format(MarkdownFilterFormat::Html) {
+            html = self.run(filter, &html)?;
+        }
+        Ok(html)
+    }
+
+    pub fn write_cache(&self) -> Result<()> {
+        if let Some(ref cache) = self.cache {
+            if self.dirty.replace(false) {
+                cache.write().context("Failed to write the markdown filters cache")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callouts::CalloutTag;
+    use libs::pulldown_cmark::{HeadingLevel, TagEnd};
+
+    #[test]
+    fn can_convert_events_to_json_and_back() {
+        let events: Vec<Event<CalloutTag>> = vec![
+            Event::Start(Tag::Heading {
+                level: HeadingLevel::H2,
+                id: Some("intro".into()),
+                classes: vec![],
+                attrs: vec![],
+            }),
+            Event::Text("Intro".into()),
+            Event::End(TagEnd::Heading(HeadingLevel::H2)),
+            Event::SoftBreak,
+        ];
+        let value = events_to_json(&events);
+        assert_eq!(
+            value,
+            json!([
+                {"type": "start", "tag": "heading", "level": 2, "id": "intro", "index": 0},
+                {"type": "text", "text": "Intro"},
+                {"type": "end", "tag": "heading", "index": 2},
+                {"type": "soft_break", "index": 3},
+            ])
+        );
+
+        let filtered = json!([
+            {"type": "start", "tag": "heading", "index": 0},
+            {"type": "html", "text": "<abbr>Intro</abbr>"},
+            {"type": "end", "index": 2},
+        ]);
+        assert_eq!(
+            events_from_json(filtered, &events).unwrap(),
+            vec![events[0].clone(), Event::Html("<abbr>Intro</abbr>".into()), events[2].clone()]
+        );
+    }
+
+    #[test]
+    fn errors_on_events_without_index() {
+        let events: Vec<Event<CalloutTag>> = vec![Event::Rule];
+        assert!(events_from_json(json!([{"type": "rule"}]), &events).is_err());
+        assert!(events_from_json(json!([{"type": "rule", "index": 1}]), &events).is_err());
+        assert!(events_from_json(json!({"type": "text"}), &events).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_apply_filters() {
+        let filters = vec![
+            MarkdownFilter { command: "cat".to_string(), ..Default::default() },
+            MarkdownFilter {
+                command: "cat".to_string(),
+                format: MarkdownFilterFormat::Events,
+                ..Default::default()
+            },
+            MarkdownFilter { command: "false".to_string(), ..Default::default() },
+        ];
+        let events: Vec<Event<CalloutTag>> = vec![Event::Text("Hello".into()), Event::Rule];
+        let pipeline = Filters::new(&filters[..2], None, "a.md", "en", None);
+        assert_eq!(pipeline.apply_to_events(events.clone()).unwrap(), events);
+        assert_eq!(pipeline.apply_to_html("<p>Hi</p>".to_string()).unwrap(), "<p>Hi</p>");
+
+        let pipeline = Filters::new(&filters, None, "a.md", "en", None);
+        let err = pipeline.apply_to_html("<p>Hi</p>".to_string()).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to run the markdown filter `false`");
+    }
+}
@@ -1,20 +1,36 @@
 pub mod cache;
 pub mod callouts;
 pub mod codeblock;
+pub mod command;
 pub mod context;
+pub mod diagram;
+pub mod directives;
+pub mod filters;
 pub mod markdown;
 pub mod math;
 pub mod shortcode;
 
+use directives::{directives_to_shortcodes, ColumnMap};
 use shortcode::{extract_shortcodes, insert_md_shortcodes};
 
 use errors::Result;
 
 use crate::markdown::markdown_to_html;
-pub use crate::markdown::Rendered;
+pub use crate::markdown::{find_link_offsets, Rendered};
 pub use context::RenderContext;
 
 pub fn render_content(content: &str, context: &RenderContext) -> Result<markdown::Rendered> {
+    let with_directives;
+    let mut columns = ColumnMap::default();
+    let content = if context.config.markdown.directives && content.contains("::") {
+        let (rewritten, rewritten_columns) = directives_to_shortcodes(content)?;
+        with_directives = rewritten;
+        columns = rewritten_columns;
+        &with_directives
+    } else {
+        content
+    };
+
     // avoid parsing the content if needed
     if !content.contains("{{") && !content.contains("{%") {
         return markdown_to_html(content, context, Vec::new());
@@ -22,7 +38,14 @@ pub fn render_content(content: &str, context: &RenderContext) -> Result<markdown
 
     let definitions = context.shortcode_definitions.as_ref();
     // Extract all the defined shortcodes
-    let (content, shortcodes) = extract_shortcodes(content, definitions)?;
+    let file = context.current_page_path.map(|path| format!("content/{}", path));
+    let (content, shortcodes) = extract_shortcodes(
+        content,
+        definitions,
+        file.as_deref(),
+        context.content_line_offset,
+        &columns,
+    )?;
 
     // Step 1: we render the MD shortcodes before rendering the markdown so they can get processed
     let (content, html_shortcodes) =
@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 use std::fmt::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::cache::GenericCache;
-use crate::callouts::ObsidianCalloutsHandler;
+use crate::callouts::{extract_callout_headers, CalloutsHandler};
 use crate::markdown::cmark::CowStr;
 
 use crate::math::{
@@ -26,16 +26,24 @@ use libs::pulldown_cmark_escape::escape_html;
 use libs::regex::{Regex, RegexBuilder};
 use utils::site::resolve_internal_link;
 use utils::slugs::slugify_anchors;
-use utils::table_of_contents::{make_table_of_contents, Heading};
+use utils::table_of_contents::{make_table_of_contents, Heading, HeadingNumbering};
 use utils::types::InsertAnchor;
 
 use self::cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
 use crate::codeblock::{CodeBlock, FenceSettings};
+use crate::diagram::Diagrams;
+use crate::filters::Filters;
 use crate::shortcode::{Shortcode, SHORTCODE_PLACEHOLDER};
 
 const CONTINUE_READING: &str = "<span id=\"continue-reading\"></span>";
 const SUMMARY_CUTOFF_TEMPLATE: &str = "summary-cutoff.html";
 const ANCHOR_LINK_TEMPLATE: &str = "anchor-link.html";
+const TOC_PLACEHOLDER: &str = "<!-- toc -->";
+/// Not overridable, sites often have a `toc.html` partial of their own
+const TOC_TEMPLATE: &str = "__zola_builtins/toc.html";
+const CALLOUT_TEMPLATE: &str = "callout.html";
+/// Where the body of a callout goes in the output of the callout template
+const CALLOUT_BODY_PLACEHOLDER: &str = "<!--zola-callout-body-->";
 static EMOJI_REPLACER: Lazy<EmojiReplacer> = Lazy::new(EmojiReplacer::new);
 
 /// Set as a regex to help match some extra cases. This way, spaces and case don't matter.
@@ -47,6 +55,15 @@ static MORE_DIVIDER_RE: Lazy<Regex> = Lazy::new(|| {
         .unwrap()
 });
 
+/// Same as above, for the marker replaced by the table of contents
+static TOC_MARKER_RE: Lazy<Regex> = Lazy::new(|| {
+    RegexBuilder::new(r#"<!--\s*toc\s*-->"#)
+        .case_insensitive(true)
+        .dot_matches_new_line(true)
+        .build()
+        .unwrap()
+});
+
 static FOOTNOTES_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r#"<sup class="footnote-reference"><a href=\s*.*?>\s*.*?</a></sup>"#).unwrap()
 });
@@ -99,6 +116,8 @@ pub struct Rendered {
     pub internal_links: Vec<(String, Option<String>)>,
     /// Outgoing links to external webpages (i.e. HTTP(S) targets).
     pub external_links: Vec<String>,
+    /// Files read while rendering, such as the ones included in code blocks
+    pub dependencies: Vec<PathBuf>,
 }
 
 /// Tracks a heading in a slice of pulldown-cmark events
@@ -409,6 +428,34 @@ fn convert_footnotes_to_github_style<AD: PartialEq>(old_events: &mut Vec<Event<'
     old_events.push(Event::Html("</ol>\n</footer>\n".into()));
 }
 
+/// Renders the custom title of a callout, which is inline markdown
+fn render_callout_title(title: &str) -> String {
+    let mut html = String::new();
+    let events = Parser::new_ext(title, Options::ENABLE_STRIKETHROUGH).filter(|event| {
+        !matches!(event, Event::Start(Tag::Paragraph) | Event::End(TagEnd::Paragraph))
+    });
+    cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// The destination of every link in the content, with the byte offset where the link starts.
+/// Used to point at the links in the source files when reporting problems with them.
+pub fn find_link_offsets(content: &str) -> Vec<(String, usize)> {
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_FOOTNOTES);
+    opts.insert(Options::ENABLE_STRIKETHROUGH);
+    opts.insert(Options::ENABLE_TASKLISTS);
+    opts.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    Parser::new_ext(content, opts)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Link { dest_url, .. }) => Some((dest_url.to_string(), range.start)),
+            _ => None,
+        })
+        .collect()
+}
+
 pub fn markdown_to_html(
     content: &str,
     context: &RenderContext,
@@ -432,9 +479,10 @@ pub fn markdown_to_html(
     // default HTML body escaping for text nodes.
     let mut inside_attribute = false;
 
-    let mut headings: Vec<Heading> = vec![];
+    let mut toc = vec![];
     let mut internal_links = Vec::new();
     let mut external_links = Vec::new();
+    let mut dependencies = Vec::new();
 
     let mut stop_next_end_p = false;
 
@@ -506,6 +554,23 @@ pub fn markdown_to_html(
         }
     }
 
+    let diagrams = Diagrams::new(
+        &context.config.markdown.diagrams,
+        context.caches.as_ref().map(|e| e.diagrams.clone()),
+        context.base_path,
+    );
+    // The language of the diagram code block we are in
+    let mut diagram: Option<String> = None;
+    // The code of the code block we are in when it comes from its `include` option
+    let mut included_block: Option<String> = None;
+    let filters = Filters::new(
+        &context.config.markdown.filters,
+        context.caches.as_ref().map(|e| e.filters.clone()),
+        path.unwrap_or_default(),
+        context.lang,
+        context.base_path,
+    );
+
     if matches!(context.config.markdown.math.svgo, BoolWithPath::True(_)) {
         Svgo::default().check_bin().map_err(|e| {
             Error::msg(format!(
@@ -577,17 +642,22 @@ pub fn markdown_to_html(
 
         let mut accumulated_block = String::new();
 
+        let (content, mut callout_headers) = extract_callout_headers(content, &context.config);
+        let content = content.as_ref();
+        // The closing HTML of the callouts we are in
+        let mut callout_ends: Vec<String> = Vec::new();
+
         for (event, mut range) in Parser::new_with_callbacks(
             content,
             opts,
             None::<cmark::DefaultBrokenLinkCallback>,
-            ObsidianCalloutsHandler,
+            CalloutsHandler { config: &context.config },
         )
         .into_offset_iter()
         {
             match event {
                 Event::Text(text) => {
-                    if let Some(ref mut _code_block) = code_block {
+                    if code_block.is_some() || diagram.is_some() {
                         if contains_shortcode(text.as_ref()) {
                             // mark the start of the code block events
                             let stack_start = events.len();
@@ -646,11 +716,27 @@ pub fn markdown_to_html(
                         }
                         _ => false,
                     };
-                    if should_render {
+                    let included = fence.include(
+                        context.parent_absolute.as_ref(),
+                        &context.config.markdown.include_dirs,
+                    );
+                    match included {
+                        Ok(Some((file, code))) => {
+                            dependencies.push(file);
+                            included_block = Some(code);
+                        }
+                        Ok(None) => (),
+                        Err(e) => {
+                            error = Some(e);
+                            break;
+                        }
+                    }
+
+                    if let Some(lang) = fence.language.filter(|lang| diagrams.is_diagram(lang)) {
+                        diagram = Some(lang.to_string());
+                    } else if should_render {
                         if let Some(ref compiler) = compiler {
-                            let inner = fence
-                                .include(context.parent_absolute.as_ref())
-                                .unwrap_or(accumulated_block.clone());
+                            let inner = included_block.clone().unwrap_or(accumulated_block.clone());
                             let rendered = compiler.compile(
                                 &inner,
                                 MathRenderMode::Raw,
@@ -679,9 +765,21 @@ pub fn markdown_to_html(
                     }
                 }
                 Event::End(TagEnd::CodeBlock { .. }) => {
+                    let included = included_block.take();
+                    let source = included.as_deref().unwrap_or(&accumulated_block);
+                    if let Some(lang) = diagram.take() {
+                        match diagrams.render(&lang, source) {
+                            Ok(html) => events.push(Event::Html(html.into())),
+                            Err(e) => error = Some(e),
+                        }
+                    }
                     if let Some(ref mut code_block) = code_block {
-                        let html = code_block.highlight(&accumulated_block);
+                        let html = code_block.highlight(source);
                         events.push(Event::Html(html.into()));
+                        events.push(Event::Html("</code></pre>\n".into()));
+                        if let Some(notes) = code_block.annotations() {
+                            events.push(Event::Html(notes.into()));
+                        }
                     }
 
                     // reset code block state
@@ -715,14 +813,59 @@ pub fn markdown_to_html(
                         Event::Start(Tag::Image { link_type, dest_url: link, title, id })
                     });
                 }
-                Event::Start(Tag::BlockQuote(Some(kind))) => {
-                    const CALLOUT_START: &str = include_str!("../assets/callouts/start.tmpl");
-                    let class = kind.as_str();
+                Event::Start(Tag::BlockQuote(Some(tag))) => {
+                    let (kind, default_title) = context
+                        .config
+                        .markdown
+                        .get_callout_kind(tag.as_str())
+                        .expect("callout tags are checked by the handler");
+                    let line_start = content[..range.start].rfind('\n').map_or(0, |i| i + 1);
+                    let header = callout_headers.remove(&line_start).unwrap_or_default();
+                    let title = match header.title {
+                        Some(title) => render_callout_title(&title),
+                        None => {
+                            let mut buffer = String::new();
+                            escape_html(&mut buffer, default_title.unwrap_or(kind)).unwrap();
+                            buffer
+                        }
+                    };
 
-                    events.push(Event::Html(CALLOUT_START.replace("{{kind}}", &class).into()));
+                    let mut c = tera::Context::new();
+                    c.insert("kind", kind);
+                    c.insert("title", &title);
+                    c.insert("foldable", &header.fold.is_some());
+                    c.insert("open", &header.fold.unwrap_or(true));
+                    c.insert("body", CALLOUT_BODY_PLACEHOLDER);
+                    c.insert("lang", &context.lang);
+                    let rendered = utils::templates::render_template(
+                        CALLOUT_TEMPLATE,
+                        &context.tera,
+                        c,
+                        &None,
+                    )
+                    .context("Failed to render callout template");
+                    match rendered {
+                        Ok(rendered) => match rendered.split_once(CALLOUT_BODY_PLACEHOLDER) {
+                            Some((start, end)) => {
+                                events.push(Event::Html(start.to_string().into()));
+                                callout_ends.push(end.to_string());
+                            }
+                            None => {
+                                error = Some(Error::msg(
+                                    "The callout template needs to output the `body` variable with the `safe` filter",
+                                ));
+                                callout_ends.push(String::new());
+                            }
+                        },
+                        Err(e) => {
+                            error = Some(e);
+                            callout_ends.push(String::new());
+                        }
+                    }
                 }
                 Event::End(TagEnd::BlockQuote(Some(_))) => {
-                    events.push(Event::Html("</div>".into()));
+                    let end = callout_ends.pop().unwrap_or_default();
+                    events.push(Event::Html(end.into()));
                 }
                 Event::End(TagEnd::Image) => events.push(if lazy_async_image {
                     Event::Html("\" loading=\"lazy\" decoding=\"async\" />".into())
@@ -839,6 +982,18 @@ pub fn markdown_to_html(
                     has_summary = true;
                     events.push(Event::Html(CONTINUE_READING.into()));
                 }
+                Event::Html(text) | Event::InlineHtml(text)
+                    if TOC_MARKER_RE.is_match(text.as_ref()) =>
+                {
+                    // Only the marker is replaced, the HTML around it is kept
+                    let mut last = 0;
+                    for marker in TOC_MARKER_RE.find_iter(text.as_ref()) {
+                        events.push(Event::Html(text[last..marker.start()].to_string().into()));
+                        events.push(Event::Html(TOC_PLACEHOLDER.into()));
+                        last = marker.end();
+                    }
+                    events.push(Event::Html(text[last..].to_string().into()));
+                }
                 Event::Html(text) | Event::InlineHtml(text)
                     if contains_shortcode(text.as_ref()) =>
                 {
@@ -857,6 +1012,8 @@ pub fn markdown_to_html(
         let heading_refs = get_heading_refs(&events);
 
         let mut anchors_to_insert = vec![];
+        let mut headings = vec![];
+        let mut numbering = HeadingNumbering::default();
         let mut inserted_anchors = vec![];
         for heading in &heading_refs {
             if let Some(s) = &heading.id {
@@ -873,7 +1030,7 @@ pub fn markdown_to_html(
             if heading_ref.id.is_none() {
                 heading_ref.id = Some(find_anchor(
                     &inserted_anchors,
-                    slugify_anchors(&title, context.config.slugify.anchors),
+                    slugify_anchors(&title, context.config.slugify_for(context.lang).anchors),
                     0,
                 ));
             }
@@ -884,6 +1041,11 @@ pub fn markdown_to_html(
             let html = heading_ref.to_html(id);
             events[start_idx] = Event::Html(html.into());
 
+            let in_toc = context.toc.includes(heading_ref.level);
+            let number =
+                (in_toc && context.toc.numbering).then(|| numbering.next(heading_ref.level));
+            let mut heading_inserts = vec![];
+
             // generate anchors and places to insert them
             if context.insert_anchor != InsertAnchor::None {
                 let anchor_idx = match context.insert_anchor {
@@ -905,14 +1067,22 @@ pub fn markdown_to_html(
                 )
                 .context("Failed to render anchor link template")?;
                 if context.insert_anchor != InsertAnchor::Heading {
-                    anchors_to_insert.push((anchor_idx, Event::Html(anchor_link.into())));
+                    heading_inserts.push((anchor_idx, Event::Html(anchor_link.into())));
                 } else if let Some(captures) = A_HTML_TAG.captures(&anchor_link) {
                     let opening_tag = captures.get(1).map_or("", |m| m.as_str()).to_string();
-                    anchors_to_insert.push((start_idx + 1, Event::Html(opening_tag.into())));
-                    anchors_to_insert.push((end_idx, Event::Html("</a>".into())));
+                    heading_inserts.push((start_idx + 1, Event::Html(opening_tag.into())));
+                    heading_inserts.push((end_idx, Event::Html("</a>".into())));
                 }
             }
 
+            if let Some(ref number) = number {
+                let number = format!("<span class=\"heading-number\">{}</span> ", number);
+                heading_inserts.push((start_idx + 1, Event::Html(number.into())));
+            }
+            // The number goes after the anchor if they are at the same position
+            heading_inserts.sort_by_key(|(idx, _)| *idx);
+            anchors_to_insert.extend(heading_inserts);
+
             // record heading to make table of contents
             let permalink = format!("{}#{}", context.current_page_permalink, id);
             let h = Heading {
@@ -920,19 +1090,37 @@ pub fn markdown_to_html(
                 id: id.to_owned(),
                 permalink,
                 title,
+                number,
                 children: Vec::new(),
             };
-            headings.push(h);
+            if in_toc {
+                headings.push(h);
+            }
         }
 
-        if context.insert_anchor != InsertAnchor::None {
-            insert_many(&mut events, anchors_to_insert);
+        insert_many(&mut events, anchors_to_insert);
+        toc = make_table_of_contents(headings);
+
+        if events.iter().any(|e| matches!(e, Event::Html(CowStr::Borrowed(TOC_PLACEHOLDER)))) {
+            let mut c = tera::Context::new();
+            c.insert("toc", &toc);
+            c.insert("lang", &context.lang);
+            let rendered_toc =
+                utils::templates::render_template(TOC_TEMPLATE, &context.tera, c, &None)
+                    .context("Failed to render table of contents template")?;
+            for event in events.iter_mut() {
+                if matches!(event, Event::Html(CowStr::Borrowed(TOC_PLACEHOLDER))) {
+                    *event = Event::Html(rendered_toc.clone().into());
+                }
+            }
         }
 
         if context.config.markdown.bottom_footnotes {
             convert_footnotes_to_github_style(&mut events);
         }
 
+        events = filters.apply_to_events(events)?;
+
         let continue_reading = events
             .iter()
             .position(|e| matches!(e, Event::Html(CowStr::Borrowed(CONTINUE_READING))))
@@ -987,21 +1175,21 @@ pub fn markdown_to_html(
         if let Some(ref compiler) = compiler {
             compiler.write_cache()?;
         }
+        diagrams.write_cache()?;
 
         // emit everything after summary
         cmark::html::push_html(&mut html, events);
+
+        html = filters.apply_to_html(html)?;
+        // Filtered on its own as it's built from the HTML before the filters
+        summary = summary.map(|summary| filters.apply_to_html(summary)).transpose()?;
+        filters.write_cache()?;
     }
 
     if let Some(e) = error {
         Err(e)
     } else {
-        Ok(Rendered {
-            summary,
-            body: html,
-            toc: make_table_of_contents(headings),
-            internal_links,
-            external_links,
-        })
+        Ok(Rendered { summary, body: html, toc, internal_links, external_links, dependencies })
     }
 }
 
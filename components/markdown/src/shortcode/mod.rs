@@ -4,19 +4,28 @@ use errors::Result;
 use libs::tera;
 use utils::templates::{ShortcodeDefinition, ShortcodeFileType, ShortcodeInvocationCounter};
 
+use crate::directives::ColumnMap;
+
 mod parser;
 
 pub(crate) use parser::{parse_for_shortcodes, Shortcode, SHORTCODE_PLACEHOLDER};
 
-/// Extracts the shortcodes present in the source, check if we know them and errors otherwise
+/// Extracts the shortcodes present in the source, check if we know them and errors otherwise.
+/// `file`, `line_offset` and `columns` are used to report where the shortcodes are in the
+/// content file.
 pub fn extract_shortcodes(
     source: &str,
     definitions: &HashMap<String, ShortcodeDefinition>,
+    file: Option<&str>,
+    line_offset: usize,
+    columns: &ColumnMap,
 ) -> Result<(String, Vec<Shortcode>)> {
     let (out, mut shortcodes) =
         parse_for_shortcodes(source, &mut ShortcodeInvocationCounter::new())?;
 
     for sc in &mut shortcodes {
+        sc.map_columns(columns);
+        sc.locate(file, line_offset);
         sc.fill_tera_name(definitions)?;
     }
 
@@ -78,6 +87,7 @@ mod tests {
                         nth: 1,
                         inner: Vec::new(),
                         tera_name: "shortcodes/a.md".to_owned(),
+                        location: Default::default(),
                     },
                     Shortcode {
                         name: "a".to_string(),
@@ -87,6 +97,7 @@ mod tests {
                         nth: 2,
                         inner: Vec::new(),
                         tera_name: "shortcodes/a.md".to_owned(),
+                        location: Default::default(),
                     }
                 ],
                 &tera_context,
@@ -108,6 +119,7 @@ mod tests {
                     nth: 1,
                     inner: Vec::new(),
                     tera_name: "shortcodes/bodied.md".to_owned(),
+                    location: Default::default(),
                 },],
                 &tera_context,
                 &tera
@@ -134,8 +146,10 @@ mod tests {
                         nth: 1,
                         inner: Vec::new(),
                         tera_name: "shortcodes/bodied.md".to_owned(),
+                        location: Default::default(),
                     },],
                     tera_name: "shortcodes/bodied.md".to_owned(),
+                    location: Default::default(),
                 },],
                 &tera_context,
                 &tera
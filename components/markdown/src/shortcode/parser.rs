@@ -1,4 +1,4 @@
-use std::{collections::HashMap, ops::Range};
+use std::{collections::HashMap, fmt, ops::Range};
 
 use errors::{bail, Context as ErrorContext, Result};
 use libs::tera::{to_value, Context, Map, Tera, Value};
@@ -7,8 +7,48 @@ use pest::Parser;
 use pest_derive::Parser;
 use utils::templates::{ShortcodeDefinition, ShortcodeFileType, ShortcodeInvocationCounter};
 
+use crate::directives::ColumnMap;
+
 pub const SHORTCODE_PLACEHOLDER: &str = "@@ZOLA_SC_PLACEHOLDER@@";
 
+/// Where a shortcode is called in a content file
+#[derive(PartialEq, Debug, Eq, Clone, Default)]
+pub struct ShortcodeLocation {
+    /// The path of the file relative to the site root, if known
+    pub(crate) file: Option<String>,
+    /// 1-based, in the whole file once `ShortcodeLocation::shift` has been called
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+impl ShortcodeLocation {
+    fn new((line, column): (usize, usize)) -> Self {
+        ShortcodeLocation { file: None, line, column }
+    }
+}
+
+impl fmt::Display for ShortcodeLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file, self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+/// Returns the 1-based position after `text` if it starts at `(line, column)`
+fn advance((mut line, mut column): (usize, usize), text: &str) -> (usize, usize) {
+    for c in text.chars() {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
 #[derive(PartialEq, Debug, Eq)]
 pub struct Shortcode {
     pub(crate) name: String,
@@ -20,20 +60,27 @@ pub struct Shortcode {
     pub(crate) inner: Vec<Shortcode>,
     // set later down the line, for quick access without needing the definitions
     pub(crate) tera_name: String,
+    pub(crate) location: ShortcodeLocation,
 }
 
 impl Shortcode {
     /// Attempts to fill the `tera_name` field from the provided definitions for self and all of self.inner.
     ///
-    /// This returns an error if the definitions do not have this shortcode.
+    /// This returns an error if the definitions do not have this shortcode or if its arguments
+    /// do not match its schema. The default values of the arguments are filled in as well.
     pub fn fill_tera_name(
         &mut self,
         definitions: &HashMap<String, ShortcodeDefinition>,
     ) -> Result<()> {
         if let Some(def) = definitions.get(&self.name) {
             self.tera_name = def.tera_name.clone();
+            if let (Some(schema), Value::Object(args)) = (&def.schema, &mut self.args) {
+                schema.apply(&self.name, args).with_context(|| {
+                    format!("Invalid arguments for shortcode `{}` at {}", self.name, self.location)
+                })?;
+            }
         } else {
-            return Err(errors::anyhow!("Found usage of a shortcode named `{}` but we do not know about. Make sure it's not a typo and that a field name `{}.{{html,md}}` exists in the `templates/shortcodes` directory.", self.name, self.name));
+            return Err(errors::anyhow!("Found usage of a shortcode named `{}` at {} but we do not know about. Make sure it's not a typo and that a field name `{}.{{html,md}}` exists in the `templates/shortcodes` directory.", self.name, self.location, self.name));
         }
         for inner_sc in self.inner.iter_mut() {
             inner_sc.fill_tera_name(definitions)?;
@@ -41,6 +88,37 @@ impl Shortcode {
         Ok(())
     }
 
+    /// Gives the columns of self and all of self.inner in the content before the directives
+    /// were rewritten
+    pub fn map_columns(&mut self, columns: &ColumnMap) {
+        self.location.column = columns.original_column(self.location.line, self.location.column);
+        for inner_sc in self.inner.iter_mut() {
+            inner_sc.map_columns(columns);
+        }
+    }
+
+    /// Makes the location of self and all of self.inner relative to the file rather than the
+    /// content given to `parse_for_shortcodes`, which starts after `line_offset` lines
+    pub fn locate(&mut self, file: Option<&str>, line_offset: usize) {
+        self.location.file = file.map(|f| f.to_string());
+        self.location.line += line_offset;
+        for inner_sc in self.inner.iter_mut() {
+            inner_sc.locate(file, line_offset);
+        }
+    }
+
+    /// Makes the location of self and all of self.inner relative to the text containing the
+    /// body they come from, which starts at `start`
+    fn shift(&mut self, start: (usize, usize)) {
+        if self.location.line == 1 {
+            self.location.column += start.1 - 1;
+        }
+        self.location.line += start.0 - 1;
+        for inner_sc in self.inner.iter_mut() {
+            inner_sc.shift(start);
+        }
+    }
+
     pub fn file_type(&self) -> ShortcodeFileType {
         if self.tera_name.ends_with("md") {
             ShortcodeFileType::Markdown
@@ -75,9 +153,12 @@ impl Shortcode {
         // 2. as an .html shortcode, the result is inserted into the document _during_ MD -> HTML conversion. (The HTML
         //    is injected into cmark's AST)
         // 3. As an inner-part of a shortcode which is being flattened. The file_type is not considered.
-        self.render_inner_shortcodes(tera, context)?;
+        self.render_inner_shortcodes(tera, context).with_context(|| {
+            format!("Failed to render the body of {} shortcode at {}", self.name, self.location)
+        })?;
 
         let name = self.name;
+        let location = self.location;
         let tpl_name = self.tera_name;
         let mut new_context = Context::from_value(self.args)?;
 
@@ -89,7 +170,7 @@ impl Shortcode {
         new_context.extend(context.clone());
 
         let res = utils::templates::render_template(&tpl_name, tera, new_context, &None)
-            .with_context(|| format!("Failed to render {} shortcode", name))?
+            .with_context(|| format!("Failed to render {} shortcode at {}", name, location))?
             .replace("\r\n", "\n");
 
         Ok(res)
@@ -250,6 +331,7 @@ pub fn parse_for_shortcodes(
             Rule::text => output.push_str(p.as_span().as_str()),
             Rule::inline_shortcode => {
                 let start = output.len();
+                let location = ShortcodeLocation::new(p.as_span().start_pos().line_col());
                 let (name, args) = parse_shortcode_call(p);
                 let nth = invocation_counter.get(&name);
                 shortcodes.push(Shortcode {
@@ -260,20 +342,29 @@ pub fn parse_for_shortcodes(
                     nth,
                     inner: Vec::new(),
                     tera_name: String::new(),
+                    location,
                 });
                 output.push_str(SHORTCODE_PLACEHOLDER);
             }
             Rule::shortcode_with_body => {
                 let start = output.len();
+                let location = ShortcodeLocation::new(p.as_span().start_pos().line_col());
                 let mut inner = p.into_inner();
                 // 3 items in inner: call, body, end
                 // we don't care about the closing tag
                 let (name, args) = parse_shortcode_call(inner.next().unwrap());
                 let nth = invocation_counter.get(&name);
-                let (body, inner) = parse_for_shortcodes(
-                    inner.next().unwrap().as_span().as_str().trim(),
-                    invocation_counter,
-                )?;
+                let body_pair = inner.next().unwrap();
+                let raw_body = body_pair.as_span().as_str();
+                let trimmed_body = raw_body.trim();
+                let body_start = advance(
+                    body_pair.as_span().start_pos().line_col(),
+                    &raw_body[..raw_body.len() - raw_body.trim_start().len()],
+                );
+                let (body, mut inner) = parse_for_shortcodes(trimmed_body, invocation_counter)?;
+                for inner_sc in inner.iter_mut() {
+                    inner_sc.shift(body_start);
+                }
                 shortcodes.push(Shortcode {
                     name,
                     args,
@@ -282,6 +373,7 @@ pub fn parse_for_shortcodes(
                     nth,
                     inner,
                     tera_name: String::new(),
+                    location,
                 });
                 output.push_str(SHORTCODE_PLACEHOLDER)
             }
@@ -425,6 +517,7 @@ mod tests {
             nth: 0,
             inner: Vec::new(),
             tera_name: String::new(),
+            location: Default::default(),
         };
         // 6 -> 10 in length so +4 on both sides of the range
         sc.update_range(&(2..8), 10);
@@ -445,6 +538,7 @@ mod tests {
             nth: 0,
             inner: Vec::new(),
             tera_name: String::new(),
+            location: Default::default(),
         };
         sc.update_range(&(9..32), 3);
         assert_eq!(sc.span, 22..45);
@@ -551,6 +645,25 @@ mod tests {
         assert_eq!(shortcodes[0].body, Some(format!("Somebody {SHORTCODE_PLACEHOLDER}")));
     }
 
+    #[test]
+    fn can_locate_shortcodes() {
+        let (_, mut shortcodes) = parse_for_shortcodes(
+            "Hello\n  {{ a() }}\n{% b() %}\nhey {{ c() }} {% d() %} {{ e() }}{% end %}\n{% end %}",
+            &mut ShortcodeInvocationCounter::new(),
+        )
+        .unwrap();
+        assert_eq!(shortcodes[0].location.to_string(), "line 2, column 3");
+        assert_eq!(shortcodes[1].location.to_string(), "line 3, column 1");
+        let inner = &shortcodes[1].inner;
+        assert_eq!(inner[0].location.to_string(), "line 4, column 5");
+        assert_eq!(inner[1].location.to_string(), "line 4, column 15");
+        assert_eq!(inner[1].inner[0].location.to_string(), "line 4, column 25");
+
+        shortcodes[1].locate(Some("content/page.md"), 4);
+        assert_eq!(shortcodes[1].location.to_string(), "content/page.md:7:1");
+        assert_eq!(shortcodes[1].inner[1].inner[0].location.to_string(), "content/page.md:8:25");
+    }
+
     #[test]
     fn can_handle_multiple_shortcodes() {
         let (_, shortcodes) = parse_for_shortcodes(
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use config::Config;
 
 mod common;
@@ -37,6 +39,12 @@ bar
     insta::assert_snapshot!(body);
 }
 
+#[test]
+fn closes_code_blocks_once() {
+    let body = render_codeblock("```\nfoo\n```\n\nbar\n", HighlightMode::None);
+    assert_eq!(body, "<pre><code>foo\n</code></pre>\n<p>bar</p>\n");
+}
+
 #[test]
 fn can_hide_lines() {
     let body = render_codeblock(
@@ -390,3 +398,135 @@ bar
     );
     insta::assert_snapshot!(body);
 }
+
+#[test]
+fn can_render_diagrams() {
+    let mut config = Config::default_for_test();
+    config.markdown.highlight_code = true;
+    config.markdown.diagrams.graphviz = true;
+    let body = common::render_with_config(
+        r#"
+```dot
+digraph { a -> b }
+```
+
+```rust
+fn main() {}
+```
+    "#,
+        config,
+    )
+    .unwrap()
+    .body;
+    assert!(body.starts_with("<div class=\"diagram diagram-dot\"><svg"));
+    assert!(!body.contains("digraph"));
+    assert!(body.contains("<pre data-lang=\"rust\""));
+}
+
+#[test]
+fn errors_on_invalid_diagrams() {
+    let mut config = Config::default_for_test();
+    config.markdown.diagrams.graphviz = true;
+    let res = common::render_with_config("```graphviz\ndigraph { a -> \n```", config);
+    assert!(res.is_err());
+}
+
+#[cfg(feature = "tree-sitter")]
+#[test]
+fn can_highlight_with_tree_sitter() {
+    use config::HighlighterBackend;
+
+    let mut config = Config::default_for_test();
+    config.markdown.highlight_code = true;
+    config.markdown.highlight_theme = "css".to_owned();
+    config.markdown.highlighter = HighlighterBackend::TreeSitter;
+    let body = common::render_with_config(
+        r#"
+```tsx hl_lines=2
+const a = <div>{x}</div>;
+let b: number = 1;
+```
+
+```fortran
+print *, "hello"
+```
+    "#,
+        config,
+    )
+    .unwrap()
+    .body;
+    assert!(body.contains("<pre data-lang=\"tsx\" class=\"language-tsx z-code\">"));
+    assert!(body.contains("<span class=\"z-keyword\">const</span>"));
+    assert!(body.contains("<mark><span class=\"z-keyword\">let</span>"));
+    // Languages without a bundled grammar are still highlighted with syntect
+    assert!(body.contains("<pre data-lang=\"fortran\" class=\"language-fortran z-code\">"));
+}
+
+#[test]
+fn leaves_marker_comments_of_plain_blocks_alone() {
+    let body = render_codeblock("```\nadd(); // [!code ++]\n```\n", HighlightMode::None);
+    assert_eq!(body, "<pre><code>add(); // [!code ++]\n</code></pre>\n");
+}
+
+#[test]
+fn can_render_diff_focus_and_annotations() {
+    let body = render_codeblock(
+        r#"
+```rust,diff,annotate,markers,focus_lines=2-3
+fn main() {
+-    old();
++    new(); // (1) Calls the <new> API
+    done(); // [!code highlight]
+}
+```
+
+```diff
++a
+```
+    "#,
+        HighlightMode::None,
+    );
+    assert!(body.contains("<span class=\"dim\">fn main() {\n</span>"));
+    assert!(body.contains("<span class=\"del\">-    old();\n</span>"));
+    assert!(body.contains(
+        "<span class=\"ins\">+    new();<span class=\"code-annotation\">1</span>\n</span>"
+    ));
+    assert!(body.contains("<mark><span class=\"dim\">    done();\n</span></mark>"));
+    assert!(body.contains(
+        "}\n</span></code></pre>\n<ol class=\"code-annotations\"><li value=\"1\">Calls the &lt;new&gt; API</li></ol>"
+    ));
+    // A diff block on its own is highlighted as a diff
+    assert!(body.contains("<code class=\"language-diff\" data-lang=\"diff\">+a\n</code></pre>"));
+}
+
+#[test]
+fn can_include_files_in_codeblocks() {
+    let mut config = Config::default_for_test();
+    config.markdown.include_dirs = vec![PathBuf::from(env!("CARGO_MANIFEST_DIR"))];
+    let res = common::render_with_config(
+        r#"
+```toml,include="/Cargo.toml",lines=1-2
+This is replaced by the file
+```
+    "#,
+        config.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.body,
+        "<pre data-lang=\"toml\" class=\"language-toml \"><code class=\"language-toml\" data-lang=\"toml\">[package]\nname = &quot;markdown&quot;\n</code></pre>\n"
+    );
+    assert_eq!(
+        res.dependencies,
+        vec![PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml")]
+    );
+
+    for lines in ["abc", "500-600"] {
+        let content = format!("```toml,include=\"/Cargo.toml\",lines={}\n```", lines);
+        let res = common::render_with_config(&content, config.clone());
+        assert!(res.is_err(), "{}", lines);
+    }
+
+    let res = common::render_with_config("```rust,include=nope.rs\n```", config);
+    assert!(res.is_err());
+}
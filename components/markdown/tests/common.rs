@@ -8,6 +8,7 @@ use config::Config;
 use errors::Result;
 use markdown::{render_content, RenderContext, Rendered};
 use templates::ZOLA_TERA;
+use utils::templates::ShortcodeSchema;
 use utils::types::InsertAnchor;
 
 fn configurable_render(
@@ -54,6 +55,10 @@ fn configurable_render(
     .unwrap();
     tera.add_raw_template("shortcodes/md_passthrough.md", "{{body}}").unwrap();
     tera.add_raw_template("shortcodes/nth.html", "{{ nth }}").unwrap();
+    tera.add_raw_template("shortcodes/typed.html", "<img src='{{ src }}' width='{{ width }}'>")
+        .unwrap();
+    // A partial of the site that must not replace the built-in table of contents
+    tera.add_raw_template("toc.html", "<p>site toc partial</p>").unwrap();
 
     let mut permalinks = HashMap::new();
     permalinks.insert("pages/about.md".to_owned(), "https://getzola.org/about/".to_owned());
@@ -71,7 +76,22 @@ fn configurable_render(
         insert_anchor,
         None,
     );
-    let shortcode_def = utils::templates::get_shortcodes(&tera);
+    let mut shortcode_def = utils::templates::get_shortcodes(&tera);
+    templates::load_builtin_shortcode_schemas(&mut shortcode_def);
+    shortcode_def.get_mut("typed").unwrap().schema = Some(
+        ShortcodeSchema::parse(
+            r#"
+[args.src]
+type = "string"
+required = true
+
+[args.width]
+type = "integer"
+default = 800
+"#,
+        )
+        .unwrap(),
+    );
     context.set_shortcode_definitions(&shortcode_def);
     context.set_current_page_path("my_page.md");
 
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use libs::tera::Tera;
 
-use config::Config;
+use config::{CalloutKind, Config, MarkdownFilter};
 use markdown::{render_content, RenderContext};
 use templates::ZOLA_TERA;
 use utils::slugs::SlugifyStrategy;
@@ -451,3 +451,56 @@ Footnotes can also be referenced with identifiers[^first].
     let body = common::render_with_config(&markdown, config).unwrap().body;
     insta::assert_snapshot!(body);
 }
+
+#[test]
+fn can_render_callouts() {
+    let body = common::render("> [!NOTE]\n> Some *text*").unwrap().body;
+    assert!(body.starts_with(
+        "<div class=\"callouts callouts-note\">\n<p class=\"callouts-title\">\n  <span class=\"callouts-icon\"></span>\n  note\n</p>\n"
+    ));
+    assert!(body.contains("<p>Some <em>text</em></p>"));
+    assert!(body.trim_end().ends_with("</div>"));
+}
+
+#[test]
+fn can_render_callouts_with_custom_titles_and_folds() {
+    let body = common::render("> [!tip]- A *custom* title\n> Hidden").unwrap().body;
+    assert!(body.starts_with("<details class=\"callouts callouts-tip\">\n<summary"));
+    assert!(body.contains("  A <em>custom</em> title\n</summary>"));
+    assert!(body.contains("<p>Hidden</p>"));
+    assert!(body.trim_end().ends_with("</details>"));
+
+    let body = common::render("> [!tip]+\n> Shown").unwrap().body;
+    assert!(body.starts_with("<details class=\"callouts callouts-tip\" open>"));
+}
+
+#[test]
+fn can_render_configured_callouts() {
+    let mut config = Config::default_for_test();
+    config.markdown.callouts.insert(
+        "todo".to_string(),
+        CalloutKind { aliases: vec!["task".to_string()], title: Some("To do".to_string()) },
+    );
+    let body = common::render_with_config("> [!Task]\n> Write docs", config.clone()).unwrap().body;
+    assert!(body.starts_with("<div class=\"callouts callouts-todo\">"));
+    assert!(body.contains("  To do\n</p>"));
+
+    let body = common::render_with_config("> [!unknown]\n> Text", config).unwrap().body;
+    assert!(body.starts_with("<blockquote>"));
+}
+
+#[cfg(unix)]
+#[test]
+fn can_apply_markdown_filters() {
+    let mut config = Config::default_for_test();
+    config
+        .markdown
+        .filters
+        .push(MarkdownFilter { command: "sed s/Hello/Bye/".to_string(), ..Default::default() });
+    let body = common::render_with_config("Hello *world*", config.clone()).unwrap().body;
+    assert_eq!(body, "<p>Bye <em>world</em></p>\n");
+
+    config.markdown.filters[0].command = "false".to_string();
+    let err = common::render_with_config("Hello", config).unwrap_err();
+    assert_eq!(err.to_string(), "Failed to run the markdown filter `false`");
+}
@@ -144,6 +144,46 @@ Hello
     insta::assert_snapshot!(body);
 }
 
+#[test]
+fn can_use_shortcode_schema_defaults() {
+    let body = common::render(r#"{{ typed(src="a.jpg") }}"#).unwrap().body;
+    assert!(body.contains("<img src='a.jpg' width='800'>"));
+}
+
+#[test]
+fn errors_on_shortcode_args_not_matching_schema() {
+    for (content, expected) in [
+        (r#"{{ typed(src="a.jpg", widht=100) }}"#, "Unknown argument `widht`"),
+        (r#"{{ typed(width=100) }}"#, "Missing required argument `src`"),
+        (r#"{{ typed(src="a.jpg", width="100") }}"#, "should be an integer but got a string"),
+    ] {
+        let err = common::render(content).unwrap_err();
+        assert!(format!("{:#}", err).contains(expected), "{:#}", err);
+    }
+}
+
+#[test]
+fn reports_location_of_failing_shortcodes() {
+    let err = common::render("Hello\n\n  {{ unknown() }}").unwrap_err();
+    assert!(format!("{:#}", err).contains("content/my_page.md:3:3"), "{:#}", err);
+
+    let err = common::render("{% quote() %}\nHello {{ typed(widht=1) }}\n{% end %}").unwrap_err();
+    assert!(
+        format!("{:#}", err)
+            .contains("Invalid arguments for shortcode `typed` at content/my_page.md:2:7"),
+        "{:#}",
+        err
+    );
+
+    let err = common::render("Hey {{ image(alt=1) }}\n{{ out_put_id() }}").unwrap_err();
+    assert!(
+        format!("{:#}", err)
+            .contains("Failed to render out_put_id shortcode at content/my_page.md:2:1"),
+        "{:#}",
+        err
+    );
+}
+
 // https://github.com/getzola/zola/issues/1172
 #[test]
 fn doesnt_escape_html_shortcodes() {
@@ -397,3 +437,142 @@ fn can_render_nested_shortcodes_with_bodies_with_nth() {
     .body;
     insta::assert_snapshot!(body);
 }
+
+#[test]
+fn can_use_builtin_youtube_and_vimeo_shortcodes() {
+    let body =
+        common::render(r#"{{ youtube(id="dQw4w9WgXcQ", start=30, autoplay=true) }}"#).unwrap().body;
+    assert!(body.contains(
+        r#"src="https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?autoplay=1&amp;start=30""#
+    ));
+    assert!(body.contains(r#"title="YouTube video""#));
+
+    let body = common::render(r#"{{ vimeo(id="124313553", class="wide") }}"#).unwrap().body;
+    assert!(body.contains(r#"<div class="embed vimeo wide">"#));
+    assert!(body.contains(r#"src="https://player.vimeo.com/video/124313553?dnt=1""#));
+}
+
+#[test]
+fn can_use_builtin_figure_shortcode() {
+    let body = common::render(
+        r#"{{ figure(src="https://example.com/cat.jpg", alt="A cat", caption="A *cute* cat") }}"#,
+    )
+    .unwrap()
+    .body;
+    assert!(body.contains(r#"<img src="https://example.com/cat.jpg" alt="A cat" loading="lazy">"#));
+    assert!(body.contains("<figcaption>A <em>cute</em> cat</figcaption>"));
+
+    let err = common::render(r#"{{ figure(alt="No source") }}"#).unwrap_err();
+    assert!(format!("{:#}", err).contains("Missing required argument `src`"));
+}
+
+#[test]
+fn can_use_builtin_gist_and_asciinema_shortcodes() {
+    let body = common::render(
+        r#"{{ gist(url="https://gist.github.com/Keats/e5fb6aad409f28721c0ba14161644c57/", file="cli.rs") }}"#,
+    )
+    .unwrap()
+    .body;
+    assert!(body.contains(
+        r#"<script src="https://gist.github.com/Keats/e5fb6aad409f28721c0ba14161644c57.js?file=cli.rs"></script>"#
+    ));
+
+    let body = common::render(r#"{{ asciinema(id="335480", speed=1.5) }}"#).unwrap().body;
+    assert!(body.contains(
+        r#"<script src="https://asciinema.org/a/335480.js" id="asciicast-335480" async data-speed="1.5"></script>"#
+    ));
+}
+
+#[test]
+fn can_use_builtin_details_and_mermaid_shortcodes() {
+    let body = common::render(
+        r#"{% details(summary="More **info**", open=true) %}
+Some *hidden* text
+{% end %}
+
+{% mermaid() %}
+graph TD
+    A --> B
+{% end %}"#,
+    )
+    .unwrap()
+    .body;
+    assert!(body.contains("<details open>"));
+    assert!(body.contains("<summary>More <strong>info</strong></summary>"));
+    assert!(body.contains("<p>Some <em>hidden</em> text</p>"));
+    assert!(body.contains("<pre class=\"mermaid\">\ngraph TD\n    A --&gt; B\n</pre>"));
+}
+
+#[test]
+fn can_use_builtin_tabs_shortcode() {
+    let body = common::render(
+        r#"{% tabs() %}
+{% tab(title="Rust") %}
+`cargo build`
+{% end %}
+{% tab(title="Python") %}
+`pip install`
+{% end %}
+{% end %}"#,
+    )
+    .unwrap()
+    .body;
+    assert!(body
+        .contains(r#"<input type="radio" class="tab-input" name="tabs-1" id="tabs-1-1" checked>"#));
+    assert!(body.contains(r#"<label class="tab-label" for="tabs-1-2">Python</label>"#));
+    assert!(body.contains(r#"<div class="tab-panel"><p><code>cargo build</code></p>"#));
+}
+
+#[test]
+fn can_override_builtin_shortcodes() {
+    let mut tera = libs::tera::Tera::default();
+    tera.extend(&templates::ZOLA_TERA).unwrap();
+    tera.add_raw_template("shortcodes/youtube.html", "my {{ id }}").unwrap();
+    let mut definitions = utils::templates::get_shortcodes(&tera);
+    templates::load_builtin_shortcode_schemas(&mut definitions);
+    assert_eq!(definitions["youtube"].tera_name, "shortcodes/youtube.html");
+    assert!(definitions["youtube"].schema.is_none());
+    assert!(definitions["figure"].schema.is_some());
+}
+
+#[test]
+fn can_use_directives_to_call_shortcodes() {
+    let mut config = Config::default_for_test();
+    config.markdown.directives = true;
+    let body = common::render_with_config(
+        r#"Watch ::youtube{id=dQw4w9WgXcQ start=30} now.
+
+:::details{summary="More" .fancy}
+Some *hidden* text
+:::
+
+```
+::youtube{id=in_code}
+```
+"#,
+        config,
+    )
+    .unwrap()
+    .body;
+    assert!(body.contains("youtube-nocookie.com/embed/dQw4w9WgXcQ?start=30"));
+    assert!(body.contains(r#"<details class="fancy">"#));
+    assert!(body.contains("<p>Some <em>hidden</em> text</p>"));
+    assert!(body.contains("::youtube{id=in_code}"));
+}
+
+#[test]
+fn doesnt_parse_directives_unless_enabled() {
+    let body = common::render("Watch ::youtube{id=abc}").unwrap().body;
+    assert_eq!(body, "<p>Watch ::youtube{id=abc}</p>\n");
+}
+
+#[test]
+fn reports_shortcode_errors_at_their_position_with_directives() {
+    let mut config = Config::default_for_test();
+    config.markdown.directives = true;
+    let err = common::render_with_config("Hi ::youtube{id=a} and {{ unknown() }}", config.clone())
+        .unwrap_err();
+    assert!(err.to_string().contains("content/my_page.md:1:24"));
+    let err = common::render_with_config("Hi ::unknown{a=1}", config).unwrap_err();
+    assert!(err.to_string().contains("content/my_page.md:1:4"));
+}
@@ -1,5 +1,7 @@
 mod common;
 
+use config::Config;
+
 #[test]
 fn can_make_simple_toc() {
     let res = common::render(
@@ -75,3 +77,108 @@ fn can_make_toc_all_levels() {
     assert_eq!(toc[0].children[1].children[0].children.len(), 1);
     assert_eq!(toc[0].children[1].children[0].children[0].children.len(), 1);
 }
+
+#[test]
+fn can_limit_toc_levels() {
+    let mut config = Config::default_for_test();
+    config.markdown.toc.min_level = 2;
+    config.markdown.toc.max_level = 3;
+    let res = common::render_with_config(
+        r#"
+# Title
+
+## Intro
+
+### Details
+
+#### Too deep
+
+## Conclusion
+    "#,
+        config,
+    )
+    .unwrap();
+
+    let toc = res.toc;
+    assert_eq!(toc.len(), 2);
+    assert_eq!(toc[0].id, "intro");
+    assert_eq!(toc[0].children.len(), 1);
+    assert_eq!(toc[0].children[0].id, "details");
+    assert!(toc[0].children[0].children.is_empty());
+    assert_eq!(toc[1].id, "conclusion");
+    // Headings out of the TOC still get an id
+    assert!(res.body.contains(r#"<h4 id="too-deep">"#));
+}
+
+#[test]
+fn can_number_headings() {
+    let mut config = Config::default_for_test();
+    config.markdown.toc.min_level = 2;
+    config.markdown.toc.numbering = true;
+    let res = common::render_with_config(
+        r#"
+# Title
+
+## Intro
+
+### Details
+
+### More details
+
+## Conclusion
+    "#,
+        config,
+    )
+    .unwrap();
+
+    let toc = res.toc;
+    assert_eq!(toc[0].number.as_deref(), Some("1"));
+    assert_eq!(toc[0].children[1].number.as_deref(), Some("1.2"));
+    assert_eq!(toc[1].number.as_deref(), Some("2"));
+    assert_eq!(toc[1].title, "Conclusion");
+    assert!(res.body.contains(r#"<h1 id="title">Title</h1>"#));
+    assert!(res.body.contains(
+        r#"<h3 id="more-details"><span class="heading-number">1.2</span> More details</h3>"#
+    ));
+}
+
+#[test]
+fn can_render_toc_marker() {
+    let res = common::render(
+        r#"
+Some intro
+
+<!-- toc -->
+
+## First
+
+### Nested
+
+## Second
+    "#,
+    )
+    .unwrap();
+
+    assert!(!res.body.contains("<!-- toc -->"));
+    assert!(res.body.contains(r#"<nav class="toc">"#));
+    assert!(res.body.contains(r##"<li><a href="#nested">Nested</a>"##));
+    assert!(res.body.find(r#"<nav class="toc">"#) < res.body.find(r#"<h2 id="first">"#));
+}
+
+#[test]
+fn can_render_toc_marker_inside_html() {
+    let res = common::render(
+        r#"
+<aside class="sidebar"><!-- toc --></aside>
+
+## First
+
+## Second
+    "#,
+    )
+    .unwrap();
+
+    assert!(res.body.contains(r#"<aside class="sidebar"><nav class="toc">"#));
+    assert!(res.body.contains("</nav></aside>"));
+    assert!(!res.body.contains("site toc partial"));
+}
@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, HashMap};
+
+use config::{Config, Search};
+use content::{Library, Section};
+use errors::{bail, Result};
+use libs::elasticlunr::{lang, Language, Pipeline};
+use libs::serde_json::{self, json, Map, Value};
+use libs::time::format_description::well_known::Rfc3339;
+
+use crate::elasticlunr::path_tokenizer;
+use crate::{
+    clean_and_truncate_body, extra_field_text, filter_extra, filter_taxonomies, split_by_headings,
+};
+
+/// Bumped whenever the layout of the files changes so clients can detect it
+const FORMAT_VERSION: u32 = 1;
+
+/// A document as stored in the fragment files
+struct Document<'a> {
+    url: &'a str,
+    title: Option<&'a str>,
+    description: Option<&'a str>,
+    date: Option<String>,
+    path: &'a str,
+    body: Option<String>,
+    extra: &'a Map<String, Value>,
+    taxonomies: Option<&'a HashMap<String, Vec<String>>>,
+}
+
+impl Document<'_> {
+    /// The text of the given field, as returned by `build_fields`
+    fn field_text(&self, field: &str) -> String {
+        match field.split_once('.') {
+            Some(("extra", name)) => extra_field_text(self.extra, name).unwrap_or_default(),
+            Some((_, name)) => self
+                .taxonomies
+                .and_then(|t| t.get(name))
+                .map(|terms| terms.join(" "))
+                .unwrap_or_default(),
+            None => match field {
+                "title" => self.title.unwrap_or_default().to_string(),
+                "description" => self.description.unwrap_or_default().to_string(),
+                "path" => self.path.to_string(),
+                _ => self.body.clone().unwrap_or_default(),
+            },
+        }
+    }
+}
+
+/// The name of the fields that are indexed, in the order used in the postings
+fn build_fields(search_config: &Search) -> Vec<String> {
+    let mut fields = Vec::new();
+    if search_config.include_title {
+        fields.push("title".to_string());
+    }
+    if search_config.include_description {
+        fields.push("description".to_string());
+    }
+    if search_config.include_path {
+        fields.push("path".to_string());
+    }
+    if search_config.include_content {
+        fields.push("body".to_string());
+    }
+    for name in &search_config.extra_fields {
+        fields.push(format!("extra.{}", name));
+    }
+    for name in &search_config.taxonomies {
+        fields.push(format!("taxonomies.{}", name));
+    }
+    fields
+}
+
+fn collect_section<'a>(
+    documents: &mut Vec<Document<'a>>,
+    section: &'a Section,
+    library: &'a Library,
+    search_config: &Search,
+) {
+    if !section.meta.in_search_index {
+        return;
+    }
+
+    // Don't index redirecting sections
+    if section.meta.redirect_to.is_none() {
+        documents.push(Document {
+            url: &section.permalink,
+            title: section.meta.title.as_deref(),
+            description: section.meta.description.as_deref(),
+            date: None,
+            path: &section.path,
+            body: search_config.include_content.then(|| {
+                clean_and_truncate_body(search_config.truncate_content_length, &section.content)
+            }),
+            extra: &section.meta.extra,
+            taxonomies: None,
+        });
+
+        if search_config.index_headings {
+            for heading in split_by_headings(&section.toc, &section.content) {
+                documents.push(Document {
+                    url: heading.permalink,
+                    title: Some(heading.title),
+                    description: None,
+                    date: None,
+                    path: &section.path,
+                    body: search_config.include_content.then(|| {
+                        clean_and_truncate_body(
+                            search_config.truncate_content_length,
+                            heading.content,
+                        )
+                    }),
+                    extra: &section.meta.extra,
+                    taxonomies: None,
+                });
+            }
+        }
+    }
+
+    for key in &section.pages {
+        let page = &library.pages[key];
+        if !page.meta.in_search_index {
+            continue;
+        }
+
+        let date = page.meta.datetime.and_then(|d| d.format(&Rfc3339).ok());
+        documents.push(Document {
+            url: &page.permalink,
+            title: page.meta.title.as_deref(),
+            description: page.meta.description.as_deref(),
+            date: date.clone(),
+            path: &page.path,
+            body: search_config.include_content.then(|| {
+                clean_and_truncate_body(search_config.truncate_content_length, &page.content)
+            }),
+            extra: &page.meta.extra,
+            taxonomies: Some(&page.meta.taxonomies),
+        });
+
+        if search_config.index_headings {
+            for heading in split_by_headings(&page.toc, &page.content) {
+                documents.push(Document {
+                    url: heading.permalink,
+                    title: Some(heading.title),
+                    description: None,
+                    date: date.clone(),
+                    path: &page.path,
+                    body: search_config.include_content.then(|| {
+                        clean_and_truncate_body(
+                            search_config.truncate_content_length,
+                            heading.content,
+                        )
+                    }),
+                    extra: &page.meta.extra,
+                    taxonomies: Some(&page.meta.taxonomies),
+                });
+            }
+        }
+    }
+}
+
+/// Turns a term prefix into something that can safely be used in a filename and a URL:
+/// ASCII letters and digits are kept as is, everything else becomes its hexadecimal code point
+/// between underscores.
+fn shard_name(prefix: &str) -> String {
+    let mut name = String::with_capacity(prefix.len());
+    for c in prefix.chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            name.push(c);
+        } else {
+            name.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    name
+}
+
+fn shard_prefix(term: &str, length: usize) -> &str {
+    match term.char_indices().nth(length) {
+        Some((i, _)) => &term[..i],
+        None => term,
+    }
+}
+
+fn tokenize(language: &dyn Language, pipeline: &Pipeline, field: &str, text: &str) -> Vec<String> {
+    let tokens = if field == "path" { path_tokenizer(text) } else { language.tokenize(text) };
+    pipeline.run(tokens)
+}
+
+/// Builds the chunked index for the given language.
+/// Returns the list of `(filename, content)` to write, relative to the directory containing
+/// `meta.json`:
+///
+/// - `meta.json`: the list of fields, the language pipeline, the shards and how many documents
+///   there are in each fragment
+/// - `shards/{prefix}.json`: a map of every term starting with that prefix to its postings, as
+///   `[document id, field index, term frequency]`
+/// - `fragments/{n}.json`: the documents `n * fragment_size` to `(n + 1) * fragment_size - 1`
+///
+/// Errors if the language given is not available in Elasticlunr, which is used for the stemming
+pub fn build_index(
+    lang: &str,
+    library: &Library,
+    config: &Config,
+) -> Result<Vec<(String, String)>> {
+    let language = match lang::from_code(lang) {
+        Some(l) => l,
+        None => {
+            bail!("Tried to build search index for language {} which is not supported", lang);
+        }
+    };
+    let pipeline = language.make_pipeline();
+    let search_config = &config.languages[lang].search;
+    let fields = build_fields(search_config);
+
+    let mut documents = Vec::new();
+    for (_, section) in &library.sections {
+        if section.lang == lang {
+            collect_section(&mut documents, section, library, search_config);
+        }
+    }
+
+    // prefix -> term -> postings, sorted to get reproducible files
+    let mut shards: BTreeMap<String, BTreeMap<String, Vec<[usize; 3]>>> = BTreeMap::new();
+    for (doc_id, doc) in documents.iter().enumerate() {
+        for (field_id, field) in fields.iter().enumerate() {
+            let text = doc.field_text(field);
+            let mut frequencies: BTreeMap<String, usize> = BTreeMap::new();
+            for term in tokenize(language.as_ref(), &pipeline, field, &text) {
+                *frequencies.entry(term).or_default() += 1;
+            }
+            for (term, frequency) in frequencies {
+                let prefix = shard_prefix(&term, search_config.shard_prefix_length).to_string();
+                shards
+                    .entry(prefix)
+                    .or_default()
+                    .entry(term)
+                    .or_default()
+                    .push([doc_id, field_id, frequency]);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    let mut shard_files = Map::new();
+    for (prefix, terms) in shards {
+        let filename = format!("shards/{}.json", shard_name(&prefix));
+        files.push((filename.clone(), serde_json::to_string(&terms)?));
+        shard_files.insert(prefix, Value::String(filename));
+    }
+
+    let fragments = documents.chunks(search_config.fragment_size);
+    let fragment_count = fragments.len();
+    for (i, fragment) in fragments.enumerate() {
+        let docs: Vec<Value> = fragment
+            .iter()
+            .map(|doc| {
+                let mut out = Map::new();
+                out.insert("url".to_string(), json!(doc.url));
+                if search_config.include_title {
+                    out.insert("title".to_string(), json!(doc.title.unwrap_or_default()));
+                }
+                if search_config.include_description {
+                    out.insert(
+                        "description".to_string(),
+                        json!(doc.description.unwrap_or_default()),
+                    );
+                }
+                if search_config.include_date {
+                    out.insert("date".to_string(), json!(doc.date));
+                }
+                if search_config.include_path {
+                    out.insert("path".to_string(), json!(doc.path));
+                }
+                if let Some(body) = &doc.body {
+                    out.insert("body".to_string(), json!(body));
+                }
+                let extra = filter_extra(search_config, doc.extra);
+                if !extra.is_empty() {
+                    out.insert("extra".to_string(), json!(extra));
+                }
+                if let Some(taxonomies) = doc.taxonomies {
+                    let taxonomies = filter_taxonomies(search_config, taxonomies);
+                    if !taxonomies.is_empty() {
+                        out.insert("taxonomies".to_string(), json!(taxonomies));
+                    }
+                }
+                Value::Object(out)
+            })
+            .collect();
+        files.push((format!("fragments/{}.json", i), serde_json::to_string(&docs)?));
+    }
+
+    let meta = json!({
+        "version": FORMAT_VERSION,
+        "lang": lang,
+        "pipeline": pipeline,
+        "fields": fields,
+        "boosts": search_config.boosts.iter().collect::<BTreeMap<_, _>>(),
+        "shard_prefix_length": search_config.shard_prefix_length,
+        "shards": shard_files,
+        "document_count": documents.len(),
+        "fragment_size": search_config.fragment_size,
+        "fragment_count": fragment_count,
+    });
+    files.insert(0, ("meta.json".to_string(), serde_json::to_string(&meta)?));
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_fields() {
+        let mut config = Config::default();
+        assert_eq!(build_fields(&config.search), vec!["title", "body"]);
+
+        config.search.include_description = true;
+        config.search.include_path = true;
+        config.search.include_content = false;
+        assert_eq!(build_fields(&config.search), vec!["title", "description", "path"]);
+
+        config.search.extra_fields = vec!["author".to_string()];
+        config.search.taxonomies = vec!["tags".to_string()];
+        assert_eq!(
+            build_fields(&config.search),
+            vec!["title", "description", "path", "extra.author", "taxonomies.tags"]
+        );
+    }
+
+    #[test]
+    fn can_get_shard_prefix() {
+        assert_eq!(shard_prefix("search", 2), "se");
+        assert_eq!(shard_prefix("a", 2), "a");
+        assert_eq!(shard_prefix("été", 2), "ét");
+    }
+
+    #[test]
+    fn can_make_safe_shard_names() {
+        assert_eq!(shard_name("se"), "se");
+        assert_eq!(shard_name("4k"), "4k");
+        assert_eq!(shard_name("ét"), "_e9_t");
+        assert_eq!(shard_name("c+"), "c_2b_");
+    }
+
+    #[test]
+    fn stems_terms_using_the_language() {
+        let language = lang::from_code("en").unwrap();
+        let pipeline = language.make_pipeline();
+        assert_eq!(
+            tokenize(language.as_ref(), &pipeline, "body", "The cats are running"),
+            vec!["cat", "run"]
+        );
+        assert_eq!(
+            tokenize(language.as_ref(), &pipeline, "path", "/posts/running-cats/"),
+            vec!["post", "run", "cat"]
+        );
+    }
+}
@@ -1,11 +1,15 @@
+use std::collections::{BTreeMap, HashMap};
+
 use config::{Config, Search};
 use content::{Library, Section};
 use errors::{bail, Result};
 use libs::elasticlunr::{lang, Index, IndexBuilder};
+use libs::serde_json::{self, json, Map as JsonMap};
+use libs::tera::{Map, Value};
 use libs::time::format_description::well_known::Rfc3339;
 use libs::time::OffsetDateTime;
 
-use crate::clean_and_truncate_body;
+use crate::{clean_and_truncate_body, extra_field_text, split_by_headings};
 
 pub const ELASTICLUNR_JS: &str = include_str!("elasticlunr.min.js");
 
@@ -30,16 +34,25 @@ fn build_fields(search_config: &Search, mut index: IndexBuilder) -> IndexBuilder
         index = index.add_field("body")
     }
 
+    for name in &search_config.extra_fields {
+        index = index.add_field(&format!("extra.{}", name));
+    }
+
+    for name in &search_config.taxonomies {
+        index = index.add_field(&format!("taxonomies.{}", name));
+    }
+
     index
 }
 
-fn path_tokenizer(text: &str) -> Vec<String> {
+pub(crate) fn path_tokenizer(text: &str) -> Vec<String> {
     text.split(|c: char| c.is_whitespace() || c == '-' || c == '/')
         .filter(|s| !s.is_empty())
         .map(|s| s.trim().to_lowercase())
         .collect()
 }
 
+#[allow(clippy::too_many_arguments)]
 fn fill_index(
     search_config: &Search,
     title: &Option<String>,
@@ -47,6 +60,8 @@ fn fill_index(
     datetime: &Option<OffsetDateTime>,
     path: &str,
     content: &str,
+    extra: &Map<String, Value>,
+    taxonomies: &HashMap<String, Vec<String>>,
 ) -> Vec<String> {
     let mut row = vec![];
 
@@ -73,6 +88,14 @@ fn fill_index(
     if search_config.include_content {
         row.push(clean_and_truncate_body(search_config.truncate_content_length, content));
     }
+
+    for name in &search_config.extra_fields {
+        row.push(extra_field_text(extra, name).unwrap_or_default());
+    }
+
+    for name in &search_config.taxonomies {
+        row.push(taxonomies.get(name).map(|terms| terms.join(" ")).unwrap_or_default());
+    }
     row
 }
 
@@ -98,7 +121,20 @@ pub fn build_index(lang: &str, library: &Library, config: &Config) -> Result<Str
         }
     }
 
-    Ok(index.to_json())
+    if language_options.search.boosts.is_empty() {
+        return Ok(index.to_json());
+    }
+
+    // Boosts are applied when querying so we add the search config elasticlunr.js expects
+    // next to the index
+    let mut fields = JsonMap::new();
+    let boosts: BTreeMap<_, _> = language_options.search.boosts.iter().collect();
+    for (field, boost) in boosts {
+        fields.insert(field.clone(), json!({ "boost": boost }));
+    }
+    let mut out = serde_json::to_value(&index)?;
+    out["searchConfig"] = json!({ "fields": fields });
+    Ok(serde_json::to_string(&out)?)
 }
 
 fn add_section_to_index(
@@ -113,6 +149,7 @@ fn add_section_to_index(
 
     // Don't index redirecting sections
     if section.meta.redirect_to.is_none() {
+        let no_taxonomies = HashMap::new();
         index.add_doc(
             &section.permalink,
             &fill_index(
@@ -122,8 +159,28 @@ fn add_section_to_index(
                 &None,
                 &section.path,
                 &section.content,
+                &section.meta.extra,
+                &no_taxonomies,
             ),
         );
+
+        if search_config.index_headings {
+            for heading in split_by_headings(&section.toc, &section.content) {
+                index.add_doc(
+                    heading.permalink,
+                    &fill_index(
+                        search_config,
+                        &Some(heading.title.to_string()),
+                        &None,
+                        &None,
+                        &section.path,
+                        heading.content,
+                        &section.meta.extra,
+                        &no_taxonomies,
+                    ),
+                );
+            }
+        }
     }
 
     for key in &section.pages {
@@ -141,8 +198,28 @@ fn add_section_to_index(
                 &page.meta.datetime,
                 &page.path,
                 &page.content,
+                &page.meta.extra,
+                &page.meta.taxonomies,
             ),
         );
+
+        if search_config.index_headings {
+            for heading in split_by_headings(&page.toc, &page.content) {
+                index.add_doc(
+                    heading.permalink,
+                    &fill_index(
+                        search_config,
+                        &Some(heading.title.to_string()),
+                        &None,
+                        &page.meta.datetime,
+                        &page.path,
+                        heading.content,
+                        &page.meta.extra,
+                        &page.meta.taxonomies,
+                    ),
+                );
+            }
+        }
     }
 }
 
@@ -180,7 +257,16 @@ mod tests {
         let path = "/a/page/".to_string();
         let content = "Some content".to_string();
 
-        let res = fill_index(&config.search, &title, &description, &None, &path, &content);
+        let res = fill_index(
+            &config.search,
+            &title,
+            &description,
+            &None,
+            &path,
+            &content,
+            &Map::new(),
+            &HashMap::new(),
+        );
         assert_eq!(res.len(), 2);
         assert_eq!(res[0], title.unwrap());
         assert_eq!(res[1], content);
@@ -195,7 +281,16 @@ mod tests {
         let path = "/a/page/".to_string();
         let content = "Some content".to_string();
 
-        let res = fill_index(&config.search, &title, &description, &None, &path, &content);
+        let res = fill_index(
+            &config.search,
+            &title,
+            &description,
+            &None,
+            &path,
+            &content,
+            &Map::new(),
+            &HashMap::new(),
+        );
         assert_eq!(res.len(), 3);
         assert_eq!(res[0], title.unwrap());
         assert_eq!(res[1], description.unwrap());
@@ -211,7 +306,16 @@ mod tests {
         let path = "/a/page/".to_string();
         let content = "Some content".to_string();
 
-        let res = fill_index(&config.search, &title, &description, &None, &path, &content);
+        let res = fill_index(
+            &config.search,
+            &title,
+            &description,
+            &None,
+            &path,
+            &content,
+            &Map::new(),
+            &HashMap::new(),
+        );
         assert_eq!(res.len(), 2);
         assert_eq!(res[0], title.unwrap());
         assert_eq!(res[1], content[..5]);
@@ -227,10 +331,47 @@ mod tests {
         let content = "Some content".to_string();
         let datetime = Some(OffsetDateTime::parse("2023-01-31T00:00:00Z", &Rfc3339).unwrap());
 
-        let res = fill_index(&config.search, &title, &description, &datetime, &path, &content);
+        let res = fill_index(
+            &config.search,
+            &title,
+            &description,
+            &datetime,
+            &path,
+            &content,
+            &Map::new(),
+            &HashMap::new(),
+        );
         assert_eq!(res.len(), 3);
         assert_eq!(res[0], title.unwrap());
         assert_eq!(res[1], "2023-01-31T00:00:00Z");
         assert_eq!(res[2], content);
     }
+
+    #[test]
+    fn can_fill_index_extra_fields_and_taxonomies() {
+        let mut config = Config::default();
+        config.search.extra_fields = vec!["author".to_string(), "missing".to_string()];
+        config.search.taxonomies = vec!["tags".to_string()];
+        let index = build_fields(&config.search, IndexBuilder::new()).build();
+        assert_eq!(
+            index.get_fields(),
+            vec!["title", "body", "extra.author", "extra.missing", "taxonomies.tags"]
+        );
+
+        let mut extra = Map::new();
+        extra.insert("author".to_string(), Value::String("Jane Doe".to_string()));
+        let mut taxonomies = HashMap::new();
+        taxonomies.insert("tags".to_string(), vec!["rust".to_string(), "web".to_string()]);
+        let res = fill_index(
+            &config.search,
+            &Some("A title".to_string()),
+            &None,
+            &None,
+            "/a/page/",
+            "Some content",
+            &extra,
+            &taxonomies,
+        );
+        assert_eq!(res, vec!["A title", "Some content", "Jane Doe", "", "rust web"]);
+    }
 }
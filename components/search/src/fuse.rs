@@ -1,9 +1,12 @@
+use std::collections::BTreeMap;
+
 use config::Search;
 use content::Library;
 use errors::Result;
 use libs::serde_json;
+use libs::tera::Value;
 
-use crate::clean_and_truncate_body;
+use crate::{clean_and_truncate_body, filter_extra, filter_taxonomies, split_by_headings};
 
 /// build index in Fuse.js format.
 pub fn build_index(lang: &str, library: &Library, config: &Search) -> Result<String> {
@@ -14,6 +17,10 @@ pub fn build_index(lang: &str, library: &Library, config: &Search) -> Result<Str
         description: Option<&'a str>,
         body: Option<String>, // AMMONIA.clean has to allocate anyway
         path: Option<&'a str>,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        extra: BTreeMap<&'a str, &'a Value>,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        taxonomies: BTreeMap<&'a str, &'a [String]>,
     }
     let mut items: Vec<Item> = Vec::new();
     for (_, section) in &library.sections {
@@ -42,7 +49,24 @@ pub fn build_index(lang: &str, library: &Library, config: &Search) -> Result<Str
                     true => Some(&section.path),
                     false => None,
                 },
+                extra: filter_extra(config, &section.meta.extra),
+                taxonomies: BTreeMap::new(),
             });
+            if config.index_headings {
+                for heading in split_by_headings(&section.toc, &section.content) {
+                    items.push(Item {
+                        url: heading.permalink,
+                        title: config.include_title.then_some(heading.title),
+                        description: None,
+                        body: config.include_content.then(|| {
+                            clean_and_truncate_body(config.truncate_content_length, heading.content)
+                        }),
+                        path: config.include_path.then_some(section.path.as_str()),
+                        extra: filter_extra(config, &section.meta.extra),
+                        taxonomies: BTreeMap::new(),
+                    });
+                }
+            }
             for page in &section.pages {
                 let page = &library.pages[page];
                 if page.meta.in_search_index {
@@ -67,7 +91,27 @@ pub fn build_index(lang: &str, library: &Library, config: &Search) -> Result<Str
                             true => Some(&page.path),
                             false => None,
                         },
-                    })
+                        extra: filter_extra(config, &page.meta.extra),
+                        taxonomies: filter_taxonomies(config, &page.meta.taxonomies),
+                    });
+                    if config.index_headings {
+                        for heading in split_by_headings(&page.toc, &page.content) {
+                            items.push(Item {
+                                url: heading.permalink,
+                                title: config.include_title.then_some(heading.title),
+                                description: None,
+                                body: config.include_content.then(|| {
+                                    clean_and_truncate_body(
+                                        config.truncate_content_length,
+                                        heading.content,
+                                    )
+                                }),
+                                path: config.include_path.then_some(page.path.as_str()),
+                                extra: filter_extra(config, &page.meta.extra),
+                                taxonomies: filter_taxonomies(config, &page.meta.taxonomies),
+                            });
+                        }
+                    }
                 }
             }
         }
@@ -1,10 +1,16 @@
+mod chunked;
 mod elasticlunr;
 mod fuse;
 
+use config::Search;
 use libs::ammonia;
 use libs::once_cell::sync::Lazy;
-use std::collections::{HashMap, HashSet};
+use libs::pulldown_cmark_escape::escape_html;
+use libs::tera::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use utils::table_of_contents::Heading;
 
+pub use chunked::build_index as build_chunked;
 pub use elasticlunr::{build_index as build_elasticlunr, ELASTICLUNR_JS};
 pub use fuse::build_index as build_fuse;
 
@@ -33,6 +39,101 @@ pub fn clean_and_truncate_body(truncate_content_length: Option<usize>, body: &st
     clean
 }
 
+/// The part of a page or section under one of its headings, indexed as its own document
+/// when `index_headings` is enabled
+pub(crate) struct HeadingContent<'a> {
+    pub permalink: &'a str,
+    pub title: &'a str,
+    /// The HTML between the end of this heading and the start of the next one
+    pub content: &'a str,
+}
+
+/// Splits the rendered `content` of a page or section at each of the headings of its `toc`
+pub(crate) fn split_by_headings<'a>(
+    toc: &'a [Heading],
+    content: &'a str,
+) -> Vec<HeadingContent<'a>> {
+    fn flatten<'a>(headings: &'a [Heading], out: &mut Vec<&'a Heading>) {
+        for heading in headings {
+            out.push(heading);
+            flatten(&heading.children, out);
+        }
+    }
+    let mut headings = Vec::new();
+    flatten(toc, &mut headings);
+
+    // (start of the heading tag, end of the heading tag, heading)
+    let mut positions = Vec::new();
+    let mut offset = 0;
+    for heading in headings {
+        // The id is escaped when the heading is rendered
+        let mut id = String::new();
+        escape_html(&mut id, &heading.id).unwrap();
+        let opening = format!("<h{} id=\"{}\"", heading.level, id);
+        let closing = format!("</h{}>", heading.level);
+        if let Some(start) = content[offset..].find(&opening).map(|i| i + offset) {
+            if let Some(end) = content[start..].find(&closing).map(|i| i + start + closing.len()) {
+                positions.push((start, end, heading));
+                offset = end;
+            }
+        }
+    }
+
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, (_, end, heading))| {
+            let next_start = positions.get(i + 1).map_or(content.len(), |(start, _, _)| *start);
+            HeadingContent {
+                permalink: &heading.permalink,
+                title: &heading.title,
+                content: &content[*end..next_start],
+            }
+        })
+        .collect()
+}
+
+/// Returns the value of the `extra` front matter field `name` as text to index.
+/// Arrays are joined with spaces, tables and missing fields are skipped.
+pub(crate) fn extra_field_text(extra: &Map<String, Value>, name: &str) -> Option<String> {
+    fn to_text(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            Value::Array(values) => {
+                Some(values.iter().filter_map(to_text).collect::<Vec<_>>().join(" "))
+            }
+            Value::Null | Value::Object(_) => None,
+        }
+    }
+    extra.get(name).and_then(to_text)
+}
+
+/// Only keeps the `extra` fields that should be in the index
+pub(crate) fn filter_extra<'a>(
+    config: &Search,
+    extra: &'a Map<String, Value>,
+) -> BTreeMap<&'a str, &'a Value> {
+    extra
+        .iter()
+        .filter(|(name, _)| config.extra_fields.contains(name))
+        .map(|(name, value)| (name.as_str(), value))
+        .collect()
+}
+
+/// Only keeps the taxonomies that should be in the index
+pub(crate) fn filter_taxonomies<'a>(
+    config: &Search,
+    taxonomies: &'a HashMap<String, Vec<String>>,
+) -> BTreeMap<&'a str, &'a [String]> {
+    taxonomies
+        .iter()
+        .filter(|(name, _)| config.taxonomies.contains(name))
+        .map(|(name, terms)| (name.as_str(), terms.as_slice()))
+        .collect()
+}
+
 #[cfg(test)]
 #[test]
 fn clean_and_truncate_body_test() {
@@ -46,3 +147,48 @@ fn clean_and_truncate_body_test() {
     assert_eq!(clean_and_truncate_body(Some(6), "hello \u{202E} world"), "hello ");
     assert_eq!(clean_and_truncate_body(Some(7), "hello \u{202E} world"), "hello \u{202e}");
 }
+
+#[cfg(test)]
+#[test]
+fn split_by_headings_test() {
+    let heading = |level, id: &str, children| Heading {
+        level,
+        id: id.to_string(),
+        permalink: format!("https://example.com/page/#{}", id),
+        title: id.to_uppercase(),
+        number: None,
+        children,
+    };
+    let toc = vec![
+        heading(1, "intro", vec![heading(2, "details", vec![])]),
+        heading(1, "end", vec![]),
+        heading(1, "q&a", vec![]),
+    ];
+    let content = r#"<p>Before</p><h1 id="intro">Intro</h1><p>Hello</p><h2 id="details">Details</h2><p>More</p><h1 id="end">End</h1><p>Bye</p><h1 id="q&amp;a">Q&amp;A</h1><p>Ask</p>"#;
+
+    let parts = split_by_headings(&toc, content);
+    assert_eq!(parts.len(), 4);
+    assert_eq!(parts[0].permalink, "https://example.com/page/#intro");
+    assert_eq!(parts[0].title, "INTRO");
+    assert_eq!(parts[0].content, "<p>Hello</p>");
+    assert_eq!(parts[1].content, "<p>More</p>");
+    assert_eq!(parts[2].content, "<p>Bye</p>");
+    assert_eq!(parts[3].permalink, "https://example.com/page/#q&a");
+    assert_eq!(parts[3].content, "<p>Ask</p>");
+}
+
+#[cfg(test)]
+#[test]
+fn extra_field_text_test() {
+    let mut extra = Map::new();
+    extra.insert("author".to_string(), Value::String("Jane".to_string()));
+    extra.insert(
+        "aliases".to_string(),
+        Value::Array(vec![Value::String("a".to_string()), Value::String("b".to_string())]),
+    );
+    extra.insert("meta".to_string(), Value::Object(Map::new()));
+    assert_eq!(extra_field_text(&extra, "author"), Some("Jane".to_string()));
+    assert_eq!(extra_field_text(&extra, "aliases"), Some("a b".to_string()));
+    assert_eq!(extra_field_text(&extra, "meta"), None);
+    assert_eq!(extra_field_text(&extra, "missing"), None);
+}
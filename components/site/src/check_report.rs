@@ -0,0 +1,179 @@
+//! Machine-readable reports of the problems found by `zola check`
+use libs::serde_json::{json, Value};
+
+use crate::link_checking::{LinkProblem, Severity};
+
+fn severity_str(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+fn kind_str(problem: &LinkProblem) -> String {
+    match libs::serde_json::to_value(problem.kind) {
+        Ok(Value::String(s)) => s,
+        _ => unreachable!("problem kinds serialize to strings"),
+    }
+}
+
+/// `{"problems": [...]}` with one object per problem
+pub fn to_json(problems: &[LinkProblem]) -> String {
+    libs::serde_json::to_string_pretty(&json!({ "problems": problems })).unwrap()
+}
+
+/// A SARIF 2.1.0 log, which is understood by most code scanning tools
+pub fn to_sarif(problems: &[LinkProblem], tool_version: &str) -> String {
+    let results: Vec<Value> = problems
+        .iter()
+        .map(|problem| {
+            let mut physical_location = json!({
+                "artifactLocation": { "uri": problem.file },
+            });
+            if let (Some(line), Some(column)) = (problem.line, problem.column) {
+                physical_location["region"] = json!({ "startLine": line, "startColumn": column });
+            }
+            json!({
+                "ruleId": kind_str(problem),
+                "level": severity_str(problem.severity),
+                "message": { "text": problem.message },
+                "locations": [{ "physicalLocation": physical_location }],
+            })
+        })
+        .collect();
+
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "zola",
+                    "version": tool_version,
+                    "informationUri": "https://www.getzola.org",
+                }
+            },
+            "results": results,
+        }],
+    });
+    libs::serde_json::to_string_pretty(&sarif).unwrap()
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A JUnit XML report with one test case per problem: errors are failures while warnings
+/// are reported in the test case output
+pub fn to_junit(problems: &[LinkProblem]) -> String {
+    let failures = problems.iter().filter(|p| p.severity == Severity::Error).count();
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites name=\"zola check\" tests=\"{}\" failures=\"{}\">\n",
+        problems.len(),
+        failures
+    ));
+    out.push_str(&format!(
+        "  <testsuite name=\"links\" tests=\"{}\" failures=\"{}\">\n",
+        problems.len(),
+        failures
+    ));
+    for problem in problems {
+        let location = match (problem.line, problem.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", problem.file, line, column),
+            _ => problem.file.clone(),
+        };
+        out.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\">\n",
+            escape_xml(&location),
+            escape_xml(&problem.link)
+        ));
+        match problem.severity {
+            Severity::Error => out.push_str(&format!(
+                "      <failure type=\"{}\" message=\"{}\"/>\n",
+                kind_str(problem),
+                escape_xml(&problem.message)
+            )),
+            Severity::Warning => out.push_str(&format!(
+                "      <system-out>warning: {}</system-out>\n",
+                escape_xml(&problem.message)
+            )),
+        }
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_checking::ProblemKind;
+
+    fn problems() -> Vec<LinkProblem> {
+        vec![
+            LinkProblem {
+                file: "content/a.md".to_string(),
+                line: Some(3),
+                column: Some(10),
+                link: "https://example.com/<nope>".to_string(),
+                kind: ProblemKind::BrokenLink,
+                severity: Severity::Error,
+                message: "Broken link & co".to_string(),
+            },
+            LinkProblem {
+                file: "content/b.md".to_string(),
+                line: None,
+                column: None,
+                link: "https://example.com".to_string(),
+                kind: ProblemKind::PermanentRedirect,
+                severity: Severity::Warning,
+                message: "redirects".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn can_output_json() {
+        let out: Value = libs::serde_json::from_str(&to_json(&problems())).unwrap();
+        assert_eq!(out["problems"][0]["kind"], "broken_link");
+        assert_eq!(out["problems"][0]["line"], 3);
+        assert_eq!(out["problems"][1]["severity"], "warning");
+        assert_eq!(out["problems"][1]["line"], Value::Null);
+    }
+
+    #[test]
+    fn can_output_sarif() {
+        let out: Value = libs::serde_json::from_str(&to_sarif(&problems(), "1.0.0")).unwrap();
+        let results = &out["runs"][0]["results"];
+        assert_eq!(results[0]["ruleId"], "broken_link");
+        assert_eq!(results[0]["level"], "error");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "content/a.md");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["startColumn"], 10);
+        assert!(results[1]["locations"][0]["physicalLocation"].get("region").is_none());
+    }
+
+    #[test]
+    fn can_output_junit() {
+        let out = to_junit(&problems());
+        assert!(out.contains("tests=\"2\" failures=\"1\""));
+        assert!(out.contains(
+            "<testcase classname=\"content/a.md:3:10\" name=\"https://example.com/&lt;nope&gt;\">"
+        ));
+        assert!(out.contains("<failure type=\"broken_link\" message=\"Broken link &amp; co\"/>"));
+        assert!(out.contains("<system-out>warning: redirects</system-out>"));
+    }
+}
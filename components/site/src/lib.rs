@@ -1,9 +1,11 @@
+pub mod check_report;
 pub mod feeds;
 pub mod link_checking;
 mod minify;
 pub mod sass;
 pub mod sitemap;
 pub mod tpls;
+pub mod translations;
 
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
@@ -20,8 +22,10 @@ use config::{get_config, Config, IndexFormat};
 use content::{Library, Page, Paginator, Section, Taxonomy};
 use errors::{anyhow, bail, Result};
 use libs::relative_path::RelativePathBuf;
+use link_checking::{LinkProblem, ProblemKind};
 use markdown::context::Caches;
 use std::time::Instant;
+use templates::global_fns::FluentTranslations;
 use templates::{load_tera, render_redirect_template};
 use utils::fs::{
     clean_site_output_folder, copy_directory, copy_file_if_needed, create_directory, create_file,
@@ -67,12 +71,17 @@ pub struct Site {
     pub library: Arc<RwLock<Library>>,
     /// The caches for rendered content
     pub caches: Option<Arc<Caches>>,
+    /// The Fluent translation files in `i18n/`
+    pub fluent_translations: Arc<FluentTranslations>,
     /// Whether to load draft pages
     include_drafts: bool,
     build_mode: BuildMode,
     shortcode_definitions: HashMap<String, ShortcodeDefinition>,
     /// Whether to check external links
     check_external_links: bool,
+    /// The problems found by the link checker during the last load, used by `zola check`
+    /// to output a report
+    pub link_problems: Vec<LinkProblem>,
 }
 
 impl Site {
@@ -89,7 +98,8 @@ impl Site {
         }
 
         let tera = load_tera(path, &config)?;
-        let shortcode_definitions = utils::templates::get_shortcodes(&tera);
+        let fluent_translations = Arc::new(FluentTranslations::load(path, &config)?);
+        let shortcode_definitions = get_shortcode_definitions(path, &config, &tera)?;
 
         let content_path = path.join("content");
         let sass_path = path.join("sass");
@@ -128,7 +138,9 @@ impl Site {
             build_mode: BuildMode::Disk,
             shortcode_definitions,
             check_external_links: true,
+            link_problems: Vec::new(),
             caches,
+            fluent_translations,
         };
 
         Ok(site)
@@ -177,6 +189,8 @@ impl Site {
     /// Reloads the templates and rebuild the site without re-markdown the Markdown.
     pub fn reload_templates(&mut self) -> Result<()> {
         self.tera.full_reload()?;
+        self.shortcode_definitions =
+            get_shortcode_definitions(&self.base_path, &self.config, &self.tera)?;
         // TODO: be smarter than that, no need to recompile sass for example
         self.build()
     }
@@ -195,6 +209,11 @@ impl Site {
         self.config.minify_html = true;
     }
 
+    /// Only use the cached responses of remote `load_data` requests
+    pub fn offline(&mut self) {
+        self.config.load_data.offline = true;
+    }
+
     /// Reads all .md files in the `content` directory and create pages/sections
     /// out of them
     pub fn load(&mut self) -> Result<()> {
@@ -353,14 +372,15 @@ impl Site {
         tpls::register_tera_global_fns(self);
 
         // Needs to be done after rendering markdown as we only get the anchors at that point
-        let internal_link_messages = link_checking::check_internal_links_with_anchors(self);
+        let internal_link_problems = link_checking::check_internal_links_with_anchors(self);
+        self.link_problems = internal_link_problems.clone();
 
         // log any broken internal links and error out if needed
-        if !internal_link_messages.is_empty() {
-            let messages: Vec<String> = internal_link_messages
+        if !internal_link_problems.is_empty() {
+            let messages: Vec<String> = internal_link_problems
                 .iter()
                 .enumerate()
-                .map(|(i, msg)| format!("  {}. {}", i + 1, msg))
+                .map(|(i, problem)| format!("  {}. {}", i + 1, problem.message))
                 .collect();
             let msg = format!(
                 "Found {} broken internal anchor link(s)\n{}",
@@ -375,13 +395,16 @@ impl Site {
 
         // check external links, log the results, and error out if needed
         if self.config.is_in_check_mode() && self.check_external_links {
-            let external_link_messages = link_checking::check_external_links(self);
-            if !external_link_messages.is_empty() {
-                let messages: Vec<String> = external_link_messages
-                    .iter()
-                    .enumerate()
-                    .map(|(i, msg)| format!("  {}. {}", i + 1, msg))
-                    .collect();
+            let external_link_problems = link_checking::check_external_links(self);
+            self.link_problems.extend(external_link_problems.iter().cloned());
+            // permanent redirects are only warnings and have already been logged
+            let messages: Vec<String> = external_link_problems
+                .iter()
+                .filter(|problem| problem.kind != ProblemKind::PermanentRedirect)
+                .enumerate()
+                .map(|(i, problem)| format!("  {}. {}", i + 1, problem.message))
+                .collect();
+            if !messages.is_empty() {
                 let msg = format!(
                     "Found {} broken external link(s)\n{}",
                     messages.len(),
@@ -417,21 +440,25 @@ impl Site {
                 index_section.file.parent = self.content_path.clone();
                 index_section.file.filename =
                     index_path.file_name().unwrap().to_string_lossy().to_string();
+                let url_prefix =
+                    self.config.url_prefix(lang.unwrap_or(&self.config.default_language));
+                index_section.path = if url_prefix.is_empty() {
+                    "/".to_string()
+                } else {
+                    format!("/{}/", url_prefix)
+                };
+                index_section.permalink = self.config.make_permalink(&index_section.path);
                 if let Some(ref l) = lang {
                     index_section.file.name = format!("_index.{}", l);
-                    index_section.path = format!("{}/", l);
-                    index_section.permalink = self.config.make_permalink(l);
                     let filename = format!("_index.{}.md", l);
                     index_section.file.path = self.content_path.join(&filename);
                     index_section.file.relative = filename;
                     index_section.file.canonical = self.content_path.join(format!("_index.{}", l));
                 } else {
                     index_section.file.name = "_index".to_string();
-                    index_section.permalink = self.config.make_permalink("");
                     index_section.file.path = self.content_path.join("_index.md");
                     index_section.file.relative = "_index.md".to_string();
                     index_section.file.canonical = self.content_path.join("_index");
-                    index_section.path = "/".to_string();
                 }
                 index_section.lang = index_section.file.find_language(
                     &self.config.default_language,
@@ -475,6 +502,7 @@ impl Site {
                     permalinks,
                     tera,
                     config,
+                    &self.base_path,
                     insert_anchor,
                     &self.shortcode_definitions,
                     self.caches.clone(),
@@ -492,6 +520,7 @@ impl Site {
                     permalinks,
                     tera,
                     config,
+                    &self.base_path,
                     &self.shortcode_definitions,
                     self.caches.clone(),
                 )
@@ -522,6 +551,7 @@ impl Site {
                 &self.permalinks,
                 &self.tera,
                 &self.config,
+                &self.base_path,
                 insert_anchor,
                 &self.shortcode_definitions,
                 self.caches.clone(),
@@ -556,6 +586,7 @@ impl Site {
                 &self.permalinks,
                 &self.tera,
                 &self.config,
+                &self.base_path,
                 &self.shortcode_definitions,
                 self.caches.clone(),
             )?;
@@ -578,6 +609,24 @@ impl Site {
         self.render_section(section, true)
     }
 
+    /// The files read while rendering the content, such as the ones included in code blocks
+    pub fn content_dependencies(&self) -> HashSet<PathBuf> {
+        let library = self.library.read().unwrap();
+        let pages = library.pages.values().flat_map(|p| &p.dependencies);
+        let sections = library.sections.values().flat_map(|s| &s.dependencies);
+        pages.chain(sections).cloned().collect()
+    }
+
+    /// Finds the pages and sections having read the file at `path` while rendering.
+    /// Only used in `zola serve --fast`
+    pub fn find_dependents(&self, path: &Path) -> Vec<PathBuf> {
+        let library = self.library.read().unwrap();
+        let pages = library.pages.values().filter(|p| p.dependencies.iter().any(|d| d == path));
+        let sections =
+            library.sections.values().filter(|s| s.dependencies.iter().any(|d| d == path));
+        pages.map(|p| p.file.path.clone()).chain(sections.map(|s| s.file.path.clone())).collect()
+    }
+
     /// Finds the insert_anchor for the parent section of the directory at `path`.
     /// Defaults to the global setting if no parent section found
     pub fn find_parent_section_insert_anchor(
@@ -802,7 +851,9 @@ impl Site {
             } else {
                 library.pages.values().collect()
             };
-            self.render_feeds(pages, None, &self.config.default_language, |c| c)?;
+            let url_prefix = self.config.url_prefix(&self.config.default_language);
+            let base_path = (!url_prefix.is_empty()).then(|| PathBuf::from(url_prefix));
+            self.render_feeds(pages, base_path.as_ref(), &self.config.default_language, |c| c)?;
             start = log_time(start, "Generated feed in default language");
         }
 
@@ -811,7 +862,12 @@ impl Site {
                 continue;
             }
             let pages: Vec<_> = library.pages.values().filter(|p| &p.lang == code).collect();
-            self.render_feeds(pages, Some(&PathBuf::from(code)), code, |c| c)?;
+            self.render_feeds(
+                pages,
+                Some(&PathBuf::from(self.config.url_prefix(code))),
+                code,
+                |c| c,
+            )?;
             start = log_time(start, "Generated feed in other language");
         }
         self.render_themes_css()?;
@@ -857,23 +913,31 @@ impl Site {
         let path = &self.output_path.join(self.config.search.index_format.filename(lang));
         let library = self.library.read().unwrap();
         let content = match &self.config.search.index_format {
-            IndexFormat::ElasticlunrJavascript | IndexFormat::ElasticlunrJson => {
+            IndexFormat::ElasticlunrJson => {
                 search::build_elasticlunr(lang, &library, &self.config)?
             }
-            IndexFormat::FuseJson | IndexFormat::FuseJavascript => {
+            IndexFormat::ElasticlunrJavascript => format!(
+                "window.searchIndex = {}",
+                search::build_elasticlunr(lang, &library, &self.config)?
+            ),
+            IndexFormat::FuseJson => search::build_fuse(lang, &library, &self.config.search)?,
+            IndexFormat::FuseJavascript => format!(
+                "window.searchIndex = {}",
                 search::build_fuse(lang, &library, &self.config.search)?
+            ),
+            IndexFormat::Chunked => {
+                let files = search::build_chunked(lang, &library, &self.config)?;
+                drop(library);
+                // `path` is the meta.json file, every other file is relative to its directory
+                let index_dir = path.parent().unwrap();
+                for (filename, content) in files {
+                    create_file(&index_dir.join(filename), content)?;
+                }
+                return Ok(());
             }
         };
         drop(library); // no need to hold on to this guard while writing
-        create_file(
-            path,
-            match self.config.search.index_format {
-                IndexFormat::ElasticlunrJson | IndexFormat::FuseJson => content,
-                IndexFormat::ElasticlunrJavascript | IndexFormat::FuseJavascript => {
-                    format!("window.searchIndex = {}", content)
-                }
-            },
-        )
+        create_file(path, content)
     }
 
     pub fn build_search_index(&self) -> Result<()> {
@@ -973,8 +1037,9 @@ impl Site {
         }
 
         let mut components = Vec::new();
-        if taxonomy.lang != self.config.default_language {
-            components.push(taxonomy.lang.as_ref());
+        let url_prefix = self.config.url_prefix(&taxonomy.lang);
+        if !url_prefix.is_empty() {
+            components.push(url_prefix);
         }
 
         components.push(taxonomy.slug.as_ref());
@@ -1011,10 +1076,10 @@ impl Site {
                 }
 
                 if taxonomy.kind.feed {
-                    let tax_path = if taxonomy.lang == self.config.default_language {
+                    let tax_path = if url_prefix.is_empty() {
                         PathBuf::from(format!("{}/{}", taxonomy.slug, item.slug))
                     } else {
-                        PathBuf::from(format!("{}/{}/{}", taxonomy.lang, taxonomy.slug, item.slug))
+                        PathBuf::from(format!("{}/{}/{}", url_prefix, taxonomy.slug, item.slug))
                     };
                     self.render_feeds(
                         item.pages.iter().map(|p| library.pages.get(p).unwrap()).collect(),
@@ -1123,9 +1188,10 @@ impl Site {
         let mut output_path = self.output_path.clone();
         let mut components: Vec<&str> = Vec::new();
 
-        if section.lang != self.config.default_language {
-            components.push(&section.lang);
-            output_path.push(&section.lang);
+        let url_prefix = self.config.url_prefix(&section.lang);
+        if !url_prefix.is_empty() {
+            components.push(url_prefix);
+            output_path.push(url_prefix);
         }
 
         for component in &section.file.components {
@@ -1254,11 +1320,28 @@ impl Site {
     }
 }
 
+/// Finds the shortcodes in the templates along with their schema if they have one, the ones
+/// of the site taking precedence over the ones of the theme
+fn get_shortcode_definitions(
+    path: &Path,
+    config: &Config,
+    tera: &Tera,
+) -> Result<HashMap<String, ShortcodeDefinition>> {
+    let mut definitions = utils::templates::get_shortcodes(tera);
+    templates::load_builtin_shortcode_schemas(&mut definitions);
+    let mut templates_dirs = vec![path.join("templates")];
+    if let Some(ref theme) = config.theme {
+        templates_dirs.push(path.join("themes").join(theme).join("templates"));
+    }
+    utils::templates::load_shortcode_schemas(&mut definitions, &templates_dirs)?;
+    Ok(definitions)
+}
+
 fn log_time(start: Instant, message: &str) -> Instant {
     let do_print = std::env::var("ZOLA_PERF_LOG").is_ok();
     let now = Instant::now();
     if do_print {
-        println!("{} took {}ms", message, now.duration_since(start).as_millis());
+        console::plain(&format!("{} took {}ms", message, now.duration_since(start).as_millis()));
     }
     now
 }
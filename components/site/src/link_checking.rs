@@ -1,25 +1,151 @@
-use core::time;
 use std::path::{Path, PathBuf};
 use std::{cmp, collections::HashMap, collections::HashSet, iter::FromIterator, thread};
 
-use config::LinkCheckerLevel;
+use config::{BoolWithPath, LinkCheckerLevel};
 use libs::globset::GlobSet;
 use libs::rayon::prelude::*;
+use libs::sha2::{Digest, Sha256};
+
+use serde::Serialize;
 
 use crate::Site;
+use content::Library;
 use errors::{bail, Result};
 use libs::rayon;
 use libs::url::Url;
+use markdown::context::CACHE_DIR;
 use utils::anchors::is_special_anchor;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemKind {
+    /// An internal link to an anchor that doesn't exist
+    MissingAnchor,
+    /// An external link that could not be parsed as a URL
+    InvalidUrl,
+    /// An external link that doesn't work
+    BrokenLink,
+    /// An external link that permanently redirects to another URL
+    PermanentRedirect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl From<&LinkCheckerLevel> for Severity {
+    fn from(level: &LinkCheckerLevel) -> Self {
+        match level {
+            LinkCheckerLevel::Error => Severity::Error,
+            LinkCheckerLevel::Warn => Severity::Warning,
+        }
+    }
+}
+
+/// A problem found by the link checker
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LinkProblem {
+    /// The content file containing the link, relative to the site root
+    pub file: String,
+    /// The 1-based line and column of the link in the file, if it could be found
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// The link target, as written in the content
+    pub link: String,
+    pub kind: ProblemKind,
+    pub severity: Severity,
+    /// The human-readable description of the problem
+    pub message: String,
+}
+
+/// The destination and 1-based line and column of every link in the markdown content of a
+/// file, whose first `line_offset` lines are the front matter
+fn find_link_locations(content: &str, line_offset: usize) -> Vec<(String, (usize, usize))> {
+    markdown::find_link_offsets(content)
+        .into_iter()
+        .map(|(link, offset)| {
+            let before = &content[..offset];
+            let line = line_offset + before.matches('\n').count() + 1;
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            (link, (line, before[line_start..].chars().count() + 1))
+        })
+        .collect()
+}
+
+/// Gives the location of links in the content files, parsing each file once and giving the
+/// successive occurrences of a link in a file for each call
+struct LinkLocator<'a> {
+    base_path: &'a Path,
+    library: &'a Library,
+    links: HashMap<PathBuf, Vec<(String, (usize, usize))>>,
+    seen: HashMap<(PathBuf, String), usize>,
+}
+
+impl<'a> LinkLocator<'a> {
+    fn new(base_path: &'a Path, library: &'a Library) -> Self {
+        LinkLocator { base_path, library, links: HashMap::new(), seen: HashMap::new() }
+    }
+
+    /// Tries each way the link could be written and returns the first one found
+    fn locate(&mut self, file: &Path, candidates: &[String]) -> Option<(usize, usize)> {
+        let library = self.library;
+        let links = self.links.entry(file.to_path_buf()).or_insert_with(|| {
+            if let Some(page) = library.pages.get(file) {
+                find_link_locations(&page.raw_content, page.content_line_offset)
+            } else if let Some(section) = library.sections.get(file) {
+                find_link_locations(&section.raw_content, section.content_line_offset)
+            } else {
+                Vec::new()
+            }
+        });
+        for candidate in candidates {
+            let seen = self.seen.entry((file.to_path_buf(), candidate.clone())).or_default();
+            let found = links.iter().filter(|(link, _)| link == candidate).nth(*seen);
+            if let Some((_, location)) = found {
+                *seen += 1;
+                return Some(*location);
+            }
+        }
+        None
+    }
+
+    fn problem(
+        &mut self,
+        file: &Path,
+        link: &str,
+        candidates: &[String],
+        kind: ProblemKind,
+        severity: Severity,
+        message: String,
+    ) -> LinkProblem {
+        let location = self.locate(file, candidates);
+        LinkProblem {
+            file: file
+                .strip_prefix(self.base_path)
+                .unwrap_or(file)
+                .to_string_lossy()
+                .replace('\\', "/"),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+            link: link.to_string(),
+            kind,
+            severity,
+            message,
+        }
+    }
+}
+
 /// Check whether all internal links pointing to explicit anchor fragments are valid.
 ///
 /// This is very similar to `check_external_links`, although internal links checking
 /// is always performed (while external ones only conditionally in `zola check`).  If broken links
 /// are encountered, the `internal_level` setting in config.toml will determine whether they are
 /// treated as warnings or errors.
-pub fn check_internal_links_with_anchors(site: &Site) -> Vec<String> {
-    println!("Checking all internal links with anchors.");
+pub fn check_internal_links_with_anchors(site: &Site) -> Vec<LinkProblem> {
+    console::plain("Checking all internal links with anchors.");
     let library = site.library.write().expect("Get lock for check_internal_links_with_anchors");
 
     // Chain all internal links, from both sections and pages.
@@ -65,7 +191,7 @@ pub fn check_internal_links_with_anchors(site: &Site) -> Vec<String> {
                     page.strip_prefix(&site.base_path).unwrap()
                 )
             });
-            !section.has_anchor(anchor)
+            !(section.has_anchor(anchor) || section.has_anchor_id(anchor))
         } else {
             let page = library.pages.get(&full_path).unwrap_or_else(|| {
                 panic!(
@@ -80,26 +206,41 @@ pub fn check_internal_links_with_anchors(site: &Site) -> Vec<String> {
     });
 
     // Format faulty entries into error messages, and collect them.
+    let severity = Severity::from(&site.config.link_checker.internal_level);
+    let mut locator = LinkLocator::new(&site.base_path, &library);
     let messages = missing_targets
         .map(|(page_path, md_path, anchor)| {
-            format!(
-                "The anchor in the link `@/{}#{}` in {} does not exist.",
-                md_path,
-                anchor,
+            let link = format!("@/{}#{}", md_path, anchor);
+            let message = format!(
+                "The anchor in the link `{}` in {} does not exist.",
+                link,
                 page_path.to_string_lossy(),
+            );
+            // Anchors of the current page can be written without the path
+            let candidates = [link.clone(), format!("#{}", anchor)];
+            locator.problem(
+                &page_path,
+                &link,
+                &candidates,
+                ProblemKind::MissingAnchor,
+                severity,
+                message,
             )
         })
         .collect::<Vec<_>>();
 
     // Finally emit a summary, and return overall anchors-checking result.
     if messages.is_empty() {
-        println!("> Successfully checked {} internal link(s) with anchors.", anchors_total);
+        console::plain(&format!(
+            "> Successfully checked {} internal link(s) with anchors.",
+            anchors_total
+        ));
     } else {
-        println!(
+        console::plain(&format!(
             "> Checked {} internal link(s) with anchors: {} target(s) missing.",
             anchors_total,
             messages.len(),
-        );
+        ));
     }
     messages
 }
@@ -124,7 +265,7 @@ fn get_link_domain(link: &str) -> Result<String> {
 
 /// Checks all external links and returns all the errors that were encountered.
 /// Empty vec == all good
-pub fn check_external_links(site: &Site) -> Vec<String> {
+pub fn check_external_links(site: &Site) -> Vec<LinkProblem> {
     let library = site.library.write().expect("Get lock for check_external_links");
 
     struct LinkDef {
@@ -143,7 +284,9 @@ pub fn check_external_links(site: &Site) -> Vec<String> {
         }
     }
 
-    let mut messages: Vec<String> = vec![];
+    let severity = Severity::from(&site.config.link_checker.external_level);
+    let mut locator = LinkLocator::new(&site.base_path, &library);
+    let mut messages: Vec<LinkProblem> = vec![];
     let mut external_links = Vec::new();
     for p in library.pages.values() {
         external_links.push((&p.file.path, &p.external_links));
@@ -173,7 +316,14 @@ pub fn check_external_links(site: &Site) -> Vec<String> {
                         // We could use the messages.len() to keep track of them for below
                         // but it's more explicit this way
                         invalid_url_links += 1;
-                        messages.push(err.to_string());
+                        messages.push(locator.problem(
+                            file_path,
+                            link,
+                            &[link.to_string()],
+                            ProblemKind::InvalidUrl,
+                            severity,
+                            err.to_string(),
+                        ));
                     }
                 }
             }
@@ -186,7 +336,7 @@ pub fn check_external_links(site: &Site) -> Vec<String> {
     )
     .len();
 
-    println!(
+    console::plain(&format!(
         "Checking {} external link(s). Skipping {} external link(s).{}",
         unique_links_count,
         skipped_link_count,
@@ -195,7 +345,7 @@ pub fn check_external_links(site: &Site) -> Vec<String> {
         } else {
             format!(" {} link(s) had unparseable URLs.", invalid_url_links)
         }
-    );
+    ));
 
     if checked_links.is_empty() {
         return Vec::new();
@@ -206,13 +356,37 @@ pub fn check_external_links(site: &Site) -> Vec<String> {
         return messages;
     }
 
-    let mut links_by_domain: HashMap<&str, Vec<&LinkDef>> = HashMap::new();
+    // Every unique link is only checked once (e.g. for translated pages), and the links of a
+    // domain are split between `per_domain_concurrency` workers
+    let concurrency = site.config.link_checker.per_domain_concurrency;
+    let mut links_by_domain: HashMap<&str, Vec<Vec<&str>>> = HashMap::new();
+    let mut seen_links = HashSet::new();
     for link in checked_links.iter() {
-        if links_by_domain.contains_key(link.domain.as_str()) {
-            links_by_domain.get_mut(link.domain.as_str()).unwrap().push(link);
-        } else {
-            links_by_domain.insert(link.domain.as_str(), vec![link]);
+        if !seen_links.insert(link.external_link.as_str()) {
+            continue;
         }
+        let workers = links_by_domain
+            .entry(link.domain.as_str())
+            .or_insert_with(|| vec![Vec::new(); concurrency]);
+        let worker = workers.iter_mut().min_by_key(|w| w.len()).unwrap();
+        worker.push(link.external_link.as_str());
+    }
+    let workers: Vec<Vec<&str>> =
+        links_by_domain.into_values().flatten().filter(|w| !w.is_empty()).collect();
+
+    let cache_path = match site.config.link_checker.cache {
+        BoolWithPath::True(Some(ref p)) => Some(site.base_path.join(p).join("results.json")),
+        // The user cache directory is shared by all the sites so each one gets its own file
+        BoolWithPath::True(None) => {
+            let base_path =
+                site.base_path.canonicalize().unwrap_or_else(|_| site.base_path.clone());
+            let hash = format!("{:x}", Sha256::digest(base_path.to_string_lossy().as_bytes()));
+            Some(CACHE_DIR.join("link_checker").join(format!("{}.json", &hash[..16])))
+        }
+        BoolWithPath::False => None,
+    };
+    if let Some(ref path) = cache_path {
+        link_checker::load_cache(path);
     }
 
     let cpu_count = match thread::available_parallelism() {
@@ -220,77 +394,114 @@ pub fn check_external_links(site: &Site) -> Vec<String> {
         Err(_) => 1,
     };
     // create thread pool with lots of threads so we can fetch
-    // (almost) all pages simultaneously, limiting all links for a single
-    // domain to one thread to avoid rate-limiting
-    let num_threads = cmp::min(links_by_domain.len(), cmp::max(8, cpu_count));
+    // (almost) all pages simultaneously. The link checker spaces out the requests to
+    // a single domain to avoid rate-limiting
+    let num_threads = cmp::min(workers.len(), cmp::max(8, cpu_count));
     match rayon::ThreadPoolBuilder::new().num_threads(num_threads).build() {
         Ok(pool) => {
-            let errors = pool.install(|| {
-                links_by_domain
+            let results: HashMap<&str, link_checker::LinkCheck> = pool.install(|| {
+                workers
                     .par_iter()
-                    .map(|(_, links)| {
-                        let mut num_links_left = links.len();
-                        let mut checked_links: HashMap<&str, Option<link_checker::Result>> =
-                            HashMap::new();
-                        links
-                            .iter()
-                            .filter_map(move |link_def| {
-                                num_links_left -= 1;
-
-                                // Avoid double-checking the same url (e.g. for translated pages).
-                                let external_link = link_def.external_link.as_str();
-                                if let Some(optional_res) = checked_links.get(external_link) {
-                                    if let Some(res) = optional_res {
-                                        return Some((
-                                            &link_def.file_path,
-                                            external_link,
-                                            res.clone(),
-                                        ));
-                                    }
-                                    return None;
-                                }
-
-                                let res = link_checker::check_url(
-                                    external_link,
-                                    &site.config.link_checker,
-                                );
-
-                                if num_links_left > 0 {
-                                    // Prevent rate-limiting, wait before next crawl unless we're done with this domain
-                                    thread::sleep(time::Duration::from_millis(500));
-                                }
-
-                                if link_checker::is_valid(&res) {
-                                    checked_links.insert(external_link, None);
-                                    None
-                                } else {
-                                    checked_links.insert(external_link, Some(res.clone()));
-                                    Some((&link_def.file_path, external_link, res))
-                                }
-                            })
-                            .collect::<Vec<_>>()
+                    .flat_map_iter(|links| {
+                        links.iter().map(|link| {
+                            (*link, link_checker::check_link(link, &site.config.link_checker))
+                        })
                     })
-                    .flatten()
-                    .collect::<Vec<_>>()
+                    .collect()
             });
+            let errors: Vec<_> = checked_links
+                .iter()
+                .filter_map(|link_def| {
+                    let check = &results[link_def.external_link.as_str()];
+                    if link_checker::is_valid(&check.result) {
+                        None
+                    } else {
+                        Some((&link_def.file_path, &link_def.external_link, check.result.clone()))
+                    }
+                })
+                .collect();
+
+            let mut redirects = Vec::new();
+            if site.config.link_checker.report_permanent_redirects {
+                for link_def in &checked_links {
+                    let check = &results[link_def.external_link.as_str()];
+                    if let (Ok(_), Some(target)) = (&check.result, &check.permanent_redirect) {
+                        let message = format!(
+                            "Link in {} to {} permanently redirects to {}",
+                            link_def.file_path.to_string_lossy(),
+                            link_def.external_link,
+                            target
+                        );
+                        console::warn(&message);
+                        redirects.push((link_def, message));
+                    }
+                }
+            }
 
-            println!(
+            if let Some(ref path) = cache_path {
+                if let Err(e) = link_checker::save_cache(path, &site.config.link_checker) {
+                    console::warn(&format!("Failed to save the link checker cache: {}", e));
+                }
+            }
+
+            console::plain(&format!(
                 "> Checked {} external link(s): {} error(s) found.",
                 unique_links_count,
                 errors.len()
-            );
+            ));
 
             for (page_path, link, check_res) in errors {
-                messages.push(format!(
+                let message = format!(
                     "Broken link in {} to {} : {}",
                     page_path.to_string_lossy(),
                     link,
                     link_checker::message(&check_res)
+                );
+                messages.push(locator.problem(
+                    page_path,
+                    link,
+                    &[link.to_string()],
+                    ProblemKind::BrokenLink,
+                    severity,
+                    message,
+                ));
+            }
+            for (link_def, message) in redirects {
+                let link = &link_def.external_link;
+                messages.push(locator.problem(
+                    &link_def.file_path,
+                    link,
+                    &[link.to_string()],
+                    ProblemKind::PermanentRedirect,
+                    Severity::Warning,
+                    message,
                 ));
             }
         }
-        Err(pool_err) => messages.push(pool_err.to_string()),
+        Err(pool_err) => messages.push(LinkProblem {
+            file: String::new(),
+            line: None,
+            column: None,
+            link: String::new(),
+            kind: ProblemKind::BrokenLink,
+            severity,
+            message: pool_err.to_string(),
+        }),
     }
 
     messages
 }
+
+#[cfg(test)]
+mod tests {
+    use super::find_link_locations;
+
+    #[test]
+    fn can_find_link_locations() {
+        let content = "\n[a](https://a.com) and [é](https://a.com#x)\n\n`[b](https://a.com)` #x\n";
+        assert_eq!(
+            find_link_locations(content, 3),
+            vec![("https://a.com".to_string(), (5, 1)), ("https://a.com#x".to_string(), (5, 24)),]
+        );
+    }
+}
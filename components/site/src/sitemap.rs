@@ -5,7 +5,7 @@ use std::hash::{Hash, Hasher};
 use serde::Serialize;
 
 use config::Config;
-use content::{Library, Taxonomy};
+use content::{pager_alternates, Alternate, Library, Taxonomy};
 use libs::tera::{Map, Value};
 use std::cmp::Ordering;
 
@@ -16,6 +16,8 @@ pub struct SitemapEntry<'a> {
     pub permalink: Cow<'a, str>,
     pub updated: &'a Option<String>,
     pub extra: Option<&'a Map<String, Value>>,
+    /// The same URL in the other languages, for the `hreflang` links
+    pub alternates: Cow<'a, [Alternate]>,
 }
 
 // Hash/Eq is not implemented for tera::Map but in our case we only care about the permalink
@@ -34,12 +36,16 @@ impl<'a> Eq for SitemapEntry<'a> {}
 
 impl<'a> SitemapEntry<'a> {
     pub fn new(permalink: Cow<'a, str>, updated: &'a Option<String>) -> Self {
-        SitemapEntry { permalink, updated, extra: None }
+        SitemapEntry { permalink, updated, extra: None, alternates: Cow::Borrowed(&[]) }
     }
 
     pub fn add_extra(&mut self, extra: &'a Map<String, Value>) {
         self.extra = Some(extra);
     }
+
+    pub fn add_alternates(&mut self, alternates: Cow<'a, [Alternate]>) {
+        self.alternates = alternates;
+    }
 }
 
 impl<'a> PartialOrd for SitemapEntry<'a> {
@@ -72,6 +78,7 @@ pub fn find_entries<'a>(
             if p.meta.updated.is_some() { &p.meta.updated } else { &p.meta.date },
         );
         entry.add_extra(&p.meta.extra);
+        entry.add_alternates(Cow::Borrowed(&p.alternates));
         entries.insert(entry);
     }
 
@@ -79,6 +86,7 @@ pub fn find_entries<'a>(
         if s.meta.render {
             let mut entry = SitemapEntry::new(Cow::Borrowed(&s.permalink), &None);
             entry.add_extra(&s.meta.extra);
+            entry.add_alternates(Cow::Borrowed(&s.alternates));
             entries.insert(entry);
         }
 
@@ -87,7 +95,15 @@ pub fn find_entries<'a>(
                 let number_pagers = (s.pages.len() as f64 / paginate_by as f64).ceil() as isize;
                 for i in 1..=number_pagers {
                     let permalink = format!("{}{}/{}/", s.permalink, s.meta.paginate_path, i);
-                    entries.insert(SitemapEntry::new(Cow::Owned(permalink), &None));
+                    let mut entry = SitemapEntry::new(Cow::Owned(permalink), &None);
+                    // The first pager only redirects to the section
+                    if i > 1 {
+                        entry.add_alternates(Cow::Owned(pager_alternates(
+                            &s.alternates,
+                            i as usize,
+                        )));
+                    }
+                    entries.insert(entry);
                 }
             }
         }
@@ -97,10 +113,14 @@ pub fn find_entries<'a>(
         if !taxonomy.kind.render {
             continue;
         }
-        entries.insert(SitemapEntry::new(Cow::Borrowed(&taxonomy.permalink), &None));
+        let mut entry = SitemapEntry::new(Cow::Borrowed(&taxonomy.permalink), &None);
+        entry.add_alternates(Cow::Borrowed(&taxonomy.alternates));
+        entries.insert(entry);
 
         for item in &taxonomy.items {
-            entries.insert(SitemapEntry::new(Cow::Borrowed(&item.permalink), &None));
+            let mut entry = SitemapEntry::new(Cow::Borrowed(&item.permalink), &None);
+            entry.add_alternates(Cow::Borrowed(&item.alternates));
+            entries.insert(entry);
 
             if taxonomy.kind.is_paginated() && !config.should_exclude_paginated_pages_in_sitemap() {
                 let number_pagers = (item.pages.len() as f64
@@ -113,7 +133,14 @@ pub fn find_entries<'a>(
                         taxonomy.kind.paginate_path(),
                         i
                     ));
-                    entries.insert(SitemapEntry::new(Cow::Owned(permalink), &None));
+                    let mut entry = SitemapEntry::new(Cow::Owned(permalink), &None);
+                    if i > 1 {
+                        entry.add_alternates(Cow::Owned(pager_alternates(
+                            &item.alternates,
+                            i as usize,
+                        )));
+                    }
+                    entries.insert(entry);
                 }
             }
         }
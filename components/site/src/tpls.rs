@@ -1,5 +1,7 @@
 use crate::Site;
+use config::BoolWithPath;
 use libs::tera::Result as TeraResult;
+use markdown::context::CACHE_DIR;
 use std::sync::Arc;
 use templates::{filters, global_fns};
 
@@ -9,6 +11,14 @@ pub fn register_early_global_fns(site: &mut Site) -> TeraResult<()> {
         "num_format",
         filters::NumFormatFilter::new(&site.config.default_language),
     );
+    site.tera.register_filter(
+        "format_date",
+        filters::FormatDateFilter::new(&site.config.default_language),
+    );
+    site.tera.register_filter(
+        "relative_time",
+        filters::RelativeTimeFilter::new(&site.config.default_language),
+    );
 
     site.tera.register_function(
         "get_url",
@@ -36,22 +46,27 @@ pub fn register_early_global_fns(site: &mut Site) -> TeraResult<()> {
             site.output_path.clone(),
         ),
     );
+    let mut load_data = global_fns::LoadData::new(
+        site.base_path.clone(),
+        site.config.theme.clone(),
+        site.output_path.clone(),
+    )
+    .offline(site.config.load_data.offline);
+    if let BoolWithPath::True(ref maybe_path) = site.config.load_data.cache {
+        let cache_dir = match maybe_path {
+            Some(p) => site.base_path.join(p),
+            None => CACHE_DIR.join("load_data"),
+        };
+        load_data = load_data.with_disk_cache(cache_dir, site.config.load_data.cache_ttl);
+    }
+    site.tera.register_function("load_data", load_data);
     site.tera.register_function(
-        "load_data",
-        global_fns::LoadData::new(
-            site.base_path.clone(),
-            site.config.theme.clone(),
-            site.output_path.clone(),
-        ),
+        "trans",
+        global_fns::Trans::new(site.config.clone()).with_fluent(site.fluent_translations.clone()),
     );
-    site.tera.register_function("trans", global_fns::Trans::new(site.config.clone()));
     site.tera.register_function(
         "get_taxonomy_url",
-        global_fns::GetTaxonomyUrl::new(
-            &site.config.default_language,
-            &site.taxonomies,
-            site.config.slugify.taxonomies,
-        ),
+        global_fns::GetTaxonomyUrl::new(&site.config, &site.taxonomies),
     );
     site.tera.register_function(
         "get_hash",
@@ -67,7 +82,8 @@ pub fn register_early_global_fns(site: &mut Site) -> TeraResult<()> {
             site.config.clone(),
             site.permalinks.clone(),
             site.tera.clone(),
-        ),
+        )
+        .with_site(site.base_path.clone(), site.caches.clone()),
     );
 
     Ok(())
@@ -111,4 +127,12 @@ pub fn register_tera_global_fns(site: &mut Site) {
             site.library.clone(),
         ),
     );
+    site.tera.register_function(
+        "get_page_images",
+        global_fns::GetPageImages::new(
+            site.base_path.clone(),
+            site.library.clone(),
+            site.imageproc.clone(),
+        ),
+    );
 }
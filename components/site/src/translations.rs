@@ -0,0 +1,167 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::time::SystemTime;
+
+use libs::time::OffsetDateTime;
+use serde::Serialize;
+use utils::fs::get_file_time;
+
+use crate::Site;
+
+/// A translation older than the content of the default language it translates
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct OutdatedTranslation {
+    /// The translation, relative to the `content` directory
+    pub path: String,
+    /// The content in the default language, relative to the `content` directory
+    pub source: String,
+}
+
+/// What is missing from a language compared to the default language
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LanguageReport {
+    /// Pages and sections of the default language without a translation, relative to the
+    /// `content` directory
+    pub missing_content: Vec<String>,
+    pub outdated_content: Vec<OutdatedTranslation>,
+    /// Keys of `[translations]` and of the Fluent files of the default language that are not
+    /// translated
+    pub missing_keys: Vec<String>,
+}
+
+impl LanguageReport {
+    pub fn is_complete(&self) -> bool {
+        self.missing_content.is_empty()
+            && self.outdated_content.is_empty()
+            && self.missing_keys.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct TranslationReport {
+    pub default_language: String,
+    /// The report of every language other than the default one
+    pub languages: BTreeMap<String, LanguageReport>,
+}
+
+/// When some content was last changed: the `updated` date of its front matter and the
+/// modification time of its file
+#[derive(Debug, Clone, Copy, Default)]
+struct LastChange {
+    updated: Option<OffsetDateTime>,
+    modified: Option<SystemTime>,
+}
+
+/// The `updated` dates are compared if both pieces of content have one, the file modification
+/// times otherwise
+fn is_outdated(source: LastChange, translation: LastChange) -> bool {
+    match (source.updated, translation.updated) {
+        (Some(s), Some(t)) => s > t,
+        _ => match (source.modified, translation.modified) {
+            (Some(s), Some(t)) => s > t,
+            _ => false,
+        },
+    }
+}
+
+/// Compares the content and the translation keys of every language to the default one
+pub fn build_translation_report(site: &Site) -> TranslationReport {
+    let config = &site.config;
+    let library = site.library.read().expect("Get lock for build_translation_report");
+
+    // All the versions of the same content share the same canonical path
+    let mut contents: HashMap<&Path, Vec<(&str, &str, LastChange)>> = HashMap::new();
+    for section in library.sections.values() {
+        let last_change = LastChange { updated: None, modified: get_file_time(&section.file.path) };
+        contents.entry(section.file.canonical.as_path()).or_default().push((
+            &section.lang,
+            &section.file.relative,
+            last_change,
+        ));
+    }
+    for page in library.pages.values() {
+        let last_change = LastChange {
+            updated: page.meta.updated_datetime,
+            modified: get_file_time(&page.file.path),
+        };
+        contents.entry(page.translation_key()).or_default().push((
+            &page.lang,
+            &page.file.relative,
+            last_change,
+        ));
+    }
+
+    let mut languages: BTreeMap<String, LanguageReport> = config
+        .other_languages_codes()
+        .into_iter()
+        .map(|lang| (lang.to_string(), LanguageReport::default()))
+        .collect();
+
+    for versions in contents.values() {
+        let source = match versions.iter().find(|(lang, ..)| *lang == config.default_language) {
+            Some(s) => s,
+            // Content only existing in other languages is not a translation
+            None => continue,
+        };
+        for (lang, report) in languages.iter_mut() {
+            match versions.iter().find(|(l, ..)| *l == lang.as_str()) {
+                Some((_, relative, last_change)) => {
+                    if is_outdated(source.2, *last_change) {
+                        report.outdated_content.push(OutdatedTranslation {
+                            path: relative.to_string(),
+                            source: source.1.to_string(),
+                        });
+                    }
+                }
+                None => report.missing_content.push(source.1.to_string()),
+            }
+        }
+    }
+
+    let keys = |lang: &str| {
+        let mut keys: BTreeSet<String> =
+            config.languages[lang].translations.keys().cloned().collect();
+        if let Some(ids) = site.fluent_translations.message_ids(lang) {
+            keys.extend(ids.iter().cloned());
+        }
+        keys
+    };
+    let default_keys = keys(&config.default_language);
+    for (lang, report) in languages.iter_mut() {
+        report.missing_content.sort();
+        report.outdated_content.sort_by(|a, b| a.path.cmp(&b.path));
+        report.missing_keys = default_keys.difference(&keys(lang)).cloned().collect();
+    }
+
+    TranslationReport { default_language: config.default_language.clone(), languages }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn can_detect_outdated_translations() {
+        let before = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let after = SystemTime::UNIX_EPOCH + Duration::from_secs(2000);
+        let file = |modified| LastChange { updated: None, modified: Some(modified) };
+        assert!(is_outdated(file(after), file(before)));
+        assert!(!is_outdated(file(before), file(after)));
+        assert!(!is_outdated(file(before), file(before)));
+        assert!(!is_outdated(LastChange::default(), file(before)));
+
+        // The front matter dates take precedence when both have one
+        let updated =
+            |updated, modified| LastChange { updated: Some(updated), modified: Some(modified) };
+        assert!(is_outdated(
+            updated(OffsetDateTime::from_unix_timestamp(1709337600).unwrap(), before),
+            updated(OffsetDateTime::from_unix_timestamp(1709251200).unwrap(), after)
+        ));
+        assert!(!is_outdated(
+            updated(OffsetDateTime::from_unix_timestamp(1709251200).unwrap(), after),
+            updated(OffsetDateTime::from_unix_timestamp(1709337600).unwrap(), before)
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use std::env;
 use std::path::{Path, PathBuf};
 
 use common::{build_site, build_site_with_setup};
-use config::TaxonomyConfig;
+use config::{IndexFormat, TaxonomyConfig};
 use content::Page;
 use libs::ahash::AHashMap;
 use site::sitemap;
@@ -738,6 +738,62 @@ fn can_build_search_index() {
     assert!(file_exists!(public, "search_index.en.js"));
 }
 
+#[test]
+fn can_build_search_index_with_headings_and_boosts() {
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.config.build_search_index = true;
+        site.config.search.index_headings = true;
+        site.config.search.boosts.insert("title".to_string(), 2);
+        site.config.languages.get_mut("en").unwrap().search = site.config.search.clone();
+        (site, true)
+    });
+
+    assert!(file_contains!(
+        public,
+        "search_index.en.js",
+        r#""searchConfig":{"fields":{"title":{"boost":2}}}"#
+    ));
+    assert!(file_contains!(public, "search_index.en.js", "something-else/#title"));
+}
+
+#[test]
+fn can_build_search_index_with_headings_fuse() {
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.config.build_search_index = true;
+        site.config.search.index_format = IndexFormat::FuseJson;
+        site.config.search.index_headings = true;
+        (site, true)
+    });
+
+    assert!(file_contains!(
+        public,
+        "search_index.en.json",
+        r##"something-else/#title","title":"Title""##
+    ));
+}
+
+#[test]
+fn can_build_chunked_search_index() {
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.config.build_search_index = true;
+        site.config.search.index_format = IndexFormat::Chunked;
+        (site, true)
+    });
+
+    assert!(Path::new(&public).exists());
+    assert!(!file_exists!(public, "elasticlunr.min.js"));
+    assert!(!file_exists!(public, "search_index.en.json"));
+    assert!(file_contains!(
+        public,
+        "search_index/en/meta.json",
+        r#""pipeline":["trimmer","stopWordFilter","stemmer"]"#
+    ));
+    assert!(file_contains!(public, "search_index/en/meta.json", r#""se":"shards/se.json""#));
+    assert!(file_exists!(public, "search_index/en/fragments/0.json"));
+    // Terms are stemmed
+    assert!(file_contains!(public, "search_index/en/shards/po.json", r#""post":"#));
+}
+
 #[test]
 fn can_build_with_extra_syntaxes() {
     let (_, _tmp_dir, public) = build_site("test_site");
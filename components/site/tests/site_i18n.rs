@@ -3,7 +3,11 @@ mod common;
 use std::env;
 
 use common::*;
+use config::Slugify;
+use libs::tera::Context;
+use site::translations::build_translation_report;
 use site::Site;
+use utils::slugs::SlugifyStrategy;
 
 #[test]
 fn can_parse_multilingual_site() {
@@ -104,12 +108,33 @@ fn can_build_multilingual_site() {
         "fr/blog/something/index.html",
         "Translated in en: Something https://example.com/blog/something/"
     ));
+    assert!(file_contains!(
+        public,
+        "blog/something/index.html",
+        r#"<link rel="alternate" hreflang="fr" href="https://example.com/fr/blog/something/">"#
+    ));
+    assert!(file_contains!(
+        public,
+        "fr/blog/something/index.html",
+        r#"<link rel="alternate" hreflang="x-default" href="https://example.com/blog/something/">"#
+    ));
 
     // sitemap contains all languages
     assert!(file_exists!(public, "sitemap.xml"));
     assert!(file_contains!(public, "sitemap.xml", "https://example.com/blog/something-else/"));
     assert!(file_contains!(public, "sitemap.xml", "https://example.com/fr/blog/something-else/"));
     assert!(file_contains!(public, "sitemap.xml", "https://example.com/it/blog/something-else/"));
+    // with the links to the other languages
+    assert!(file_contains!(
+        public,
+        "sitemap.xml",
+        r#"<xhtml:link rel="alternate" hreflang="it" href="https://example.com/it/blog/something-else/"/>"#
+    ));
+    assert!(file_contains!(
+        public,
+        "sitemap.xml",
+        r#"<xhtml:link rel="alternate" hreflang="fr" href="https://example.com/fr/tags/"/>"#
+    ));
 
     // one feed per language
     assert!(file_exists!(public, "atom.xml"));
@@ -183,6 +208,46 @@ fn can_build_multilingual_site() {
     assert!(!file_exists!(public, "search_index.fr.js"));
 }
 
+#[test]
+fn can_use_the_url_prefix_and_slugify_of_a_language() {
+    let (mut site, _tmp_dir, public) = build_site_with_setup("test_site_i18n", |mut site| {
+        let slugify = Slugify { taxonomies: SlugifyStrategy::Safe, ..site.config.slugify.clone() };
+        let fr = site.config.languages.get_mut("fr").unwrap();
+        fr.url_prefix = Some("francais".to_string());
+        fr.slugify = Some(slugify);
+        (site, true)
+    });
+
+    assert!(file_exists!(public, "francais/index.html"));
+    assert!(!file_exists!(public, "fr/index.html"));
+    assert!(file_exists!(public, "francais/auteurs/Vincent Prouillet/index.html"));
+    // The other languages keep the slugify strategy of the site
+    assert!(file_exists!(public, "authors/queen-elizabeth/index.html"));
+
+    // `get_taxonomy_url` slugifies the name the same way as the language of the taxonomy
+    let url = site
+        .tera
+        .render_str(
+            r#"{{ get_taxonomy_url(kind="auteurs", name="Vincent Prouillet", lang="fr") }}"#,
+            &Context::new(),
+        )
+        .unwrap();
+    assert_eq!(url, "https://example.com/francais/auteurs/Vincent Prouillet/");
+    let url = site
+        .tera
+        .render_str(
+            r#"{{ get_taxonomy_url(kind="authors", name="Queen Elizabeth") }}"#,
+            &Context::new(),
+        )
+        .unwrap();
+    assert_eq!(url, "https://example.com/authors/queen-elizabeth/");
+    let res = site.tera.render_str(
+        r#"{{ get_taxonomy_url(kind="auteurs", name="vincent-prouillet", lang="fr") }}"#,
+        &Context::new(),
+    );
+    assert!(res.is_err());
+}
+
 #[test]
 fn correct_translations_on_all_pages() {
     let (site, _tmp_dir, public) = build_site("test_site_i18n");
@@ -207,3 +272,28 @@ fn correct_translations_on_all_pages() {
         assert!(ensure_translations_in_output(&site, path, &link));
     }
 }
+
+#[test]
+fn can_report_missing_translations() {
+    let (site, _tmp_dir, _public) = build_site("test_site_i18n");
+    let report = build_translation_report(&site);
+    assert_eq!(report.default_language, "en");
+    assert_eq!(report.languages.keys().collect::<Vec<_>>(), vec!["fr", "it"]);
+
+    let fr = &report.languages["fr"];
+    assert_eq!(fr.missing_content, vec!["blog/not-translated.md"]);
+    assert_eq!(fr.missing_keys, vec!["read-more"]);
+
+    let it = &report.languages["it"];
+    assert_eq!(
+        it.missing_content,
+        vec![
+            "_index.md",
+            "base.md",
+            "blog/not-translated.md",
+            "blog/something.md",
+            "blog/with-assets/index.md"
+        ]
+    );
+    assert_eq!(it.missing_keys, vec!["read-more", "reading-time", "title"]);
+}
@@ -1,28 +1,48 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::hash::BuildHasher;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use config::Config;
 
 use libs::base64::engine::{general_purpose::STANDARD as standard_b64, Engine};
+use libs::fixed_decimal::FixedDecimal;
+use libs::icu::calendar::{Date, DateTime};
+use libs::icu::datetime::{options::length, DateFormatter, DateTimeFormatter};
+use libs::icu::locid::Locale as IcuLocale;
+use libs::icu_experimental::relativetime::{RelativeTimeFormatter, RelativeTimeFormatterOptions};
 use libs::regex::Regex;
 use libs::tera::{
     to_value, try_get_value, Error as TeraError, Filter as TeraFilter, Result as TeraResult, Tera,
     Value,
 };
+use libs::time::OffsetDateTime;
+use libs::writeable::Writeable;
+use markdown::context::Caches;
 use markdown::{render_content, RenderContext};
+use utils::de::parse_yaml_datetime;
 
 #[derive(Debug)]
 pub struct MarkdownFilter {
     config: Config,
     permalinks: HashMap<String, String>,
     tera: Tera,
+    /// The root of the site, where the markdown filters and diagram commands run
+    base_path: Option<PathBuf>,
+    caches: Option<Arc<Caches>>,
 }
 
 impl MarkdownFilter {
     pub fn new(config: Config, permalinks: HashMap<String, String>, tera: Tera) -> Self {
-        Self { config, permalinks, tera }
+        Self { config, permalinks, tera, base_path: None, caches: None }
+    }
+
+    /// Runs the external commands from the root of the site and shares its caches
+    pub fn with_site(mut self, base_path: PathBuf, caches: Option<Arc<Caches>>) -> Self {
+        self.base_path = Some(base_path);
+        self.caches = caches;
+        self
     }
 }
 
@@ -35,7 +55,12 @@ impl TeraFilter for MarkdownFilter {
         let mut context = RenderContext::from_config(&self.config);
         context.permalinks = Cow::Borrowed(&self.permalinks);
         context.tera = Cow::Borrowed(&self.tera);
-        let def = utils::templates::get_shortcodes(&self.tera);
+        context.caches = self.caches.clone();
+        if let Some(ref base_path) = self.base_path {
+            context.set_base_path(base_path);
+        }
+        let mut def = utils::templates::get_shortcodes(&self.tera);
+        crate::load_builtin_shortcode_schemas(&mut def);
         context.set_shortcode_definitions(&def);
 
         let s = try_get_value!("markdown", "value", String, value);
@@ -158,16 +183,183 @@ impl TeraFilter for NumFormatFilter {
     }
 }
 
+/// Reads the value given to a date filter: a timestamp, or a date as found in the front matter,
+/// like `2024-03-01` or `2024-03-01T10:00:00+02:00`
+fn get_date_value(filter: &str, value: &Value) -> TeraResult<OffsetDateTime> {
+    match value {
+        Value::Number(n) => {
+            n.as_i64().and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok()).ok_or_else(|| {
+                TeraError::msg(format!("Filter `{}` received an invalid timestamp: {}", filter, n))
+            })
+        }
+        Value::String(s) => parse_yaml_datetime(s).map_err(|_| {
+            TeraError::msg(format!("Filter `{}` received an invalid date: `{}`", filter, s))
+        }),
+        _ => Err(TeraError::msg(format!(
+            "Filter `{}` was used on a value that is not a date: `{}`",
+            filter, value
+        ))),
+    }
+}
+
+fn get_locale(filter: &str, args: &HashMap<String, Value>, default: &str) -> TeraResult<IcuLocale> {
+    let locale = match args.get("locale") {
+        Some(locale) => try_get_value!(filter, "locale", String, locale),
+        None => default.to_string(),
+    };
+    locale.parse().map_err(|_| {
+        TeraError::msg(format!(
+            "Filter `{}` was called with an invalid `locale` argument: `{}`.",
+            filter, locale
+        ))
+    })
+}
+
+/// Formats a date using the CLDR patterns and names of the locale
+#[derive(Debug)]
+pub struct FormatDateFilter {
+    default_language: String,
+}
+
+impl FormatDateFilter {
+    pub fn new<S: Into<String>>(default_language: S) -> Self {
+        Self { default_language: default_language.into() }
+    }
+}
+
+impl TeraFilter for FormatDateFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+        let datetime = get_date_value("format_date", value)?;
+        let locale = get_locale("format_date", args, &self.default_language)?;
+        let get_length = |arg: &str, default: &str| -> TeraResult<Option<length::Date>> {
+            let name = match args.get(arg) {
+                Some(v) => try_get_value!("format_date", arg, String, v),
+                None => default.to_string(),
+            };
+            match name.as_str() {
+                "full" => Ok(Some(length::Date::Full)),
+                "long" => Ok(Some(length::Date::Long)),
+                "medium" => Ok(Some(length::Date::Medium)),
+                "short" => Ok(Some(length::Date::Short)),
+                "none" if arg == "time" => Ok(None),
+                _ => {
+                    let values = if arg == "time" {
+                        "full, long, medium, short or none"
+                    } else {
+                        "full, long, medium or short"
+                    };
+                    Err(TeraError::msg(format!(
+                        "Filter `format_date` received an invalid `{}` argument: `{}`. It must be one of {}.",
+                        arg, name, values
+                    )))
+                }
+            }
+        };
+        let date_length = get_length("length", "long")?.unwrap();
+        let time_length = get_length("time", "none")?;
+
+        let date = Date::try_new_iso_date(datetime.year(), datetime.month() as u8, datetime.day())
+            .map_err(|e| TeraError::msg(format!("Filter `format_date`: {}", e)))?;
+        let formatted = match time_length {
+            None => DateFormatter::try_new_with_length(&(&locale).into(), date_length)
+                .and_then(|f| f.format_to_string(&date.to_any())),
+            Some(time_length) => {
+                let time_length = match time_length {
+                    length::Date::Full => length::Time::Full,
+                    length::Date::Long => length::Time::Long,
+                    length::Date::Medium => length::Time::Medium,
+                    _ => length::Time::Short,
+                };
+                let datetime = DateTime::try_new_iso_datetime(
+                    datetime.year(),
+                    datetime.month() as u8,
+                    datetime.day(),
+                    datetime.hour(),
+                    datetime.minute(),
+                    datetime.second(),
+                )
+                .map_err(|e| TeraError::msg(format!("Filter `format_date`: {}", e)))?;
+                let options = length::Bag::from_date_time_style(date_length, time_length);
+                DateTimeFormatter::try_new(&(&locale).into(), options.into())
+                    .and_then(|f| f.format_to_string(&datetime.to_any()))
+            }
+        };
+        let formatted =
+            formatted.map_err(|e| TeraError::msg(format!("Filter `format_date`: {}", e)))?;
+        Ok(to_value(formatted).unwrap())
+    }
+}
+
+/// Formats the time between a date and the time of rendering, like `3 days ago` or `in 2 hours`
+#[derive(Debug)]
+pub struct RelativeTimeFilter {
+    default_language: String,
+    /// The time the dates are compared to, the current time when `None`.
+    /// Only set in tests, `zola serve` keeps the filter alive between rebuilds.
+    now: Option<OffsetDateTime>,
+}
+
+impl RelativeTimeFilter {
+    pub fn new<S: Into<String>>(default_language: S) -> Self {
+        Self { default_language: default_language.into(), now: None }
+    }
+}
+
+impl TeraFilter for RelativeTimeFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> TeraResult<Value> {
+        let datetime = get_date_value("relative_time", value)?;
+        let locale = get_locale("relative_time", args, &self.default_language)?;
+        let locale = (&locale).into();
+        let options = RelativeTimeFormatterOptions::default();
+
+        let now = self.now.unwrap_or_else(OffsetDateTime::now_utc);
+        let seconds = (datetime - now).whole_seconds();
+        // Use the largest unit that fits, months and years being approximate
+        let (amount, formatter) = match seconds.abs() {
+            s if s < 60 => (seconds, RelativeTimeFormatter::try_new_long_second(&locale, options)),
+            s if s < 3600 => {
+                (seconds / 60, RelativeTimeFormatter::try_new_long_minute(&locale, options))
+            }
+            s if s < 86400 => {
+                (seconds / 3600, RelativeTimeFormatter::try_new_long_hour(&locale, options))
+            }
+            s if s < 7 * 86400 => {
+                (seconds / 86400, RelativeTimeFormatter::try_new_long_day(&locale, options))
+            }
+            s if s < 30 * 86400 => {
+                (seconds / (7 * 86400), RelativeTimeFormatter::try_new_long_week(&locale, options))
+            }
+            s if s < 365 * 86400 => (
+                seconds / (30 * 86400),
+                RelativeTimeFormatter::try_new_long_month(&locale, options),
+            ),
+            _ => (
+                seconds / (365 * 86400),
+                RelativeTimeFormatter::try_new_long_year(&locale, options),
+            ),
+        };
+        let formatter =
+            formatter.map_err(|e| TeraError::msg(format!("Filter `relative_time`: {}", e)))?;
+        let formatted = formatter.format(FixedDecimal::from(amount)).write_to_string().into_owned();
+        Ok(to_value(formatted).unwrap())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::sync::Arc;
 
     use libs::tera::{to_value, Filter, Tera};
+    use markdown::context::Caches;
+    use tempfile::tempdir;
 
     use super::{
-        base64_decode, base64_encode, MarkdownFilter, NumFormatFilter, RegexReplaceFilter,
+        base64_decode, base64_encode, FormatDateFilter, MarkdownFilter, NumFormatFilter,
+        RegexReplaceFilter, RelativeTimeFilter,
     };
     use config::Config;
+    use libs::time::OffsetDateTime;
 
     #[test]
     fn markdown_filter() {
@@ -187,7 +379,7 @@ mod tests {
         let permalinks = HashMap::new();
         let mut tera = Tera::default();
         tera.add_raw_template("shortcodes/explicitlang.html", "a{{ lang }}a").unwrap();
-        let filter = MarkdownFilter { config, permalinks, tera };
+        let filter = MarkdownFilter::new(config, permalinks, tera);
         let result = filter.filter(&to_value(&"{{ explicitlang(lang='jp') }}").unwrap(), &args);
         println!("{:?}", result);
         assert!(result.is_ok());
@@ -264,6 +456,25 @@ mod tests {
         );
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn markdown_filter_runs_filters_from_the_site_root() {
+        let mut config = Config::default();
+        config.markdown.filters = vec![config::MarkdownFilter {
+            command: "sh -c 'pwd -P'".to_string(),
+            ..Default::default()
+        }];
+        let root = tempdir().unwrap();
+        let caches = Arc::new(Caches::new(&root.path().join("cache")));
+        let filter = MarkdownFilter::new(config, HashMap::new(), Tera::default())
+            .with_site(root.path().to_path_buf(), Some(caches.clone()));
+
+        let result = filter.filter(&to_value(&"Hello").unwrap(), &HashMap::new()).unwrap();
+        let root = root.path().canonicalize().unwrap();
+        assert_eq!(result, to_value(format!("{}\n", root.display())).unwrap());
+        assert_eq!(caches.filters.inner().len(), 1);
+    }
+
     #[test]
     fn base64_encode_filter() {
         // from https://tools.ietf.org/html/rfc4648#section-10
@@ -358,4 +569,69 @@ mod tests {
             assert_eq!(result.unwrap(), to_value(expected).unwrap());
         }
     }
+
+    #[test]
+    fn format_date_filter_with_locale() {
+        let tests = vec![
+            ("en", "long", "March 1, 2024"),
+            ("en", "short", "3/1/24"),
+            ("fr", "long", "1 mars 2024"),
+            ("fr", "full", "vendredi 1 mars 2024"),
+            ("de", "medium", "01.03.2024"),
+        ];
+
+        for (locale, length, expected) in tests {
+            let mut args = HashMap::new();
+            args.insert("locale".to_string(), to_value(locale).unwrap());
+            args.insert("length".to_string(), to_value(length).unwrap());
+            let result =
+                FormatDateFilter::new("en").filter(&to_value("2024-03-01").unwrap(), &args);
+            assert_eq!(result.unwrap(), to_value(expected).unwrap());
+        }
+    }
+
+    #[test]
+    fn format_date_filter_errors_on_invalid_input() {
+        let filter = FormatDateFilter::new("en");
+        let result = filter.filter(&to_value("yesterday").unwrap(), &HashMap::new());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Filter `format_date` received an invalid date: `yesterday`"
+        );
+
+        let mut args = HashMap::new();
+        args.insert("length".to_string(), to_value("tiny").unwrap());
+        let result = filter.filter(&to_value("2024-03-01").unwrap(), &args);
+        assert!(result.unwrap_err().to_string().contains("invalid `length` argument: `tiny`"));
+
+        let mut args = HashMap::new();
+        args.insert("time".to_string(), to_value("tiny").unwrap());
+        let result = filter.filter(&to_value("2024-03-01").unwrap(), &args);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Filter `format_date` received an invalid `time` argument: `tiny`. It must be one of full, long, medium, short or none."
+        );
+    }
+
+    #[test]
+    fn relative_time_filter() {
+        // 2024-03-10T12:00:00Z
+        let now = OffsetDateTime::from_unix_timestamp(1710072000).unwrap();
+        let tests = vec![
+            ("en", "2024-03-10T11:59:30Z", "30 seconds ago"),
+            ("en", "2024-03-10T09:00:00Z", "3 hours ago"),
+            ("en", "2024-03-07T12:00:00Z", "3 days ago"),
+            ("fr", "2024-03-07T12:00:00Z", "il y a 3 jours"),
+            ("en", "2024-03-24T12:00:00Z", "in 2 weeks"),
+            ("en", "2022-03-10T12:00:00Z", "2 years ago"),
+        ];
+
+        for (locale, date, expected) in tests {
+            let mut args = HashMap::new();
+            args.insert("locale".to_string(), to_value(locale).unwrap());
+            let filter = RelativeTimeFilter { now: Some(now), ..RelativeTimeFilter::new("en") };
+            let result = filter.filter(&to_value(date).unwrap(), &args);
+            assert_eq!(result.unwrap(), to_value(expected).unwrap());
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use config::Config;
 use content::{Library, Taxonomy, TaxonomyTerm};
 use libs::tera::{from_value, to_value, Function as TeraFn, Result, Value};
 use std::borrow::Cow;
@@ -8,22 +9,24 @@ use utils::slugs::{slugify_paths, SlugifyStrategy};
 
 #[derive(Debug)]
 pub struct GetTaxonomyUrl {
-    taxonomies: HashMap<String, HashMap<String, String>>,
+    /// The permalinks of the terms by slug, along with how the language of the taxonomy
+    /// slugifies them
+    taxonomies: HashMap<String, (SlugifyStrategy, HashMap<String, String>)>,
     default_lang: String,
-    slugify: SlugifyStrategy,
 }
 
 impl GetTaxonomyUrl {
-    pub fn new(default_lang: &str, all_taxonomies: &[Taxonomy], slugify: SlugifyStrategy) -> Self {
+    pub fn new(config: &Config, all_taxonomies: &[Taxonomy]) -> Self {
         let mut taxonomies = HashMap::new();
         for taxo in all_taxonomies {
+            let slugify = config.slugify_for(&taxo.lang).taxonomies;
             let mut items = HashMap::new();
             for item in &taxo.items {
-                items.insert(slugify_paths(&item.name.clone(), slugify), item.permalink.clone());
+                items.insert(slugify_paths(&item.name, slugify), item.permalink.clone());
             }
-            taxonomies.insert(format!("{}-{}", taxo.kind.name, taxo.lang), items);
+            taxonomies.insert(format!("{}-{}", taxo.kind.name, taxo.lang), (slugify, items));
         }
-        Self { taxonomies, default_lang: default_lang.to_string(), slugify }
+        Self { taxonomies, default_lang: config.default_language.clone() }
     }
 }
 impl TeraFn for GetTaxonomyUrl {
@@ -48,19 +51,20 @@ impl TeraFn for GetTaxonomyUrl {
         )
         .unwrap_or(true);
 
-        let container = match (self.taxonomies.get(&format!("{}-{}", kind, lang)), required) {
-            (Some(c), _) => c,
-            (None, false) => return Ok(Value::Null),
-            (None, true) => {
-                return Err(format!(
-                    "`get_taxonomy_url` received an unknown taxonomy as kind: {}",
-                    kind
-                )
-                .into());
-            }
-        };
+        let (slugify, container) =
+            match (self.taxonomies.get(&format!("{}-{}", kind, lang)), required) {
+                (Some(c), _) => c,
+                (None, false) => return Ok(Value::Null),
+                (None, true) => {
+                    return Err(format!(
+                        "`get_taxonomy_url` received an unknown taxonomy as kind: {}",
+                        kind
+                    )
+                    .into());
+                }
+            };
 
-        if let Some(permalink) = container.get(&slugify_paths(&name, self.slugify)) {
+        if let Some(permalink) = container.get(&slugify_paths(&name, *slugify)) {
             return Ok(to_value(permalink).unwrap());
         }
 
@@ -354,7 +358,7 @@ impl TeraFn for GetTaxonomyTerm {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use config::{Config, TaxonomyConfig};
+    use config::TaxonomyConfig;
     use content::{FileInfo, Library, Page, Section, SortBy, TaxonomyTerm};
     use std::path::Path;
     use std::sync::{Arc, RwLock};
@@ -516,6 +520,7 @@ mod tests {
             path: "/tags/".to_string(),
             permalink: "https://vincent.is/tags/".to_string(),
             items: vec![tag],
+            alternates: Vec::new(),
         };
         let tags_fr = Taxonomy {
             kind: taxo_config_fr,
@@ -524,6 +529,7 @@ mod tests {
             path: "/fr/tags/".to_string(),
             permalink: "https://vincent.is/fr/tags/".to_string(),
             items: vec![tag_fr],
+            alternates: Vec::new(),
         };
 
         let taxonomies = vec![tags.clone(), tags_fr.clone()];
@@ -587,6 +593,7 @@ mod tests {
             path: "/tags/".to_string(),
             permalink: "https://vincent.is/tags/".to_string(),
             items: vec![tag],
+            alternates: Vec::new(),
         };
         let tags_fr = Taxonomy {
             kind: taxo_config_fr,
@@ -595,11 +602,11 @@ mod tests {
             path: "/fr/tags/".to_string(),
             permalink: "https://vincent.is/fr/tags/".to_string(),
             items: vec![tag_fr],
+            alternates: Vec::new(),
         };
 
         let taxonomies = vec![tags, tags_fr];
-        let static_fn =
-            GetTaxonomyUrl::new(&config.default_language, &taxonomies, config.slugify.taxonomies);
+        let static_fn = GetTaxonomyUrl::new(&config, &taxonomies);
 
         // can find it correctly
         let mut args = HashMap::new();
@@ -654,6 +661,7 @@ mod tests {
             path: "/tags/".to_string(),
             permalink: "https://vincent.is/tags/".to_string(),
             items: vec![tag],
+            alternates: Vec::new(),
         };
         let tags_fr = Taxonomy {
             kind: taxo_config_fr,
@@ -662,6 +670,7 @@ mod tests {
             path: "/fr/tags/".to_string(),
             permalink: "https://vincent.is/fr/tags/".to_string(),
             items: vec![tag_fr],
+            alternates: Vec::new(),
         };
 
         let taxonomies = vec![tags.clone(), tags_fr.clone()];
@@ -102,10 +102,9 @@ impl TeraFn for GetUrl {
             // anything else
             let mut segments = vec![];
 
-            if lang != self.config.default_language
-                && (path.is_empty() || !path[1..].starts_with(&lang))
-            {
-                segments.push(lang);
+            let url_prefix = self.config.url_prefix(&lang);
+            if !url_prefix.is_empty() && (path.is_empty() || !path[1..].starts_with(url_prefix)) {
+                segments.push(url_prefix.to_string());
             }
 
             segments.push(path);
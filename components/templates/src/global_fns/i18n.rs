@@ -1,15 +1,162 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use libs::fluent_bundle::concurrent::FluentBundle;
+use libs::fluent_bundle::{FluentArgs, FluentResource, FluentValue};
 use libs::tera::{from_value, to_value, Error, Function as TeraFn, Result, Value};
+use libs::unic_langid::LanguageIdentifier;
 
 use config::Config;
-use std::collections::HashMap;
+use errors::{anyhow, bail, Result as ZolaResult};
+use utils::fs::read_file;
+
+/// The Fluent translation files of a site, loaded from `i18n/{lang}.ftl`.
+/// A theme can ship its own files in the same place, the ones of the site override their messages.
+#[derive(Default)]
+pub struct FluentTranslations {
+    bundles: HashMap<String, FluentBundle<FluentResource>>,
+    /// The message ids defined for each language, sorted
+    ids: HashMap<String, BTreeSet<String>>,
+}
+
+impl fmt::Debug for FluentTranslations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FluentTranslations").field("ids", &self.ids).finish()
+    }
+}
+
+impl FluentTranslations {
+    pub fn load(base_path: &Path, config: &Config) -> ZolaResult<Self> {
+        let mut translations = FluentTranslations::default();
+        let mut dirs = Vec::new();
+        if let Some(theme) = &config.theme {
+            dirs.push(base_path.join("themes").join(theme).join("i18n"));
+        }
+        dirs.push(base_path.join("i18n"));
+
+        for lang in config.languages.keys() {
+            let files: Vec<_> = dirs
+                .iter()
+                .map(|d| d.join(format!("{}.ftl", lang)))
+                .filter(|p| p.exists())
+                .collect();
+            if files.is_empty() {
+                continue;
+            }
+
+            let locale: LanguageIdentifier =
+                lang.parse().map_err(|_| anyhow!("Language `{}` is not a valid locale", lang))?;
+            let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+            // The isolation marks would end up in the HTML
+            bundle.set_use_isolating(false);
+            let mut ids = BTreeSet::new();
+            for path in files {
+                let source = read_file(&path)?;
+                let resource = match FluentResource::try_new(source) {
+                    Ok(r) => r,
+                    Err((resource, errors)) => {
+                        let messages: Vec<_> = errors
+                            .iter()
+                            .map(|e| {
+                                let line =
+                                    resource.source()[..e.pos.start].matches('\n').count() + 1;
+                                format!("line {}: {}", line, e)
+                            })
+                            .collect();
+                        bail!("Failed to parse {}:\n{}", path.display(), messages.join("\n"));
+                    }
+                };
+                for entry in resource.entries() {
+                    if let libs::fluent_syntax::ast::Entry::Message(message) = entry {
+                        ids.insert(message.id.name.to_string());
+                    }
+                }
+                bundle.add_resource_overriding(resource);
+            }
+            translations.bundles.insert(lang.clone(), bundle);
+            translations.ids.insert(lang.clone(), ids);
+        }
+
+        Ok(translations)
+    }
+
+    /// The ids of all the messages defined for that language
+    pub fn message_ids(&self, lang: &str) -> Option<&BTreeSet<String>> {
+        self.ids.get(lang)
+    }
+
+    /// Formats the message `key` of the given language.
+    /// A key of the form `message.attribute` gets the attribute of the message.
+    /// Returns `Ok(None)` if there is no such message.
+    fn format(&self, lang: &str, key: &str, args: &FluentArgs) -> Result<Option<String>> {
+        let bundle = match self.bundles.get(lang) {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        let (id, attribute) = match key.split_once('.') {
+            Some((id, attribute)) => (id, Some(attribute)),
+            None => (key, None),
+        };
+        let message = match bundle.get_message(id) {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+        let pattern = match attribute {
+            Some(attribute) => message.get_attribute(attribute).map(|a| a.value()),
+            None => message.value(),
+        };
+        let pattern = match pattern {
+            Some(p) => p,
+            None => return Ok(None),
+        };
+
+        let mut errors = Vec::new();
+        let value = bundle.format_pattern(pattern, Some(args), &mut errors);
+        if !errors.is_empty() {
+            let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(format!(
+                "`trans`: failed to format `{}` for language `{}`: {}",
+                key,
+                lang,
+                errors.join(", ")
+            )
+            .into());
+        }
+        Ok(Some(value.into_owned()))
+    }
+}
+
+fn to_fluent_value(name: &str, value: &Value) -> Result<FluentValue<'static>> {
+    match value {
+        Value::String(s) => Ok(FluentValue::from(s.clone())),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Ok(FluentValue::from(i)),
+            None => Ok(FluentValue::from(n.as_f64().unwrap_or_default())),
+        },
+        Value::Bool(b) => Ok(FluentValue::from(b.to_string())),
+        _ => {
+            Err(format!("`trans`: `args.{}` must be a string, a number or a boolean", name).into())
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Trans {
     config: Config,
+    fluent: Arc<FluentTranslations>,
 }
 impl Trans {
     pub fn new(config: Config) -> Self {
-        Self { config }
+        Self { config, fluent: Arc::new(FluentTranslations::default()) }
+    }
+
+    /// Also looks up the messages of the Fluent translation files, before the `[translations]`
+    /// of the config
+    pub fn with_fluent(mut self, fluent: Arc<FluentTranslations>) -> Self {
+        self.fluent = fluent;
+        self
     }
 }
 impl TeraFn for Trans {
@@ -17,11 +164,39 @@ impl TeraFn for Trans {
         let key = required_arg!(String, args.get("key"), "`trans` requires a `key` argument.");
         let lang = optional_arg!(String, args.get("lang"), "`trans`: `lang` must be a string.")
             .unwrap_or_else(|| self.config.default_language.clone());
+        let count = optional_arg!(f64, args.get("count"), "`trans`: `count` must be a number.");
+        let message_args = optional_arg!(
+            HashMap<String, Value>,
+            args.get("args"),
+            "`trans`: `args` must be a map of names to values."
+        );
 
-        let term = self
-            .config
-            .get_translation(&lang, &key)
-            .map_err(|e| Error::chain("Failed to retrieve term translation", e))?;
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in message_args.iter().flatten() {
+            fluent_args.set(name.clone(), to_fluent_value(name, value)?);
+        }
+        if count.is_some() {
+            fluent_args.set("count", to_fluent_value("count", &args["count"])?);
+        }
+
+        if let Some(term) = self.fluent.format(&lang, &key, &fluent_args)? {
+            return Ok(to_value(term).unwrap());
+        }
+
+        let term = match self.config.get_translation(&lang, &key) {
+            Ok(term) => term,
+            Err(e) => {
+                // Messages missing from a translation file fall back to the default language
+                if self.config.languages.contains_key(&lang) {
+                    if let Some(term) =
+                        self.fluent.format(&self.config.default_language, &key, &fluent_args)?
+                    {
+                        return Ok(to_value(term).unwrap());
+                    }
+                }
+                return Err(Error::chain("Failed to retrieve term translation", e));
+            }
+        };
 
         Ok(to_value(term).unwrap())
     }
@@ -30,6 +205,7 @@ impl TeraFn for Trans {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use libs::serde_json::json;
 
     const TRANS_CONFIG: &str = r#"
 base_url = "https://remplace-par-ton-url.fr"
@@ -80,4 +256,106 @@ title = "A title" "#;
         let error = Trans::new(config).call(&args).unwrap_err();
         assert_eq!("Failed to retrieve term translation", format!("{}", error));
     }
+
+    fn load_fluent_translations(files: &[(&str, &str)]) -> (Config, Arc<FluentTranslations>) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("i18n")).unwrap();
+        for (lang, content) in files {
+            std::fs::write(dir.path().join("i18n").join(format!("{}.ftl", lang)), content).unwrap();
+        }
+        let config = Config::parse(
+            r#"
+base_url = "https://example.com"
+default_language = "en"
+
+[languages.fr]
+[languages.pl]
+[languages.pl.translations]
+title = "Tytuł"
+"#,
+        )
+        .unwrap();
+        let translations = FluentTranslations::load(dir.path(), &config).unwrap();
+        (config, Arc::new(translations))
+    }
+
+    #[test]
+    fn can_use_plural_forms_of_the_language() {
+        let (config, translations) = load_fluent_translations(&[
+            ("en", "minutes = { $count ->\n    [one] { $count } minute\n   *[other] { $count } minutes\n}\n"),
+            (
+                "pl",
+                "minutes = { $count ->\n    [one] { $count } minuta\n    [few] { $count } minuty\n    [many] { $count } minut\n   *[other] { $count } minuty\n}\n",
+            ),
+        ]);
+        let static_fn = Trans::new(config).with_fluent(translations);
+        let mut args = HashMap::new();
+        args.insert("key".to_string(), to_value("minutes").unwrap());
+
+        for (count, expected) in [(1, "1 minute"), (5, "5 minutes")] {
+            args.insert("count".to_string(), to_value(count).unwrap());
+            assert_eq!(static_fn.call(&args).unwrap(), expected);
+        }
+
+        args.insert("lang".to_string(), to_value("pl").unwrap());
+        for (count, expected) in
+            [(1, "1 minuta"), (3, "3 minuty"), (5, "5 minut"), (22, "22 minuty")]
+        {
+            args.insert("count".to_string(), to_value(count).unwrap());
+            assert_eq!(static_fn.call(&args).unwrap(), expected);
+        }
+        args.insert("count".to_string(), to_value(1.5).unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), "1.5 minuty");
+    }
+
+    #[test]
+    fn can_use_args_and_select() {
+        let (config, translations) = load_fluent_translations(&[(
+            "en",
+            "shared = { $name } shared { $gender ->\n    [female] her\n    [male] his\n   *[other] their\n} post\nmenu = Menu\n    .title = Open the menu\n",
+        )]);
+        let static_fn = Trans::new(config).with_fluent(translations);
+        let mut args = HashMap::new();
+        args.insert("key".to_string(), to_value("shared").unwrap());
+        args.insert("args".to_string(), json!({"name": "Ada", "gender": "female"}));
+        assert_eq!(static_fn.call(&args).unwrap(), "Ada shared her post");
+
+        args.insert("args".to_string(), json!({"name": "Sam"}));
+        let err = static_fn.call(&args).unwrap_err().to_string();
+        assert!(err.starts_with("`trans`: failed to format `shared` for language `en`"));
+        assert!(err.contains("$gender"));
+
+        let mut args = HashMap::new();
+        args.insert("key".to_string(), to_value("menu.title").unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), "Open the menu");
+    }
+
+    #[test]
+    fn falls_back_to_default_language() {
+        let (config, translations) =
+            load_fluent_translations(&[("en", "hello = Hello\n"), ("fr", "bye = Au revoir\n")]);
+        let static_fn = Trans::new(config).with_fluent(translations);
+        let mut args = HashMap::new();
+        args.insert("lang".to_string(), to_value("fr").unwrap());
+        args.insert("key".to_string(), to_value("bye").unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), "Au revoir");
+        args.insert("key".to_string(), to_value("hello").unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), "Hello");
+
+        // The `[translations]` of the config are still used
+        args.insert("lang".to_string(), to_value("pl").unwrap());
+        args.insert("key".to_string(), to_value("title").unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), "Tytuł");
+    }
+
+    #[test]
+    fn errors_on_invalid_translation_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("i18n")).unwrap();
+        std::fs::write(dir.path().join("i18n").join("en.ftl"), "hello = Hello\n{ broken\n")
+            .unwrap();
+        let config = Config::parse(r#"base_url = "https://example.com""#).unwrap();
+        let err = FluentTranslations::load(dir.path(), &config).unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
 }
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 
-use libs::tera::{from_value, to_value, Function as TeraFn, Result, Value};
+use content::Library;
+use libs::tera::{from_value, to_value, Function as TeraFn, Map, Result, Value};
+use utils::fs::read_file;
 
 use crate::global_fns::helpers::search_for_file;
 
@@ -109,6 +112,12 @@ impl TeraFn for GetImageMetadata {
             "`get_image_metadata`: `allow_missing` must be a boolean (true or false)"
         )
         .unwrap_or(false);
+        let exif = optional_arg!(
+            bool,
+            args.get("exif"),
+            "`get_image_metadata`: `exif` must be a boolean (true or false)"
+        )
+        .unwrap_or(false);
 
         let (src_path, unified_path) =
             match search_for_file(&self.base_path, &path, &self.theme, &self.output_path)
@@ -123,15 +132,275 @@ impl TeraFn for GetImageMetadata {
                 }
             };
 
+        // The EXIF data is only read on demand so it needs its own cache entry
+        let cache_key = if exif { format!("{}#exif", unified_path) } else { unified_path };
         let mut cache = self.result_cache.lock().expect("result cache lock");
-        if let Some(cached_result) = cache.get(&unified_path) {
+        if let Some(cached_result) = cache.get(&cache_key) {
             return Ok(cached_result.clone());
         }
 
-        let response = imageproc::read_image_metadata(src_path)
+        let response = imageproc::read_image_metadata(&src_path)
             .map_err(|e| format!("`resize_image`: {}", e))?;
-        let out = to_value(response).unwrap();
-        cache.insert(unified_path, out.clone());
+        let mut out = to_value(response).unwrap();
+        if exif {
+            out["exif"] = to_value(imageproc::read_exif_data(&src_path)).unwrap();
+        }
+        cache.insert(cache_key, out.clone());
+
+        Ok(out)
+    }
+}
+
+/// The extensions of the colocated assets that `get_page_images` considers to be images
+const IMAGE_EXTENSIONS: &[&str] =
+    &["jpg", "jpeg", "png", "webp", "avif", "gif", "bmp", "tif", "tiff", "svg"];
+
+#[derive(Debug)]
+pub struct GetPageImages {
+    /// The base path of the Zola site
+    base_path: PathBuf,
+    library: Arc<RwLock<Library>>,
+    imageproc: Arc<Mutex<imageproc::Processor>>,
+    result_cache: Arc<Mutex<HashMap<String, Value>>>,
+}
+
+impl GetPageImages {
+    pub fn new(
+        base_path: PathBuf,
+        library: Arc<RwLock<Library>>,
+        imageproc: Arc<Mutex<imageproc::Processor>>,
+    ) -> Self {
+        Self { base_path, library, imageproc, result_cache: Arc::new(Mutex::new(HashMap::new())) }
+    }
+}
+
+/// Reads the `photo.jpg.toml` or `photo.jpg.md` file next to an image, if there is one.
+/// Returns the caption and, for TOML files, the whole table as `extra`.
+fn read_sidecar(image: &Path) -> Result<(Option<String>, Value)> {
+    let sidecar = |ext: &str| {
+        let mut path = OsString::from(image.as_os_str());
+        path.push(ext);
+        PathBuf::from(path)
+    };
+
+    let toml_path = sidecar(".toml");
+    if toml_path.exists() {
+        let content = read_file(&toml_path).map_err(|e| format!("`get_page_images`: {}", e))?;
+        let table: libs::toml::Table = libs::toml::from_str(&content).map_err(|e| {
+            format!("`get_page_images`: failed to parse {}: {}", toml_path.display(), e)
+        })?;
+        let caption = table.get("caption").and_then(|c| c.as_str()).map(|c| c.to_string());
+        return Ok((caption, to_value(table).unwrap()));
+    }
+
+    let md_path = sidecar(".md");
+    if md_path.exists() {
+        let content = read_file(&md_path).map_err(|e| format!("`get_page_images`: {}", e))?;
+        let caption = Some(content.trim().to_string()).filter(|c| !c.is_empty());
+        return Ok((caption, Value::Null));
+    }
+
+    Ok((None, Value::Null))
+}
+
+impl TeraFn for GetPageImages {
+    fn call(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        // Either the page object itself or the path to its markdown file, like `get_page`
+        let page = match args.get("page") {
+            Some(Value::Object(page)) => page.get("relative_path"),
+            page => page,
+        };
+        let page_path = required_arg!(
+            String,
+            page,
+            "`get_page_images` requires a `page` argument with a page or a string value"
+        );
+        let sort_by = optional_arg!(
+            String,
+            args.get("sort_by"),
+            "`get_page_images`: `sort_by` must be a string"
+        )
+        .unwrap_or_else(|| "filename".to_string());
+        if sort_by != "filename" && sort_by != "date" {
+            return Err(format!(
+                "`get_page_images`: `sort_by` must be either `filename` or `date`, got `{}`",
+                sort_by
+            )
+            .into());
+        }
+
+        let thumbnail_width = optional_arg!(
+            u32,
+            args.get("thumbnail_width"),
+            "`get_page_images`: `thumbnail_width` must be a non-negative integer"
+        );
+        let thumbnail_height = optional_arg!(
+            u32,
+            args.get("thumbnail_height"),
+            "`get_page_images`: `thumbnail_height` must be a non-negative integer"
+        );
+        let thumbnail_op = optional_arg!(
+            String,
+            args.get("thumbnail_op"),
+            "`get_page_images`: `thumbnail_op` must be a string"
+        )
+        .unwrap_or_else(|| DEFAULT_OP.to_string());
+        let thumbnail_format = optional_arg!(
+            String,
+            args.get("thumbnail_format"),
+            "`get_page_images`: `thumbnail_format` must be a string"
+        )
+        .unwrap_or_else(|| DEFAULT_FMT.to_string());
+        let thumbnail_quality = optional_arg!(
+            u8,
+            args.get("thumbnail_quality"),
+            "`get_page_images`: `thumbnail_quality` must be a number"
+        );
+        if let Some(quality) = thumbnail_quality {
+            if quality == 0 || quality > 100 {
+                return Err("`get_page_images`: `thumbnail_quality` must be in range 1-100"
+                    .to_string()
+                    .into());
+            }
+        }
+        let resize_op = if thumbnail_width.is_some() || thumbnail_height.is_some() {
+            Some(
+                imageproc::ResizeOperation::from_args(
+                    &thumbnail_op,
+                    thumbnail_width,
+                    thumbnail_height,
+                )
+                .map_err(|e| format!("`get_page_images`: {}", e))?,
+            )
+        } else {
+            None
+        };
+
+        // The thumbnail options change the result so they are part of the cache key
+        let cache_key = format!(
+            "{}#{}#{:?}#{:?}#{}#{}#{:?}",
+            page_path,
+            sort_by,
+            thumbnail_width,
+            thumbnail_height,
+            thumbnail_op,
+            thumbnail_format,
+            thumbnail_quality
+        );
+        if let Some(cached_result) = self.result_cache.lock().unwrap().get(&cache_key) {
+            return Ok(cached_result.clone());
+        }
+
+        // Only copy what we need from the page so the library lock is released before
+        // reading the images and taking the imageproc lock
+        let (page_dir, permalink, assets) = {
+            let library = self.library.read().unwrap();
+            let page = library
+                .pages
+                .get(&self.base_path.join("content").join(&page_path))
+                .ok_or_else(|| format!("`get_page_images`: page `{}` not found.", page_path))?;
+            let page_dir = page.file.path.parent().unwrap().to_path_buf();
+            (page_dir, page.permalink.clone(), page.assets.clone())
+        };
+
+        let mut images = Vec::new();
+        for asset in &assets {
+            let ext = asset
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_lowercase())
+                .unwrap_or_default();
+            if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+                continue;
+            }
+            let relative = asset
+                .strip_prefix(&page_dir)
+                .map_err(|_| {
+                    format!(
+                        "`get_page_images`: asset {} is not in the page directory {}",
+                        asset.display(),
+                        page_dir.display()
+                    )
+                })?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let unified_path = asset
+                .strip_prefix(&self.base_path)
+                .map_err(|_| {
+                    format!(
+                        "`get_page_images`: asset {} is not in the site directory {}",
+                        asset.display(),
+                        self.base_path.display()
+                    )
+                })?
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            let meta = imageproc::read_image_metadata(asset)
+                .map_err(|e| format!("`get_page_images`: {}", e))?;
+            let exif = imageproc::read_exif_data(asset);
+            let (caption, extra) = read_sidecar(asset)?;
+
+            let thumbnail = match &resize_op {
+                Some(op) if ext != "svg" => {
+                    let response = self
+                        .imageproc
+                        .lock()
+                        .unwrap()
+                        .enqueue(
+                            *op,
+                            unified_path.clone(),
+                            asset.clone(),
+                            &thumbnail_format,
+                            thumbnail_quality,
+                        )
+                        .map_err(|e| format!("`get_page_images`: {}", e))?;
+                    to_value(response).unwrap()
+                }
+                _ => Value::Null,
+            };
+
+            let mut image = Map::new();
+            image.insert(
+                "filename".to_string(),
+                to_value(asset.file_name().unwrap().to_string_lossy()).unwrap(),
+            );
+            image.insert(
+                "path".to_string(),
+                to_value(format!("/{}", unified_path.trim_start_matches("content/"))).unwrap(),
+            );
+            image.insert(
+                "permalink".to_string(),
+                to_value(format!("{}{}", permalink, relative)).unwrap(),
+            );
+            image.insert("width".to_string(), to_value(meta.width).unwrap());
+            image.insert("height".to_string(), to_value(meta.height).unwrap());
+            image.insert("format".to_string(), to_value(meta.format).unwrap());
+            image.insert("mime".to_string(), to_value(meta.mime).unwrap());
+            image.insert("date_time".to_string(), to_value(&exif.date_time).unwrap());
+            // A caption written by hand next to the image wins over the one embedded in it
+            image.insert(
+                "caption".to_string(),
+                to_value(caption.or_else(|| exif.caption.clone())).unwrap(),
+            );
+            image.insert("extra".to_string(), extra);
+            image.insert("thumbnail".to_string(), thumbnail);
+            image.insert("exif".to_string(), to_value(&exif).unwrap());
+            images.push((exif.date_time, Value::Object(image)));
+        }
+
+        // Assets are already sorted by filename, images without a date go last
+        if sort_by == "date" {
+            images.sort_by(|(a, _), (b, _)| match (a, b) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            });
+        }
+
+        let out = Value::Array(images.into_iter().map(|(_, image)| image).collect());
+        self.result_cache.lock().unwrap().insert(cache_key, out.clone());
 
         Ok(out)
     }
@@ -139,15 +408,16 @@ impl TeraFn for GetImageMetadata {
 
 #[cfg(test)]
 mod tests {
-    use super::{GetImageMetadata, ResizeImage};
+    use super::{GetImageMetadata, GetPageImages, ResizeImage};
 
     use std::collections::HashMap;
-    use std::fs::{copy, create_dir_all};
+    use std::fs::{copy, create_dir_all, write};
 
     use config::Config;
-    use libs::tera::{to_value, Function};
+    use content::{Library, Page};
+    use libs::tera::{to_value, Context, Function, Tera, Value};
     use std::path::{Path, PathBuf};
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, Mutex, RwLock};
     use tempfile::{tempdir, TempDir};
 
     fn create_dir_with_image() -> TempDir {
@@ -254,6 +524,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn builtin_figure_shortcode_resizes_local_images() {
+        let dir = create_dir_with_image();
+        let imageproc = imageproc::Processor::new(dir.path().to_path_buf(), &Config::default());
+        let mut tera = Tera::default();
+        tera.extend(&crate::ZOLA_TERA).unwrap();
+        tera.register_function(
+            "resize_image",
+            ResizeImage::new(
+                dir.path().to_path_buf(),
+                Arc::new(Mutex::new(imageproc)),
+                None,
+                PathBuf::new(),
+            ),
+        );
+        // The defaults of the schema of the shortcode
+        let mut context = Context::new();
+        context.insert("alt", "");
+        context.insert("op", "fill");
+        context.insert("format", "auto");
+        context.insert("lazy", &false);
+
+        context.insert("src", "static/gutenberg.jpg");
+        context.insert("width", &40);
+        context.insert("height", &40);
+        let out = tera.render("__zola_builtins/shortcodes/figure.html", &context).unwrap();
+        assert_eq!(
+            out.trim_end(),
+            r#"<figure><img src="http://a-website.com/processed_images/gutenberg.da10f4be4f1c441e.jpg" alt="" width="40" height="40"></figure>"#
+        );
+
+        // Colocated images are found from the page and resized to fit the only dimension given
+        context.insert("page", &HashMap::from([("colocated_path", "gallery/")]));
+        context.insert("src", "asset.jpg");
+        context.remove("height");
+        let out = tera.render("__zola_builtins/shortcodes/figure.html", &context).unwrap();
+        assert!(out.contains(r#"src="http://a-website.com/processed_images/asset."#));
+        assert!(out.contains(r#" width="40" height="50">"#));
+    }
+
     // TODO: consider https://github.com/getzola/zola/issues/1161
     #[test]
     fn can_get_image_metadata() {
@@ -298,5 +608,82 @@ mod tests {
         assert_eq!(data["width"], to_value(300).unwrap());
         assert_eq!(data["format"], to_value("jpg").unwrap());
         assert_eq!(data["mime"], to_value("image/jpeg").unwrap());
+        assert!(!data.contains_key("exif"));
+
+        // 5. the EXIF data is only included when asked for
+        args.insert("exif".to_string(), to_value(true).unwrap());
+        let data = static_fn.call(&args).unwrap().as_object().unwrap().clone();
+        assert_eq!(data["width"], to_value(300).unwrap());
+        assert_eq!(data["exif"]["date_time"], Value::Null);
+        assert_eq!(data["exif"]["keywords"], to_value(Vec::<String>::new()).unwrap());
+    }
+
+    #[test]
+    fn can_get_page_images() {
+        let dir = create_dir_with_image();
+        let gallery = dir.path().join("content").join("gallery");
+        write(gallery.join("index.md"), "+++\ntitle = \"Gallery\"\n+++\n").unwrap();
+        copy("gutenberg.jpg", gallery.join("b.jpg")).unwrap();
+        write(gallery.join("b.jpg.toml"), "caption = \"Printing press\"\nyear = 1440\n").unwrap();
+        write(gallery.join("asset.jpg.md"), "  A *colocated* image\n").unwrap();
+        write(gallery.join("notes.txt"), "not an image").unwrap();
+
+        let config = Config::default_for_test();
+        let mut library = Library::new(&config);
+        library
+            .insert_page(Page::from_file(gallery.join("index.md"), &config, dir.path()).unwrap());
+        let imageproc =
+            Arc::new(Mutex::new(imageproc::Processor::new(dir.path().to_path_buf(), &config)));
+        let static_fn = GetPageImages::new(
+            dir.path().to_path_buf(),
+            Arc::new(RwLock::new(library)),
+            imageproc.clone(),
+        );
+
+        let mut args = HashMap::new();
+        args.insert("page".to_string(), to_value("gallery/index.md").unwrap());
+        let res = static_fn.call(&args).unwrap();
+        let images = res.as_array().unwrap();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0]["filename"], to_value("asset.jpg").unwrap());
+        assert_eq!(images[0]["path"], to_value("/gallery/asset.jpg").unwrap());
+        assert_eq!(
+            images[0]["permalink"],
+            to_value("http://a-website.com/gallery/asset.jpg").unwrap()
+        );
+        assert_eq!(images[0]["width"], to_value(300).unwrap());
+        assert_eq!(images[0]["height"], to_value(380).unwrap());
+        assert_eq!(images[0]["caption"], to_value("A *colocated* image").unwrap());
+        assert_eq!(images[0]["extra"], Value::Null);
+        assert_eq!(images[0]["thumbnail"], Value::Null);
+        assert_eq!(images[1]["filename"], to_value("b.jpg").unwrap());
+        assert_eq!(images[1]["caption"], to_value("Printing press").unwrap());
+        assert_eq!(images[1]["extra"]["year"], to_value(1440).unwrap());
+        // The second call is served from the cache
+        assert_eq!(static_fn.result_cache.lock().unwrap().len(), 1);
+        assert_eq!(static_fn.call(&args).unwrap(), res);
+
+        // Passing the page object and asking for thumbnails
+        let page = to_value(HashMap::from([("relative_path", "gallery/index.md")])).unwrap();
+        args.insert("page".to_string(), page);
+        args.insert("thumbnail_width".to_string(), to_value(40).unwrap());
+        args.insert("thumbnail_height".to_string(), to_value(40).unwrap());
+        let res = static_fn.call(&args).unwrap();
+        let images = res.as_array().unwrap();
+        // Thumbnails are the same images as the ones `resize_image` would create
+        let resize_fn = ResizeImage::new(dir.path().to_path_buf(), imageproc, None, PathBuf::new());
+        let mut resize_args = HashMap::new();
+        resize_args.insert("path".to_string(), to_value("@/gallery/asset.jpg").unwrap());
+        resize_args.insert("width".to_string(), to_value(40).unwrap());
+        resize_args.insert("height".to_string(), to_value(40).unwrap());
+        assert_eq!(images[0]["thumbnail"], resize_fn.call(&resize_args).unwrap());
+        assert_eq!(images[0]["thumbnail"]["width"], to_value(40).unwrap());
+
+        // Errors
+        args.insert("sort_by".to_string(), to_value("size").unwrap());
+        assert!(static_fn.call(&args).is_err());
+        args.insert("sort_by".to_string(), to_value("date").unwrap());
+        args.insert("page".to_string(), to_value("missing/index.md").unwrap());
+        assert!(static_fn.call(&args).is_err());
     }
 }
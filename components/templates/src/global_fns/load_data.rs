@@ -4,17 +4,20 @@ use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use libs::csv::Reader;
 use libs::reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
-use libs::reqwest::{blocking::Client, header};
+use libs::reqwest::{blocking::Client, header, StatusCode};
+use libs::sha2::{Digest, Sha256};
+use libs::slug::slugify;
 use libs::tera::{
     from_value, to_value, Error, Error as TeraError, Function as TeraFn, Map, Result, Value,
 };
 use libs::url::Url;
 use libs::{nom_bibtex, serde_json, serde_yaml, toml};
 use utils::de::fix_toml_dates;
-use utils::fs::{get_file_time, read_file};
+use utils::fs::{create_file, get_file_time, read_file};
 
 use crate::global_fns::helpers::search_for_file;
 
@@ -152,6 +155,80 @@ impl DataSource {
     }
 }
 
+/// A stable key for a remote request, used to name its file in the on-disk cache.
+/// Unlike `get_cache_key`, it needs to be the same across builds and Rust versions.
+fn get_disk_cache_key(
+    url: &Url,
+    format: &OutputFormat,
+    method: Method,
+    post_body: &Option<String>,
+    post_content_type: &Option<String>,
+    headers: &Option<Vec<String>>,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(
+        format!(
+            "{:?}\n{:?}\n{}\n{:?}\n{:?}\n{:?}",
+            method, format, url, post_body, post_content_type, headers
+        )
+        .as_bytes(),
+    );
+    let hash = format!("{:x}", hasher.finalize());
+    // A readable prefix makes it easier to find a given response when pinning fixtures
+    let mut name = slugify(format!("{}{}", url.host_str().unwrap_or_default(), url.path()));
+    name.truncate(50);
+    format!("{}-{}.json", name.trim_end_matches('-'), &hash[..16])
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// A response to a remote request, as stored in the on-disk cache
+#[derive(Debug)]
+struct CachedResponse {
+    url: String,
+    /// When the response was last fetched or revalidated, in seconds since the epoch
+    fetched_at: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+impl CachedResponse {
+    /// Returns `None` if there is no cached response or if it can't be read
+    fn read(path: &Path) -> Option<Self> {
+        let content = read_file(path).ok()?;
+        let value: Value = serde_json::from_str(&content).ok()?;
+        let string = |key: &str| value.get(key).and_then(|v| v.as_str()).map(|v| v.to_string());
+        Some(CachedResponse {
+            url: string("url")?,
+            fetched_at: value.get("fetched_at").and_then(|v| v.as_u64()).unwrap_or_default(),
+            etag: string("etag"),
+            last_modified: string("last_modified"),
+            body: string("body")?,
+        })
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        let value = serde_json::json!({
+            "url": self.url,
+            "fetched_at": self.fetched_at,
+            "etag": self.etag,
+            "last_modified": self.last_modified,
+            "body": self.body,
+        });
+        let content = serde_json::to_string_pretty(&value).map_err(|e| format!("{:?}", e))?;
+        create_file(path, content).map_err(|e| {
+            format!("`load_data`: Failed to write the cache file {}: {}", path.display(), e).into()
+        })
+    }
+
+    fn is_fresh(&self, ttl: u64) -> bool {
+        now() < self.fetched_at.saturating_add(ttl)
+    }
+}
+
 impl Hash for DataSource {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self {
@@ -219,6 +296,12 @@ pub struct LoadData {
     client: Arc<Mutex<Client>>,
     result_cache: Arc<Mutex<HashMap<u64, Value>>>,
     output_path: PathBuf,
+    /// Where to keep the responses of remote requests across builds, if anywhere
+    disk_cache: Option<PathBuf>,
+    /// How long a response in the disk cache is used without revalidating it, in seconds
+    disk_cache_ttl: u64,
+    /// Only use the disk cache and never make a request
+    offline: bool,
 }
 impl LoadData {
     pub fn new(base_path: PathBuf, theme: Option<String>, output_path: PathBuf) -> Self {
@@ -229,7 +312,30 @@ impl LoadData {
                 .expect("reqwest client build"),
         ));
         let result_cache = Arc::new(Mutex::new(HashMap::new()));
-        Self { base_path, client, result_cache, theme, output_path }
+        Self {
+            base_path,
+            client,
+            result_cache,
+            theme,
+            output_path,
+            disk_cache: None,
+            disk_cache_ttl: 0,
+            offline: false,
+        }
+    }
+
+    /// Keeps the responses of remote requests in `dir`, and reuses them for `ttl` seconds
+    /// before revalidating them with the server
+    pub fn with_disk_cache(mut self, dir: PathBuf, ttl: u64) -> Self {
+        self.disk_cache = Some(dir);
+        self.disk_cache_ttl = ttl;
+        self
+    }
+
+    /// In offline mode, remote data is only read from the disk cache, regardless of its age
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
     }
 }
 
@@ -328,57 +434,132 @@ impl TeraFn for LoadData {
             DataSource::Path(path) => read_file(&path)
                 .map_err(|e| format!("`load_data`: error reading file {:?}: {}", path, e)),
             DataSource::Url(url) => {
-                let response_client = self.client.lock().expect("response client lock");
-                let req = match method {
-                    Method::Get => response_client
-                        .get(url.as_str())
-                        .headers(add_headers_from_args(headers)?)
-                        .header(header::ACCEPT, file_format.as_accept_header()),
-                    Method::Post => {
-                        let mut resp = response_client
-                            .post(url.as_str())
-                            .headers(add_headers_from_args(headers)?)
-                            .header(header::ACCEPT, file_format.as_accept_header());
-                        if let Some(content_type) = post_content_type {
-                            match HeaderValue::from_str(&content_type) {
-                                Ok(c) => {
-                                    resp = resp.header(CONTENT_TYPE, c);
+                let cache_path = self.disk_cache.as_ref().map(|dir| {
+                    dir.join(get_disk_cache_key(
+                        &url,
+                        &file_format,
+                        method,
+                        &post_body_arg,
+                        &post_content_type,
+                        &headers,
+                    ))
+                });
+                let cached = cache_path.as_deref().and_then(CachedResponse::read);
+
+                match cached {
+                    Some(c) if self.offline || c.is_fresh(self.disk_cache_ttl) => Ok(c.body),
+                    None if self.offline => {
+                        if !required {
+                            return Ok(Value::Null);
+                        }
+                        Err(format!(
+                            "`load_data`: {} is not in the cache and cannot be requested in offline mode",
+                            url
+                        ))
+                    }
+                    cached => {
+                        let response_client = self.client.lock().expect("response client lock");
+                        let mut req = match method {
+                            Method::Get => response_client
+                                .get(url.as_str())
+                                .headers(add_headers_from_args(headers)?)
+                                .header(header::ACCEPT, file_format.as_accept_header()),
+                            Method::Post => {
+                                let mut resp = response_client
+                                    .post(url.as_str())
+                                    .headers(add_headers_from_args(headers)?)
+                                    .header(header::ACCEPT, file_format.as_accept_header());
+                                if let Some(content_type) = post_content_type {
+                                    match HeaderValue::from_str(&content_type) {
+                                        Ok(c) => {
+                                            resp = resp.header(CONTENT_TYPE, c);
+                                        }
+                                        Err(_) => {
+                                            return Err(format!(
+                                                "`load_data`: {} is an illegal content type",
+                                                &content_type
+                                            )
+                                            .into());
+                                        }
+                                    }
                                 }
-                                Err(_) => {
-                                    return Err(format!(
-                                        "`load_data`: {} is an illegal content type",
-                                        &content_type
-                                    )
-                                    .into());
+                                if let Some(body) = post_body_arg {
+                                    resp = resp.body(body);
                                 }
+                                resp
+                            }
+                        };
+                        // Only ask for the body if it changed since we cached it
+                        if let Some(c) = &cached {
+                            if let Some(etag) = &c.etag {
+                                req = req.header(header::IF_NONE_MATCH, etag.as_str());
+                            }
+                            if let Some(last_modified) = &c.last_modified {
+                                req = req.header(header::IF_MODIFIED_SINCE, last_modified.as_str());
                             }
                         }
-                        if let Some(body) = post_body_arg {
-                            resp = resp.body(body);
-                        }
-                        resp
-                    }
-                };
 
-                match req.send().and_then(|res| res.error_for_status()) {
-                    Ok(r) => r.text().map_err(|e| {
-                        format!("`load_data`: Failed to parse response from {}: {:?}", url, e)
-                    }),
-                    Err(e) => {
-                        if !required {
-                            // HTTP error is discarded (because required=false) and
-                            // Null value is returned to the template
-                            return Ok(Value::Null);
-                        }
-                        Err(match e.status() {
-                            Some(status) => {
-                                format!("`load_data`: Failed to request {}: {}", url, status)
+                        match (req.send().and_then(|res| res.error_for_status()), cached) {
+                            (Ok(r), Some(mut c)) if r.status() == StatusCode::NOT_MODIFIED => {
+                                c.fetched_at = now();
+                                c.write(cache_path.as_deref().unwrap())?;
+                                Ok(c.body)
+                            }
+                            (Ok(r), _) => {
+                                let header_value = |name: header::HeaderName| {
+                                    r.headers()
+                                        .get(name)
+                                        .and_then(|v| v.to_str().ok())
+                                        .map(|v| v.to_string())
+                                };
+                                let etag = header_value(header::ETAG);
+                                let last_modified = header_value(header::LAST_MODIFIED);
+                                let body = r.text().map_err(|e| {
+                                    format!(
+                                        "`load_data`: Failed to parse response from {}: {:?}",
+                                        url, e
+                                    )
+                                })?;
+                                if let Some(path) = &cache_path {
+                                    CachedResponse {
+                                        url: url.to_string(),
+                                        fetched_at: now(),
+                                        etag,
+                                        last_modified,
+                                        body: body.clone(),
+                                    }
+                                    .write(path)?;
+                                }
+                                Ok(body)
                             }
-                            None => format!(
-                                "`load_data`: Could not get response status for url: {}",
-                                url
-                            ),
-                        })
+                            // A stale response is better than no response at all
+                            (Err(e), Some(c)) => {
+                                console::warn(&format!(
+                                    "`load_data`: Failed to request {} ({}), using the cached response",
+                                    url, e
+                                ));
+                                Ok(c.body)
+                            }
+                            (Err(e), None) => {
+                                if !required {
+                                    // HTTP error is discarded (because required=false) and
+                                    // Null value is returned to the template
+                                    return Ok(Value::Null);
+                                }
+                                Err(match e.status() {
+                                    Some(status) => {
+                                        format!(
+                                            "`load_data`: Failed to request {}: {}",
+                                            url, status
+                                        )
+                                    }
+                                    None => format!(
+                                        "`load_data`: Could not get response status for url: {}",
+                                        url
+                                    ),
+                                })
+                            }
+                        }
                     }
                 }
             }
@@ -1379,4 +1560,105 @@ mod tests {
             })
         )
     }
+
+    #[test]
+    fn can_reuse_disk_cache_across_builds() {
+        let mut server = mockito::Server::new();
+        let m = server
+            .mock("GET", "/Ahnoo3ievu")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"foo": "bar"}"#)
+            .expect(1)
+            .create();
+        let url = format!("{}{}", server.url(), "/Ahnoo3ievu");
+        let cache_dir = tempdir().unwrap();
+
+        let mut args = HashMap::new();
+        args.insert("url".to_string(), to_value(&url).unwrap());
+        args.insert("format".to_string(), to_value("json").unwrap());
+        for _ in 0..2 {
+            let static_fn = LoadData::new(PathBuf::new(), None, PathBuf::new())
+                .with_disk_cache(cache_dir.path().to_path_buf(), 3600);
+            let result = static_fn.call(&args).unwrap();
+            assert_eq!(result, json!({"foo": "bar"}));
+        }
+        m.assert();
+
+        let cached: Vec<_> = std::fs::read_dir(cache_dir.path()).unwrap().collect();
+        assert_eq!(cached.len(), 1);
+        let filename = cached[0].as_ref().unwrap().file_name().into_string().unwrap();
+        assert!(filename.starts_with("127-0-0-1-ahnoo3ievu-"));
+    }
+
+    #[test]
+    fn revalidates_stale_disk_cache() {
+        let mut server = mockito::Server::new();
+        let m = server
+            .mock("GET", "/eeTh5shoo3")
+            .with_header("etag", "\"v1\"")
+            .with_body("first")
+            .create();
+        let url = format!("{}{}", server.url(), "/eeTh5shoo3");
+        let cache_dir = tempdir().unwrap();
+        let static_fn = LoadData::new(PathBuf::new(), None, PathBuf::new())
+            .with_disk_cache(cache_dir.path().to_path_buf(), 0);
+        let mut args = HashMap::new();
+        args.insert("url".to_string(), to_value(&url).unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), to_value("first").unwrap());
+        m.assert();
+        m.remove();
+
+        // Not modified: the cached body is used
+        let m = server
+            .mock("GET", "/eeTh5shoo3")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create();
+        let static_fn = LoadData::new(PathBuf::new(), None, PathBuf::new())
+            .with_disk_cache(cache_dir.path().to_path_buf(), 0);
+        assert_eq!(static_fn.call(&args).unwrap(), to_value("first").unwrap());
+        m.assert();
+        m.remove();
+
+        // The server is down: the stale body is better than nothing
+        let m = server.mock("GET", "/eeTh5shoo3").with_status(500).create();
+        let static_fn = LoadData::new(PathBuf::new(), None, PathBuf::new())
+            .with_disk_cache(cache_dir.path().to_path_buf(), 0);
+        assert_eq!(static_fn.call(&args).unwrap(), to_value("first").unwrap());
+        m.assert();
+    }
+
+    #[test]
+    fn offline_mode_only_uses_disk_cache() {
+        let mut server = mockito::Server::new();
+        let m = server.mock("GET", "/ohC4ieM1ai").with_body("cached").expect(1).create();
+        let url = format!("{}{}", server.url(), "/ohC4ieM1ai");
+        let cache_dir = tempdir().unwrap();
+        let mut args = HashMap::new();
+        args.insert("url".to_string(), to_value(&url).unwrap());
+
+        let offline_fn = || {
+            LoadData::new(PathBuf::new(), None, PathBuf::new())
+                .with_disk_cache(cache_dir.path().to_path_buf(), 0)
+                .offline(true)
+        };
+        let result = offline_fn().call(&args);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "`load_data`: {} is not in the cache and cannot be requested in offline mode",
+                url
+            )
+        );
+        let mut not_required_args = args.clone();
+        not_required_args.insert("required".to_string(), to_value(false).unwrap());
+        assert_eq!(offline_fn().call(&not_required_args).unwrap(), tera::Value::Null);
+
+        let static_fn = LoadData::new(PathBuf::new(), None, PathBuf::new())
+            .with_disk_cache(cache_dir.path().to_path_buf(), 0);
+        assert_eq!(static_fn.call(&args).unwrap(), to_value("cached").unwrap());
+        // Even though the TTL is 0, the cache is used without making a request
+        assert_eq!(offline_fn().call(&args).unwrap(), to_value("cached").unwrap());
+        m.assert();
+    }
 }
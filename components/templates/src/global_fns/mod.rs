@@ -10,6 +10,6 @@ mod load_data;
 
 pub use self::content::{GetPage, GetSection, GetTaxonomy, GetTaxonomyTerm, GetTaxonomyUrl};
 pub use self::files::{GetHash, GetUrl};
-pub use self::i18n::Trans;
-pub use self::images::{GetImageMetadata, ResizeImage};
+pub use self::i18n::{FluentTranslations, Trans};
+pub use self::images::{GetImageMetadata, GetPageImages, ResizeImage};
 pub use self::load_data::LoadData;
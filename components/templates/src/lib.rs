@@ -1,6 +1,7 @@
 pub mod filters;
 pub mod global_fns;
 
+use std::collections::HashMap;
 use std::path::Path;
 
 use config::Config;
@@ -8,7 +9,31 @@ use libs::once_cell::sync::Lazy;
 use libs::tera::{Context, Tera};
 
 use errors::{bail, Context as ErrorContext, Result};
-use utils::templates::rewrite_theme_paths;
+use utils::templates::{rewrite_theme_paths, ShortcodeDefinition, ShortcodeSchema};
+
+macro_rules! builtin_shortcode {
+    ($name: expr) => {
+        (
+            $name,
+            include_str!(concat!("builtins/shortcodes/", $name, ".html")),
+            include_str!(concat!("builtins/shortcodes/", $name, ".toml")),
+        )
+    };
+}
+
+/// The shortcodes shipped with Zola as (name, template, argument schema), which can be
+/// overridden by a shortcode with the same name in the site or the theme
+const BUILTIN_SHORTCODES: &[(&str, &str, &str)] = &[
+    builtin_shortcode!("asciinema"),
+    builtin_shortcode!("details"),
+    builtin_shortcode!("figure"),
+    builtin_shortcode!("gist"),
+    builtin_shortcode!("mermaid"),
+    builtin_shortcode!("tab"),
+    builtin_shortcode!("tabs"),
+    builtin_shortcode!("vimeo"),
+    builtin_shortcode!("youtube"),
+];
 
 pub static ZOLA_TERA: Lazy<Tera> = Lazy::new(|| {
     let mut tera = Tera::default();
@@ -23,16 +48,43 @@ pub static ZOLA_TERA: Lazy<Tera> = Lazy::new(|| {
             include_str!("builtins/split_sitemap_index.xml"),
         ),
         ("__zola_builtins/anchor-link.html", include_str!("builtins/anchor-link.html")),
+        ("__zola_builtins/callout.html", include_str!("builtins/callout.html")),
         ("__zola_builtins/summary-cutoff.html", include_str!("builtins/summary-cutoff.html")),
+        ("__zola_builtins/toc.html", include_str!("builtins/toc.html")),
         ("internal/alias.html", include_str!("builtins/internal/alias.html")),
     ])
     .unwrap();
+    tera.add_raw_templates(BUILTIN_SHORTCODES.iter().map(|(name, template, _)| {
+        (format!("__zola_builtins/shortcodes/{}.html", name), *template)
+    }))
+    .unwrap();
     tera.register_filter("base64_encode", filters::base64_encode);
     tera.register_filter("base64_decode", filters::base64_decode);
     tera.register_filter("regex_replace", filters::RegexReplaceFilter::new());
     tera
 });
 
+/// The argument schemas of the built-in shortcodes, only parsed once
+static BUILTIN_SHORTCODE_SCHEMAS: Lazy<HashMap<&str, ShortcodeSchema>> = Lazy::new(|| {
+    BUILTIN_SHORTCODES
+        .iter()
+        .map(|(name, _, schema)| {
+            (*name, ShortcodeSchema::parse(schema).expect("Built-in shortcode schemas are valid"))
+        })
+        .collect()
+});
+
+/// Sets the argument schema of the built-in shortcodes that have not been overridden
+pub fn load_builtin_shortcode_schemas(definitions: &mut HashMap<String, ShortcodeDefinition>) {
+    for (name, schema) in BUILTIN_SHORTCODE_SCHEMAS.iter() {
+        if let Some(definition) = definitions.get_mut(*name) {
+            if definition.tera_name.starts_with("__zola_builtins/") {
+                definition.schema = Some(schema.clone());
+            }
+        }
+    }
+}
+
 /// Renders the `internal/alias.html` template that will redirect
 /// via refresh to the url given
 pub fn render_redirect_template(url: &str, tera: &Tera) -> Result<String> {
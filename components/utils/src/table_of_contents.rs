@@ -7,6 +7,8 @@ pub struct Heading {
     pub id: String,
     pub permalink: String,
     pub title: String,
+    /// The hierarchical number of the heading, eg `2.3.1`, if numbering is enabled
+    pub number: Option<String>,
     pub children: Vec<Heading>,
 }
 
@@ -44,6 +46,31 @@ fn insert_into_parent(potential_parent: Option<&mut Heading>, heading: &Heading)
     }
 }
 
+/// Gives headings their hierarchical number, eg `2.3.1`, in the order they appear.
+/// Skipped levels do not add a number: a `h4` right after a `h2` numbered `1` is `1.1`
+#[derive(Debug, Default)]
+pub struct HeadingNumbering {
+    /// The level and count of every heading the last one is nested in, including itself
+    counters: Vec<(u32, usize)>,
+}
+
+impl HeadingNumbering {
+    pub fn next(&mut self, level: u32) -> String {
+        let mut outermost = None;
+        while self.counters.last().is_some_and(|(l, _)| *l > level) {
+            outermost = self.counters.pop();
+        }
+        match self.counters.last_mut() {
+            Some((l, count)) if *l == level => *count += 1,
+            // A heading higher than all the previous ones carries on their numbering
+            None => self.counters.push((level, outermost.map_or(1, |(_, count)| count + 1))),
+            _ => self.counters.push((level, 1)),
+        }
+        let number: Vec<_> = self.counters.iter().map(|(_, count)| count.to_string()).collect();
+        number.join(".")
+    }
+}
+
 /// Converts the flat temp headings into a nested set of headings
 /// representing the hierarchy
 pub fn make_table_of_contents(headings: Vec<Heading>) -> Vec<Heading> {
@@ -134,6 +161,14 @@ mod tests {
         assert_eq!(toc[2].children[0].children[0].children.len(), 1);
     }
 
+    #[test]
+    fn can_number_headings() {
+        let mut numbering = HeadingNumbering::default();
+        let numbers: Vec<_> =
+            [2, 3, 3, 5, 2, 4, 3, 1].into_iter().map(|level| numbering.next(level)).collect();
+        assert_eq!(numbers, vec!["1", "1.1", "1.2", "1.2.1", "2", "2.1", "2.2", "3"]);
+    }
+
     #[test]
     fn can_make_messy_toc() {
         let input = vec![
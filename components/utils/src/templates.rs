@@ -1,8 +1,12 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use libs::tera::{Context, Tera};
+use libs::tera::{Context, Map, Tera, Value};
+use serde::Deserialize;
 
-use errors::{bail, Result};
+use errors::{bail, Context as ErrorContext, Result};
+
+use crate::fs::read_file;
 
 const DEFAULT_TPL: &str = include_str!("default_tpl.html");
 
@@ -21,16 +25,155 @@ pub enum ShortcodeFileType {
     Html,
 }
 
+/// The type of a shortcode argument, as declared in its schema
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShortcodeArgType {
+    String,
+    Integer,
+    /// Integers are accepted as well
+    Float,
+    Boolean,
+    Array,
+    #[default]
+    Any,
+}
+
+impl ShortcodeArgType {
+    pub fn matches(&self, value: &Value) -> bool {
+        match self {
+            ShortcodeArgType::String => value.is_string(),
+            ShortcodeArgType::Integer => value.is_i64() || value.is_u64(),
+            ShortcodeArgType::Float => value.is_number(),
+            ShortcodeArgType::Boolean => value.is_boolean(),
+            ShortcodeArgType::Array => value.is_array(),
+            ShortcodeArgType::Any => true,
+        }
+    }
+
+    fn describe(value: &Value) -> &'static str {
+        match value {
+            Value::String(_) => "a string",
+            Value::Number(n) if n.is_f64() => "a float",
+            Value::Number(_) => "an integer",
+            Value::Bool(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "a table",
+            Value::Null => "null",
+        }
+    }
+
+    fn expected(&self) -> &'static str {
+        match self {
+            ShortcodeArgType::String => "a string",
+            ShortcodeArgType::Integer => "an integer",
+            ShortcodeArgType::Float => "a float",
+            ShortcodeArgType::Boolean => "a boolean",
+            ShortcodeArgType::Array => "an array",
+            ShortcodeArgType::Any => "anything",
+        }
+    }
+}
+
+/// An argument declared in a shortcode schema
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShortcodeArg {
+    #[serde(rename = "type")]
+    pub arg_type: ShortcodeArgType,
+    pub required: bool,
+    /// The value to use when the argument is not given
+    pub default: Option<Value>,
+    /// Only there for documentation purposes
+    pub description: Option<String>,
+}
+
+/// The arguments a shortcode accepts, loaded from a `shortcodes/{name}.toml` file next to
+/// the template. Shortcodes without a schema accept any argument.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShortcodeSchema {
+    pub args: HashMap<String, ShortcodeArg>,
+    /// Whether arguments not declared in `args` are allowed
+    pub allow_unknown_args: bool,
+}
+
+impl ShortcodeSchema {
+    pub fn parse(content: &str) -> Result<ShortcodeSchema> {
+        let schema: ShortcodeSchema = libs::toml::from_str(content)?;
+        for (name, arg) in &schema.args {
+            match arg.default {
+                Some(_) if arg.required => {
+                    bail!("Argument `{}` cannot be both required and have a default value", name)
+                }
+                Some(ref default) if !arg.arg_type.matches(default) => bail!(
+                    "The default value of argument `{}` should be {} but is {}",
+                    name,
+                    arg.arg_type.expected(),
+                    ShortcodeArgType::describe(default)
+                ),
+                _ => (),
+            }
+        }
+        Ok(schema)
+    }
+
+    /// Checks the arguments of a call to the `shortcode` shortcode and fills in the default
+    /// values of the arguments that were not given
+    pub fn apply(&self, shortcode: &str, args: &mut Map<String, Value>) -> Result<()> {
+        if !self.allow_unknown_args {
+            for name in args.keys() {
+                if !self.args.contains_key(name) {
+                    let mut known: Vec<_> = self.args.keys().map(|k| format!("`{}`", k)).collect();
+                    known.sort();
+                    bail!(
+                        "Unknown argument `{}` for shortcode `{}`, the known arguments are: {}",
+                        name,
+                        shortcode,
+                        known.join(", ")
+                    );
+                }
+            }
+        }
+
+        let mut names: Vec<_> = self.args.keys().collect();
+        names.sort();
+        for name in names {
+            let arg = &self.args[name];
+            match args.get(name) {
+                Some(value) if !arg.arg_type.matches(value) => bail!(
+                    "Argument `{}` of shortcode `{}` should be {} but got {}",
+                    name,
+                    shortcode,
+                    arg.arg_type.expected(),
+                    ShortcodeArgType::describe(value)
+                ),
+                Some(_) => (),
+                None if arg.required => {
+                    bail!("Missing required argument `{}` for shortcode `{}`", name, shortcode)
+                }
+                None => {
+                    if let Some(ref default) = arg.default {
+                        args.insert(name.clone(), default.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ShortcodeDefinition {
     pub file_type: ShortcodeFileType,
     pub tera_name: String,
+    pub schema: Option<ShortcodeSchema>,
 }
 impl ShortcodeDefinition {
     pub fn new(file_type: ShortcodeFileType, tera_name: &str) -> ShortcodeDefinition {
         let tera_name = tera_name.to_string();
 
-        ShortcodeDefinition { file_type, tera_name }
+        ShortcodeDefinition { file_type, tera_name, schema: None }
     }
 }
 
@@ -57,6 +200,10 @@ pub fn get_shortcodes(tera: &Tera) -> HashMap<String, ShortcodeDefinition> {
     let mut shortcode_definitions = HashMap::new();
 
     for (identifier, template) in tera.templates.iter() {
+        // Shortcode schemas are picked up by the templates glob but aren't templates
+        if template.name.ends_with(".toml") {
+            continue;
+        }
         let (file_type, ext_len) = if template.name.ends_with(".md") {
             (ShortcodeFileType::Markdown, "md".len())
         } else {
@@ -87,6 +234,34 @@ pub fn get_shortcodes(tera: &Tera) -> HashMap<String, ShortcodeDefinition> {
     shortcode_definitions
 }
 
+/// Loads the `shortcodes/{name}.toml` schemas of the shortcodes from the given templates
+/// directories, in order of priority. The schema has to be next to the template that is used:
+/// a site overriding a shortcode of its theme doesn't get the schema of the theme.
+pub fn load_shortcode_schemas(
+    definitions: &mut HashMap<String, ShortcodeDefinition>,
+    templates_dirs: &[PathBuf],
+) -> Result<()> {
+    for (name, definition) in definitions.iter_mut() {
+        let Some(template) = Path::new(&definition.tera_name).file_name() else {
+            continue;
+        };
+        // The first directory with the template is where it comes from
+        let mut shortcodes_dirs = templates_dirs.iter().map(|d| d.join("shortcodes"));
+        let Some(dir) = shortcodes_dirs.find(|d| d.join(template).exists()) else {
+            continue;
+        };
+        let path = dir.join(format!("{}.toml", name));
+        if !path.exists() {
+            continue;
+        }
+        let schema = ShortcodeSchema::parse(&read_file(&path)?).with_context(|| {
+            format!("Failed to parse the schema of shortcode `{}` in {}", name, path.display())
+        })?;
+        definition.schema = Some(schema);
+    }
+    Ok(())
+}
+
 /// Renders the given template with the given context, but also ensures that, if the default file
 /// is not found, it will look up for the equivalent template for the current theme if there is one.
 /// Lastly, if it's a default template (index, section or page), it will just return an empty string
@@ -167,10 +342,12 @@ pub fn check_template_fallbacks<'a>(
 
 #[cfg(test)]
 mod tests {
-    use crate::templates::{check_template_fallbacks, get_shortcodes};
+    use crate::templates::{
+        check_template_fallbacks, get_shortcodes, load_shortcode_schemas, ShortcodeSchema,
+    };
 
     use super::rewrite_theme_paths;
-    use libs::tera::Tera;
+    use libs::tera::{Map, Tera, Value};
 
     #[test]
     fn can_rewrite_all_paths_of_theme() {
@@ -222,4 +399,92 @@ mod tests {
         let definitions = get_shortcodes(&tera);
         assert_eq!(definitions["youtube"].tera_name, "shortcodes/youtube.html");
     }
+
+    #[test]
+    fn can_load_shortcode_schemas() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let site_templates = tmp_dir.path().join("templates");
+        let theme_templates = tmp_dir.path().join("theme");
+        std::fs::create_dir_all(site_templates.join("shortcodes")).unwrap();
+        std::fs::create_dir_all(theme_templates.join("shortcodes")).unwrap();
+        for dir in [&site_templates, &theme_templates] {
+            std::fs::write(dir.join("shortcodes/figure.html"), "").unwrap();
+            std::fs::write(dir.join("shortcodes/quote.html"), "").unwrap();
+        }
+        std::fs::write(site_templates.join("shortcodes/figure.toml"), "[args.src]\n").unwrap();
+        std::fs::write(theme_templates.join("shortcodes/figure.toml"), "[args.alt]\n").unwrap();
+        // Overridden by the site without a schema
+        std::fs::write(theme_templates.join("shortcodes/quote.toml"), "[args.author]\n").unwrap();
+        std::fs::write(theme_templates.join("shortcodes/video.html"), "").unwrap();
+        std::fs::write(theme_templates.join("shortcodes/video.toml"), "[args.id]\n").unwrap();
+
+        let mut tera = Tera::default();
+        tera.add_raw_template("shortcodes/figure.html", "").unwrap();
+        tera.add_raw_template("shortcodes/figure.toml", "[args.src]").unwrap();
+        tera.add_raw_template("shortcodes/quote.html", "").unwrap();
+        tera.add_raw_template("shortcodes/video.html", "").unwrap();
+        tera.add_raw_template("shortcodes/other.html", "").unwrap();
+        let mut definitions = get_shortcodes(&tera);
+        assert_eq!(definitions.len(), 4);
+        load_shortcode_schemas(&mut definitions, &[site_templates, theme_templates]).unwrap();
+        let schema = definitions["figure"].schema.as_ref().unwrap();
+        assert!(schema.args.contains_key("src"));
+        assert!(!schema.args.contains_key("alt"));
+        assert!(definitions["quote"].schema.is_none());
+        assert!(definitions["video"].schema.as_ref().unwrap().args.contains_key("id"));
+        assert!(definitions["other"].schema.is_none());
+    }
+
+    #[test]
+    fn can_validate_shortcode_args() {
+        let schema = ShortcodeSchema::parse(
+            r#"
+[args.src]
+type = "string"
+required = true
+
+[args.width]
+type = "integer"
+default = 800
+
+[args.ratio]
+type = "float"
+"#,
+        )
+        .unwrap();
+
+        let mut args = Map::new();
+        args.insert("src".to_string(), Value::from("a.jpg"));
+        args.insert("ratio".to_string(), Value::from(2));
+        schema.apply("figure", &mut args).unwrap();
+        assert_eq!(args["width"], Value::from(800));
+
+        let mut args = Map::new();
+        let err = schema.apply("figure", &mut args).unwrap_err();
+        assert_eq!(err.to_string(), "Missing required argument `src` for shortcode `figure`");
+
+        args.insert("src".to_string(), Value::from("a.jpg"));
+        args.insert("widht".to_string(), Value::from(800));
+        let err = schema.apply("figure", &mut args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown argument `widht` for shortcode `figure`, the known arguments are: `ratio`, `src`, `width`"
+        );
+
+        args.remove("widht");
+        args.insert("width".to_string(), Value::from("800px"));
+        let err = schema.apply("figure", &mut args).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Argument `width` of shortcode `figure` should be an integer but got a string"
+        );
+    }
+
+    #[test]
+    fn errors_on_invalid_shortcode_schemas() {
+        assert!(ShortcodeSchema::parse("[args.a]\nrequired = true\ndefault = 1").is_err());
+        assert!(ShortcodeSchema::parse("[args.a]\ntype = \"string\"\ndefault = 1").is_err());
+        assert!(ShortcodeSchema::parse("[args.a]\ntype = \"number\"").is_err());
+        assert!(ShortcodeSchema::parse("[args.a]\nrequird = true").is_err());
+    }
 }
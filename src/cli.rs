@@ -1,7 +1,7 @@
 use std::net::IpAddr;
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_complete::Shell;
 
 #[derive(Parser)]
@@ -53,6 +53,10 @@ pub enum Command {
         /// Minify generated HTML files
         #[clap(long)]
         minify: bool,
+
+        /// Only use the cached responses of remote `load_data` requests
+        #[clap(long)]
+        offline: bool,
     },
 
     /// Serve the site. Rebuild and reload on change automatically
@@ -101,6 +105,10 @@ pub enum Command {
         /// Extra path to watch for changes, relative to the project root.
         #[clap(long)]
         extra_watch_path: Vec<String>,
+
+        /// Only use the cached responses of remote `load_data` requests
+        #[clap(long)]
+        offline: bool,
     },
 
     /// Try to build the project without rendering it. Checks links
@@ -111,6 +119,26 @@ pub enum Command {
         /// Skip external links
         #[clap(long)]
         skip_external_links: bool,
+
+        /// Only use the cached responses of remote `load_data` requests
+        #[clap(long)]
+        offline: bool,
+
+        /// Report the missing and outdated translations of a multilingual site
+        #[clap(long)]
+        translations: bool,
+
+        /// Also write the translation report as JSON to the given file
+        #[clap(long)]
+        translations_json: Option<PathBuf>,
+
+        /// Format of the report of the link problems found
+        #[clap(long, value_enum, default_value = "human")]
+        format: CheckFormat,
+
+        /// Write the report to the given file instead of stdout, ignored for the human format
+        #[clap(short = 'o', long)]
+        output: Option<PathBuf>,
     },
 
     /// Generate shell completion
@@ -120,3 +148,12 @@ pub enum Command {
         shell: Shell,
     },
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CheckFormat {
+    /// Log the problems found
+    Human,
+    Json,
+    Sarif,
+    Junit,
+}
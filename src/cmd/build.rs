@@ -5,6 +5,7 @@ use site::Site;
 
 use crate::messages;
 
+#[allow(clippy::too_many_arguments)]
 pub fn build(
     root_dir: &Path,
     config_file: &Path,
@@ -13,6 +14,7 @@ pub fn build(
     force: bool,
     include_drafts: bool,
     minify: bool,
+    offline: bool,
 ) -> Result<()> {
     let mut site = Site::new(root_dir, config_file)?;
     if let Some(output_dir) = output_dir {
@@ -34,6 +36,9 @@ pub fn build(
     if minify {
         site.minify();
     }
+    if offline {
+        site.offline();
+    }
     site.load()?;
     messages::notify_site_size(&site);
     messages::warn_about_ignored_pages(&site);
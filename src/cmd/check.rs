@@ -1,10 +1,16 @@
 use std::path::{Path, PathBuf};
 
-use errors::Result;
+use errors::{Context, Result};
+use libs::serde_json;
+use site::check_report;
+use site::translations::build_translation_report;
 use site::Site;
+use utils::fs::create_file;
 
+use crate::cli::CheckFormat;
 use crate::messages;
 
+#[allow(clippy::too_many_arguments)]
 pub fn check(
     root_dir: &Path,
     config_file: &Path,
@@ -12,6 +18,11 @@ pub fn check(
     base_url: Option<&str>,
     include_drafts: bool,
     skip_external_links: bool,
+    offline: bool,
+    report_translations: bool,
+    translations_json: Option<&Path>,
+    format: CheckFormat,
+    output: Option<&Path>,
 ) -> Result<()> {
     let bp = base_path.map(PathBuf::from).unwrap_or_else(|| PathBuf::from(root_dir));
     let mut site = Site::new(bp, config_file)?;
@@ -26,8 +37,35 @@ pub fn check(
     if skip_external_links {
         site.skip_external_links_check();
     }
-    site.load()?;
+    if offline {
+        site.offline();
+    }
+    // The report is written even if loading failed because of the link checker
+    let res = site.load();
+    let report = match format {
+        CheckFormat::Human => None,
+        CheckFormat::Json => Some(check_report::to_json(&site.link_problems)),
+        CheckFormat::Sarif => {
+            Some(check_report::to_sarif(&site.link_problems, env!("CARGO_PKG_VERSION")))
+        }
+        CheckFormat::Junit => Some(check_report::to_junit(&site.link_problems)),
+    };
+    if let Some(report) = report {
+        match output {
+            Some(path) => create_file(path, report).context("Failed to write the check report")?,
+            None => println!("{}", report),
+        }
+    }
+    res?;
     messages::check_site_summary(&site);
     messages::warn_about_ignored_pages(&site);
+    if report_translations && site.config.is_multilingual() {
+        let report = build_translation_report(&site);
+        messages::report_translations(&report);
+        if let Some(path) = translations_json {
+            let json = serde_json::to_string_pretty(&report)?;
+            create_file(path, json).context("Failed to write the translation report")?;
+        }
+    }
     Ok(())
 }
@@ -22,6 +22,7 @@
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
 use std::cell::Cell;
+use std::collections::HashSet;
 use std::future::IntoFuture;
 use std::net::{IpAddr, SocketAddr, TcpListener};
 use std::path::{Path, PathBuf, MAIN_SEPARATOR};
@@ -42,7 +43,8 @@ use time::{OffsetDateTime, UtcOffset};
 use libs::percent_encoding;
 use libs::relative_path::{RelativePath, RelativePathBuf};
 use libs::serde_json;
-use notify_debouncer_full::{new_debouncer, notify::RecursiveMode};
+use notify_debouncer_full::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, Debouncer, RecommendedCache};
 use ws::{Message, Sender, WebSocket};
 
 use errors::{anyhow, Context, Error, Result};
@@ -296,6 +298,49 @@ fn not_found() -> Response<Body> {
         .expect("Could not build Not Found response")
 }
 
+/// Watches the files read by the content, eg included in code blocks, which are not in one of
+/// the directories already watched
+fn watch_content_dependencies(
+    site: &Site,
+    watched_dirs: &[PathBuf],
+    watched_files: &mut HashSet<PathBuf>,
+    debouncer: &mut Debouncer<RecommendedWatcher, RecommendedCache>,
+) {
+    for path in site.content_dependencies() {
+        if watched_dirs.iter().any(|dir| path.starts_with(dir)) || watched_files.contains(&path) {
+            continue;
+        }
+        match debouncer.watch(&path, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                watched_files.insert(path);
+            }
+            Err(e) => {
+                console::warn(&format!("Can't watch `{}` for changes: {}", path.display(), e))
+            }
+        }
+    }
+}
+
+/// Renders again the pages and sections having read the file at `path`.
+/// Errors if there are none so the caller can rebuild the whole site instead.
+fn render_dependents(site: &mut Site, path: &Path) -> Result<()> {
+    let dependents = site.find_dependents(path);
+    if dependents.is_empty() {
+        return Err(anyhow!("No content depends on {}", path.display()));
+    }
+
+    for dependent in dependents {
+        let is_section =
+            dependent.file_name().is_some_and(|f| f.to_string_lossy().starts_with("_index."));
+        if is_section {
+            site.add_and_render_section(&dependent)?;
+        } else {
+            site.add_and_render_page(&dependent)?;
+        }
+    }
+    Ok(())
+}
+
 fn rebuild_done_handling(broadcaster: &Sender, res: Result<()>, reload_path: &str) {
     match res {
         Ok(_) => {
@@ -367,6 +412,7 @@ fn create_new_site(
     base_url: Option<&str>,
     config_file: &Path,
     include_drafts: bool,
+    offline: bool,
     store_html: bool,
     mut no_port_append: bool,
     ws_port: Option<u16>,
@@ -405,6 +451,9 @@ fn create_new_site(
     if include_drafts {
         site.include_drafts();
     }
+    if offline {
+        site.offline();
+    }
     site.load()?;
     if let Some(p) = ws_port {
         site.enable_live_reload_with_port(p);
@@ -428,6 +477,7 @@ pub fn serve(
     config_file: &Path,
     open: bool,
     include_drafts: bool,
+    offline: bool,
     store_html: bool,
     fast_rebuild: bool,
     no_port_append: bool,
@@ -444,6 +494,7 @@ pub fn serve(
         base_url,
         config_file,
         include_drafts,
+        offline,
         store_html,
         no_port_append,
         None,
@@ -475,6 +526,7 @@ pub fn serve(
         ("sass", WatchMode::Condition(site.config.compile_sass), RecursiveMode::Recursive),
         ("static", WatchMode::Optional, RecursiveMode::Recursive),
         ("templates", WatchMode::Optional, RecursiveMode::Recursive),
+        ("i18n", WatchMode::Optional, RecursiveMode::Recursive),
         ("themes", WatchMode::Condition(site.config.theme.is_some()), RecursiveMode::Recursive),
     ];
     watch_this.extend(
@@ -482,6 +534,12 @@ pub fn serve(
             .iter()
             .map(|path| (path.as_str(), WatchMode::Required, RecursiveMode::Recursive)),
     );
+    let include_roots = site.config.markdown.include_roots.clone();
+    watch_this.extend(
+        include_roots
+            .iter()
+            .map(|path| (path.as_str(), WatchMode::Optional, RecursiveMode::Recursive)),
+    );
 
     // Setup watchers
     let (tx, rx) = channel();
@@ -507,6 +565,11 @@ pub fn serve(
             watchers.push(entry.to_string());
         }
     }
+    // Files read by the content outside of the watched directories are watched one by one
+    let watched_dirs: Vec<PathBuf> =
+        watchers.iter().filter(|w| w.as_str() != root_dir_str).map(|w| root_dir.join(w)).collect();
+    let mut watched_files = HashSet::new();
+    watch_content_dependencies(&site, &watched_dirs, &mut watched_files, &mut debouncer);
 
     let ws_port = site.live_reload;
     let ws_address = format!("{}:{}", interface, ws_port.unwrap());
@@ -675,6 +738,7 @@ pub fn serve(
         base_url,
         config_file,
         include_drafts,
+        offline,
         store_html,
         no_port_append,
         ws_port,
@@ -703,6 +767,7 @@ pub fn serve(
                     root_dir,
                     &config_path,
                     &site.config.ignored_content_globset,
+                    &site.content_dependencies(),
                 );
                 if changes.is_empty() {
                     continue;
@@ -743,8 +808,8 @@ pub fn serve(
                                             site.add_and_render_page(full_path)
                                         } else {
                                             // an asset changed? a folder renamed?
-                                            // should we make it smarter so it doesn't reload the whole site?
-                                            Err(anyhow!("dummy"))
+                                            // only the content including it is rendered again
+                                            render_dependents(&mut site, full_path)
                                         };
 
                                         if res.is_err() {
@@ -828,18 +893,33 @@ pub fn serve(
                                 .map(|p| p.display().to_string())
                                 .collect::<Vec<String>>()
                                 .join(", ");
-                            console::info(&format!(
-                                "-> {combined_paths} changed. Recreating whole site."
-                            ));
+                            console::info(&format!("-> {combined_paths} changed."));
 
-                            // We can't know exactly what to update when a user provides the path.
-                            if let Some(s) = recreate_site() {
-                                site = s;
+                            // Only the content including the files needs to be rendered again,
+                            // otherwise we can't know exactly what to update
+                            let res = if fast_rebuild {
+                                full_paths.iter().try_for_each(|p| render_dependents(&mut site, p))
+                            } else {
+                                Err(anyhow!("dummy"))
+                            };
+                            if res.is_ok() {
+                                rebuild_done_handling(&broadcaster, res, &combined_paths);
+                            } else {
+                                console::info("Recreating whole site.");
+                                if let Some(s) = recreate_site() {
+                                    site = s;
+                                }
                             }
                         }
                     };
                     messages::report_elapsed_time(start);
                 }
+                watch_content_dependencies(
+                    &site,
+                    &watched_dirs,
+                    &mut watched_files,
+                    &mut debouncer,
+                );
             }
             Ok(Err(e)) => console::error(&format!("File system event errors: {:?}", e)),
             Err(e) => console::error(&format!("File system event receiver errors: {:?}", e)),
@@ -921,6 +1001,7 @@ mod tests {
             &config_file,
             include_drafts,
             false,
+            false,
             no_port_append,
             ws_port,
         )
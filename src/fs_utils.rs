@@ -5,6 +5,7 @@ use libs::ahash::HashMap;
 use libs::globset::GlobSet;
 use notify_debouncer_full::notify::event::*;
 use notify_debouncer_full::DebouncedEvent;
+use std::collections::HashSet;
 use std::fs::read_dir;
 use std::path::{Path, PathBuf};
 use utils::fs::is_temp_file;
@@ -65,6 +66,7 @@ pub fn filter_events(
     root_dir: &Path,
     config_path: &Path,
     ignored_content_globset: &Option<GlobSet>,
+    content_dependencies: &HashSet<PathBuf>,
 ) -> HashMap<ChangeKind, Vec<MeaningfulEvent>> {
     // Arrange events from oldest to newest.
     events.sort_by(|e1, e2| e1.time.cmp(&e2.time));
@@ -109,9 +111,14 @@ pub fn filter_events(
             continue;
         }
 
-        // Ignore ordinary files peer to config.toml. This assumes all other files we care
-        // about are nested more deeply than config.toml or are directories peer to config.toml.
-        if path != config_path && path.is_file() && path.parent() == config_path.parent() {
+        // Ignore ordinary files peer to config.toml, unless the content includes them. This
+        // assumes all other files we care about are nested more deeply than config.toml or are
+        // directories peer to config.toml.
+        if path != config_path
+            && path.is_file()
+            && path.parent() == config_path.parent()
+            && !content_dependencies.contains(&path)
+        {
             continue;
         }
 
@@ -171,10 +178,13 @@ fn detect_change_kind(pwd: &Path, path: &Path, config_path: &Path) -> (ChangeKin
 #[cfg(test)]
 mod tests {
     use notify_debouncer_full::notify::event::*;
+    use notify_debouncer_full::DebouncedEvent;
+    use std::collections::HashSet;
     use std::path::{Path, PathBuf};
+    use std::time::Instant;
 
     use super::{
-        detect_change_kind, get_relevant_event_kind, is_temp_file, ChangeKind,
+        detect_change_kind, filter_events, get_relevant_event_kind, is_temp_file, ChangeKind,
         SimpleFileSystemEventKind,
     };
 
@@ -319,4 +329,25 @@ mod tests {
         let config_filename = Path::new("config.toml");
         assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
     }
+
+    #[test]
+    fn keeps_files_peer_to_config_read_by_the_content() {
+        let root_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let config_path = root_dir.join("config.toml");
+        let path = root_dir.join("Cargo.toml");
+        let events = || {
+            let event = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.clone());
+            vec![DebouncedEvent::new(event, Instant::now())]
+        };
+
+        let changes = filter_events(events(), root_dir, &config_path, &None, &HashSet::new());
+        assert!(changes.is_empty());
+
+        let dependencies = HashSet::from([path.clone()]);
+        let changes = filter_events(events(), root_dir, &config_path, &None, &dependencies);
+        assert_eq!(
+            changes[&ChangeKind::ExtraPath],
+            vec![(PathBuf::from("/Cargo.toml"), path, SimpleFileSystemEventKind::Modify)]
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use cli::{Cli, Command};
+use cli::{CheckFormat, Cli, Command};
 use errors::anyhow;
 use utils::net::{get_available_port, port_is_available};
 
@@ -57,7 +57,7 @@ fn main() {
                 std::process::exit(1);
             }
         }
-        Command::Build { base_url, output_dir, force, drafts, minify } => {
+        Command::Build { base_url, output_dir, force, drafts, minify, offline } => {
             console::info("Building site...");
             let start = Instant::now();
             let (root_dir, config_file) = get_config_file_path(&cli_dir, &cli.config);
@@ -69,6 +69,7 @@ fn main() {
                 force,
                 drafts,
                 minify,
+                offline,
             ) {
                 Ok(()) => messages::report_elapsed_time(start),
                 Err(e) => {
@@ -89,6 +90,7 @@ fn main() {
             fast,
             no_port_append,
             extra_watch_path,
+            offline,
         } => {
             if port != 1111 && !port_is_available(interface, port) {
                 console::error("The requested port is not available");
@@ -114,6 +116,7 @@ fn main() {
                 &config_file,
                 open,
                 drafts,
+                offline,
                 store_html,
                 fast,
                 no_port_append,
@@ -124,11 +127,35 @@ fn main() {
                 std::process::exit(1);
             }
         }
-        Command::Check { drafts, skip_external_links } => {
+        Command::Check {
+            drafts,
+            skip_external_links,
+            offline,
+            translations,
+            translations_json,
+            format,
+            output,
+        } => {
+            // Keep stdout for the report so it can be piped to another program
+            if format != CheckFormat::Human && output.is_none() {
+                console::use_stderr();
+            }
             console::info("Checking site...");
             let start = Instant::now();
             let (root_dir, config_file) = get_config_file_path(&cli_dir, &cli.config);
-            match cmd::check(&root_dir, &config_file, None, None, drafts, skip_external_links) {
+            match cmd::check(
+                &root_dir,
+                &config_file,
+                None,
+                None,
+                drafts,
+                skip_external_links,
+                offline,
+                translations || translations_json.is_some(),
+                translations_json.as_deref(),
+                format,
+                output.as_deref(),
+            ) {
                 Ok(()) => messages::report_elapsed_time(start),
                 Err(e) => {
                     messages::unravel_errors("Failed to check the site", &e);
@@ -3,35 +3,64 @@ use std::convert::TryInto;
 use std::time::Instant;
 
 use errors::Error;
+use site::translations::TranslationReport;
 use site::Site;
 
 /// Display in the console the number of pages/sections in the site
 pub fn notify_site_size(site: &Site) {
     let library = site.library.read().unwrap();
-    println!(
+    console::plain(&format!(
         "-> Creating {} pages ({} orphan) and {} sections",
         library.pages.len(),
         library.get_all_orphan_pages().len(),
         library.sections.len() - 1, // -1 since we do not count the index as a section there
-    );
+    ));
 }
 
 /// Display in the console only the number of pages/sections in the site
 pub fn check_site_summary(site: &Site) {
     let library = site.library.read().unwrap();
     let orphans = library.get_all_orphan_pages();
-    println!(
+    console::plain(&format!(
         "-> Site content: {} pages ({} orphan), {} sections",
         library.pages.len(),
         orphans.len(),
         library.sections.len() - 1, // -1 since we do not count the index as a section there
-    );
+    ));
 
     for orphan in orphans {
         console::warn(&format!("Orphan page found: {}", orphan.path));
     }
 }
 
+/// Display what is missing from each language of a multilingual site
+pub fn report_translations(report: &TranslationReport) {
+    for (lang, language) in &report.languages {
+        console::plain(&format!(
+            "-> Translations ({}): {} missing, {} outdated, {} missing keys",
+            lang,
+            language.missing_content.len(),
+            language.outdated_content.len(),
+            language.missing_keys.len(),
+        ));
+        if language.is_complete() {
+            continue;
+        }
+        for path in &language.missing_content {
+            console::warn(&format!("Missing {} translation: {}", lang, path));
+        }
+        for outdated in &language.outdated_content {
+            console::warn(&format!(
+                "Outdated {} translation: {} is older than {}",
+                lang, outdated.path, outdated.source
+            ));
+        }
+        for key in &language.missing_keys {
+            console::warn(&format!("Missing {} translation key: {}", lang, key));
+        }
+    }
+}
+
 /// Display a warning in the console if there are ignored pages in the site
 pub fn warn_about_ignored_pages(site: &Site) {
     let library = site.library.read().unwrap();
@@ -0,0 +1,5 @@
+read-more = Read more
+reading-time = { $count ->
+    [one] { $count } minute
+   *[other] { $count } minutes
+}
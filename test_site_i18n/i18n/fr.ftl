@@ -0,0 +1,4 @@
+reading-time = { $count ->
+    [one] { $count } minute
+   *[other] { $count } minutes
+}
@@ -0,0 +1,19 @@
+use std::path::Path;
+use std::process::Command;
+
+use libs::serde_json::{self, Value};
+
+#[test]
+fn check_only_writes_the_report_to_stdout() {
+    let output = Command::new(env!("CARGO_BIN_EXE_zola"))
+        .args(["check", "--format", "json", "--skip-external-links"])
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("test_site"))
+        .output()
+        .unwrap();
+
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report["problems"].is_array());
+    // The progress messages are still shown
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Checking site..."));
+}